@@ -4,13 +4,12 @@ use cosmwasm_std::{
 use cosmwasm_std::{to_binary, Deps, Env, Response};
 use shade_oracles::core::{Contract, RawContract};
 use shade_oracles::create_attr_action;
-use shade_oracles::interfaces::common::OraclePrice;
+use shade_oracles::interfaces::common::{DependenciesResponse, OraclePrice};
 use shade_oracles::interfaces::index::{error::*, msg::*, *};
-use shade_oracles::querier::{query_prices, require_admin};
+use shade_oracles::querier::{query_prices, query_prices_lenient, require_admin};
 use shade_oracles::{
     common::status::GlobalStatus,
     core::{pad_handle_result, pad_query_result},
-    interfaces::providers::ReferenceData,
     ssp::ItemStorage,
     BLOCK_SIZE,
 };
@@ -63,6 +62,7 @@ pub fn execute(
 }
 
 /// Callable by anyone. Computes the peg value, freezing it if the oracle prices are stale.
+/// A frozen peg is unfrozen once the prices meet the auto unfreeze policy, if there is one.
 pub fn try_compute_index(
     deps: DepsMut,
     env: Env,
@@ -72,13 +72,15 @@ pub fn try_compute_index(
     IndexOracle::require_can_run(deps.storage, true, false, false)?;
     let router = oracle.config.router.clone();
     let symbols = oracle.asset_symbols.clone();
-    let prices = fetch_prices(deps.as_ref(), &router, &symbols)?;
+    let prices = fetch_prices(deps.as_ref(), &router, &symbols, 0)?;
+    let unfrozen = oracle.try_auto_unfreeze(prices.as_ref(), &env.block.time)?;
     oracle.compute_peg(prices.as_ref(), &env.block.time)?;
     oracle.save(deps.storage)?;
     Ok(Response::new().add_attributes(vec![
         attr_action!("compute_index"),
         attr("new_target", oracle.peg.value.to_string()),
         attr("is_frozen", oracle.peg.frozen.to_string()),
+        attr("auto_unfrozen", unfrozen.to_string()),
     ]))
 }
 
@@ -100,6 +102,38 @@ pub fn try_unfreeze(
     ]))
 }
 
+pub fn try_freeze(deps: DepsMut, env: Env, mut oracle: IndexOracle) -> IndexOracleResult<Response> {
+    oracle.freeze(FreezeReason::Manual, env.block.time.seconds());
+    oracle.save(deps.storage)?;
+    Ok(Response::new().add_attributes(vec![attr_action!("freeze")]))
+}
+
+pub fn try_set_auto_unfreeze(
+    deps: DepsMut,
+    mut oracle: IndexOracle,
+    policy: Option<AutoUnfreeze>,
+) -> IndexOracleResult<Response> {
+    if let Some(policy) = &policy {
+        policy.validate()?;
+    }
+    oracle.config.auto_unfreeze = policy;
+    oracle.config.save(deps.storage)?;
+    Ok(Response::new().add_attributes(vec![attr_action!("set_auto_unfreeze")]))
+}
+
+pub fn try_set_stale_components(
+    deps: DepsMut,
+    mut oracle: IndexOracle,
+    policy: Option<StaleComponents>,
+) -> IndexOracleResult<Response> {
+    if let Some(policy) = &policy {
+        policy.validate()?;
+    }
+    oracle.config.stale_components = policy;
+    oracle.config.save(deps.storage)?;
+    Ok(Response::new().add_attributes(vec![attr_action!("set_stale_components")]))
+}
+
 pub fn try_update_config(
     deps: DepsMut,
     _env: Env,
@@ -133,6 +167,21 @@ pub fn try_update_target(
     mut oracle: IndexOracle,
     new_target: Uint256,
 ) -> IndexOracleResult<Response> {
+    update_target(deps.as_ref(), &env, &mut oracle, new_target)?;
+    oracle.save(deps.storage)?;
+    Ok(Response::new().add_attributes(vec![
+        attr_action!("update_target"),
+        attr("new_target", new_target),
+    ]))
+}
+
+/// Sets the target and fixes the weights of the basket to it at the current prices.
+pub fn update_target(
+    deps: Deps,
+    env: &Env,
+    oracle: &mut IndexOracle,
+    new_target: Uint256,
+) -> IndexOracleResult<()> {
     if oracle.peg.frozen {
         return Err(IndexOracleError::FrozenPeg);
     }
@@ -145,11 +194,52 @@ pub fn try_update_target(
     oracle.peg.value = new_target.into();
     oracle.peg.last_value = new_target.into();
     oracle.peg.last_updated = env.block.time.seconds();
+    oracle.peg.feeds_updated = IndexOracle::oldest_update(&prices, env.block.time.seconds());
+    oracle.ramp = None;
     oracle.compute_fixed_weights(prices.as_slice())?;
+    Ok(())
+}
+
+pub fn try_ramp_target(
+    deps: DepsMut,
+    env: Env,
+    mut oracle: IndexOracle,
+    new_target: Uint256,
+    duration: Uint64,
+) -> IndexOracleResult<Response> {
+    if oracle.peg.frozen {
+        return Err(IndexOracleError::FrozenPeg);
+    }
+    if duration.is_zero() {
+        return Err(IndexOracleError::InvalidRampDuration);
+    }
+    let now = env.block.time.seconds();
+    oracle.apply_ramp(now)?;
+    oracle.ramp = Some(Ramp {
+        from: oracle.peg.target.into(),
+        to: new_target,
+        start: now,
+        duration: duration.u64(),
+    });
     oracle.save(deps.storage)?;
     Ok(Response::new().add_attributes(vec![
-        attr_action!("update_target"),
+        attr_action!("ramp_target"),
         attr("new_target", new_target),
+        attr("duration", duration),
+    ]))
+}
+
+pub fn try_cancel_ramp(
+    deps: DepsMut,
+    env: Env,
+    mut oracle: IndexOracle,
+) -> IndexOracleResult<Response> {
+    oracle.apply_ramp(env.block.time.seconds())?;
+    oracle.ramp = None;
+    oracle.save(deps.storage)?;
+    Ok(Response::new().add_attributes(vec![
+        attr_action!("cancel_ramp"),
+        attr("target", oracle.peg.target.to_string()),
     ]))
 }
 
@@ -188,7 +278,17 @@ pub fn try_admin_msg(
                 AdminMsg::UpdateTarget(new_target) => {
                     try_update_target(deps, env, oracle, new_target)
                 }
+                AdminMsg::RampTarget {
+                    new_target,
+                    duration,
+                } => try_ramp_target(deps, env, oracle, new_target, duration),
+                AdminMsg::CancelRamp {} => try_cancel_ramp(deps, env, oracle),
                 AdminMsg::UnfreezePeg {} => try_unfreeze(deps, env, oracle),
+                AdminMsg::FreezePeg {} => try_freeze(deps, env, oracle),
+                AdminMsg::SetAutoUnfreeze(policy) => try_set_auto_unfreeze(deps, oracle, policy),
+                AdminMsg::SetStaleComponents(policy) => {
+                    try_set_stale_components(deps, oracle, policy)
+                }
                 _ => panic!("code should never come here"),
             }
         }
@@ -196,18 +296,24 @@ pub fn try_admin_msg(
 }
 
 /// Used in cases where we want to tolerate Band being down.
+///
+/// Only the prices the router could return are kept, the other assets are valued at their last known price.
+/// The depth is the routing depth of the query being answered, passed on to the router.
 pub fn fetch_prices<'a>(
     deps: Deps,
     router: &Contract,
     symbols: impl IntoIterator<Item = &'a String>,
+    depth: u8,
 ) -> StdResult<Option<Vec<OraclePrice>>> {
     let symbols = symbols
         .into_iter()
         .map(|f| f.to_string())
         .collect::<Vec<String>>();
     let symbols_slice = symbols.as_slice();
-    match query_prices(router, &deps.querier, symbols_slice) {
-        Ok(prices) => Ok(Some(prices)),
+    match query_prices_lenient(router, &deps.querier, symbols_slice, depth) {
+        Ok(prices) => Ok(Some(
+            prices.into_iter().filter_map(|price| price.price).collect(),
+        )),
         Err(_) => Ok(None),
     }
 }
@@ -215,9 +321,22 @@ pub fn fetch_prices<'a>(
 pub fn try_mod_basket(
     deps: DepsMut,
     env: Env,
-    mod_basket: impl IntoIterator<Item = (String, Decimal256)>,
+    basket: impl IntoIterator<Item = (String, Decimal256)>,
     mut oracle: IndexOracle,
 ) -> IndexOracleResult<Response> {
+    mod_basket(deps.as_ref(), &env, basket, &mut oracle)?;
+    oracle.save(deps.storage)?;
+
+    Ok(Response::new().add_attributes(vec![attr_action!("mod_basket")]))
+}
+
+/// Updates the basket and fixes its weights to the current peg value.
+pub fn mod_basket(
+    deps: Deps,
+    env: &Env,
+    mod_basket: impl IntoIterator<Item = (String, Decimal256)>,
+    oracle: &mut IndexOracle,
+) -> IndexOracleResult<()> {
     if oracle.peg.frozen {
         return Err(IndexOracleError::FrozenPeg);
     }
@@ -230,15 +349,80 @@ pub fn try_mod_basket(
 
     let new_prices = new_prices.as_slice();
     oracle.compute_fixed_weights(new_prices)?;
-    oracle.save(deps.storage)?;
+    Ok(())
+}
 
-    Ok(Response::new().add_attributes(vec![attr_action!("mod_basket")]))
+pub fn get_basket(oracle: &IndexOracle) -> Vec<(String, Decimal256, Decimal256)> {
+    oracle
+        .basket
+        .iter()
+        .map(|(k, v)| (k.clone(), v.initial.into(), v.fixed.into()))
+        .collect()
+}
+
+fn require_symbol(oracle: &IndexOracle, key: &String) -> StdResult<()> {
+    if key.ne(&oracle.config.symbol) {
+        return Err(IndexOracleError::UnsupportedSymbol {
+            symbol: key.clone(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Prices the index once for every key, which must all be its symbol.
+pub fn get_prices(
+    deps: Deps,
+    env: &Env,
+    oracle: &mut IndexOracle,
+    keys: Vec<String>,
+    depth: u8,
+) -> StdResult<Vec<OraclePrice>> {
+    for key in &keys {
+        require_symbol(oracle, key)?;
+    }
+    let prices = fetch_prices(
+        deps,
+        &oracle.config.router,
+        oracle.asset_symbols.as_slice(),
+        depth,
+    )?;
+    let price = oracle.compute_peg(prices.as_ref(), &env.block.time)?;
+    Ok(vec![price; keys.capacity()])
+}
+
+/// The index is priced with every asset of its basket.
+pub fn get_dependencies(
+    oracle: &IndexOracle,
+    keys: Vec<String>,
+) -> StdResult<DependenciesResponse> {
+    let mut dependencies = vec![];
+    for key in keys {
+        require_symbol(oracle, &key)?;
+        dependencies.push((key, oracle.asset_symbols.clone()));
+    }
+    Ok(dependencies)
+}
+
+/// Result of a change made to the oracle in memory.
+pub fn simulation(oracle: &IndexOracle, result: IndexOracleResult<()>) -> SimulationResponse {
+    match result {
+        Ok(()) => SimulationResponse {
+            basket: get_basket(oracle),
+            peg: Some(oracle.peg.value.into()),
+            error: None,
+        },
+        Err(err) => SimulationResponse {
+            basket: vec![],
+            peg: None,
+            error: Some(err.to_string()),
+        },
+    }
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<QueryResponse> {
     let mut oracle = IndexOracle::load(deps.storage)?;
-    let now = env.block.time.seconds();
 
     let binary = match msg {
         QueryMsg::GetPrice { key } => {
@@ -246,54 +430,41 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<QueryResponse> {
             if key != oracle.config.symbol {
                 return Err(IndexOracleError::UnsupportedSymbol { symbol: key }.into());
             }
-            let prices =
-                fetch_prices(deps, &oracle.config.router, oracle.asset_symbols.as_slice())?;
-            oracle.compute_peg(prices.as_ref(), &env.block.time)?;
-            let price = OraclePrice::new(
-                oracle.config.symbol,
-                ReferenceData {
-                    rate: oracle.peg.value.into(),
-                    last_updated_base: now,
-                    last_updated_quote: now,
-                },
-            );
+            let prices = fetch_prices(
+                deps,
+                &oracle.config.router,
+                oracle.asset_symbols.as_slice(),
+                0,
+            )?;
+            let price = oracle.compute_peg(prices.as_ref(), &env.block.time)?;
             to_binary(&price)
         }
         QueryMsg::GetPrices { keys } => {
             IndexOracle::require_can_run(deps.storage, true, false, false)?;
-            for key in &keys {
-                if key.ne(&oracle.config.symbol) {
-                    return Err(IndexOracleError::UnsupportedSymbol {
-                        symbol: key.clone(),
-                    }
-                    .into());
-                }
-            }
-            let prices =
-                fetch_prices(deps, &oracle.config.router, oracle.asset_symbols.as_slice())?;
-            oracle.compute_peg(prices.as_ref(), &env.block.time)?;
-            let price = OraclePrice::new(
-                oracle.config.symbol,
-                ReferenceData {
-                    rate: oracle.peg.value.into(),
-                    last_updated_base: now,
-                    last_updated_quote: now,
-                },
-            );
-            let prices = vec![price; keys.capacity()];
-            to_binary(&prices)
+            to_binary(&get_prices(deps, &env, &mut oracle, keys, 0)?)
+        }
+        QueryMsg::GetPricesAtDepth { keys, depth } => {
+            IndexOracle::require_can_run(deps.storage, true, false, false)?;
+            to_binary(&get_prices(deps, &env, &mut oracle, keys, depth)?)
         }
+        QueryMsg::GetDependencies { keys } => to_binary(&get_dependencies(&oracle, keys)?),
         QueryMsg::GetIndexData {} => {
             IndexOracle::require_can_run(deps.storage, true, true, false)?;
-            let prices =
-                fetch_prices(deps, &oracle.config.router, oracle.asset_symbols.as_slice())?;
+            let prices = fetch_prices(
+                deps,
+                &oracle.config.router,
+                oracle.asset_symbols.as_slice(),
+                0,
+            )?;
             oracle.compute_peg(prices.as_ref(), &env.block.time)?;
+            let now = env.block.time.seconds();
             let basket = oracle
                 .basket
                 .iter()
                 .map(|(k, v)| IndexAsset {
                     symbol: k.into(),
                     weight: v.clone().into(),
+                    health: oracle.health(k, prices.as_ref(), now),
                 })
                 .collect::<Vec<IndexAsset>>();
             to_binary(&IndexDataResponse {
@@ -302,16 +473,30 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<QueryResponse> {
                 when_stale: Uint64::new(oracle.config.when_stale),
                 peg: oracle.peg.into(),
                 basket,
+                auto_unfreeze: oracle.config.auto_unfreeze,
+                recovery: oracle.recovery,
+                stale_components: oracle.config.stale_components,
+                ramp: oracle.ramp.map(|ramp| RampProgress {
+                    progress: ramp.progress(now),
+                    ramp,
+                }),
             })
         }
         QueryMsg::GetBasket {} => {
             IndexOracle::require_can_run(deps.storage, true, true, false)?;
-            let basket = oracle
-                .basket
-                .iter()
-                .map(|(k, v)| (k.clone(), v.initial.into(), v.fixed.into()))
-                .collect::<Vec<(String, Decimal256, Decimal256)>>();
-            to_binary(&BasketResponse { basket })
+            to_binary(&BasketResponse {
+                basket: get_basket(&oracle),
+            })
+        }
+        QueryMsg::SimulateModBasket { basket } => {
+            IndexOracle::require_can_run(deps.storage, true, true, false)?;
+            let result = mod_basket(deps, &env, basket, &mut oracle);
+            to_binary(&simulation(&oracle, result))
+        }
+        QueryMsg::SimulateUpdateTarget { target } => {
+            IndexOracle::require_can_run(deps.storage, true, true, false)?;
+            let result = update_target(deps, &env, &mut oracle, target);
+            to_binary(&simulation(&oracle, result))
         }
     }?;
     pad_query_result(Ok(binary), BLOCK_SIZE)
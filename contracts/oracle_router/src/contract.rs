@@ -1,8 +1,8 @@
 use std::collections::HashMap;
 
 use cosmwasm_std::{
-    entry_point, to_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, QuerierWrapper,
-    Response, StdResult,
+    attr, entry_point, to_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo, QuerierWrapper,
+    Response, StdError, StdResult,
 };
 use shade_oracles::{
     core::{
@@ -13,11 +13,11 @@ use shade_oracles::{
     },
     create_attr_action,
     interfaces::{
-        common::{OraclePrice, PriceResponse, PricesResponse},
-        providers::ReferenceData,
+        common::{OraclePrice, PriceResponse, PriceSource, PricesResponse, SourcedPrice},
+        providers::{Provider, ReferenceData},
         router::{error::*, msg::*, registry::*},
     },
-    querier::{query_price, query_prices},
+    querier::{query_price, query_prices, query_routed_prices},
     status::GlobalStatus,
     BLOCK_SIZE,
 };
@@ -31,14 +31,23 @@ pub fn instantiate(
     _info: MessageInfo,
     msg: InstantiateMsg,
 ) -> OracleRouterResult<Response> {
+    let providers = msg
+        .providers
+        .into_iter()
+        .map(|provider| provider.into_valid(deps.api))
+        .collect::<StdResult<Vec<Provider>>>()?;
+    let aggregation = msg.aggregation.unwrap_or_default();
+    aggregation.validate()?;
     let config = Config {
         admin_auth: msg.admin_auth.into_valid(deps.api)?,
         this: Contract::new(&env.contract.address, &env.contract.code_hash),
-        provider: msg.provider.into_valid(deps.api)?,
+        providers,
         quote_symbol: msg.quote_symbol,
+        max_age: msg.max_age,
+        aggregation,
+        timelock: 0,
     };
     OracleRouter::init_status(deps.storage)?;
-    OracleRouter::init_storage(deps.storage)?;
     config.save(deps.storage)?;
     Ok(Response::default().add_attributes(vec![attr_action!("instantiate")]))
 }
@@ -71,10 +80,39 @@ fn require_bot(
     Ok(())
 }
 
+/// Circuit breakers and overrides that aren't forced can also be managed by the price bot,
+/// everything else requires an admin.
+fn require_registry_permission(
+    router: &OracleRouter,
+    querier: &QuerierWrapper,
+    sender: &Addr,
+    operation: &RegistryOperation,
+) -> OracleRouterResult<()> {
+    match operation {
+        RegistryOperation::ResetCircuitBreaker { .. }
+        | RegistryOperation::SetOverride { force: false, .. }
+        | RegistryOperation::RemoveOverrides { .. } => {
+            require_bot(router, querier, sender).or_else(|_| require_admin(router, querier, sender))
+        }
+        _ => require_admin(router, querier, sender),
+    }
+}
+
+/// Operations that aren't emergencies have to be proposed while the timelock is enabled.
+fn require_registry_timelock(
+    router: &OracleRouter,
+    operation: &RegistryOperation,
+) -> OracleRouterResult<()> {
+    if !operation.is_emergency() {
+        router.require_no_timelock()?;
+    }
+    Ok(())
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
-    deps: DepsMut,
-    _env: Env,
+    mut deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> OracleRouterResult<Response> {
@@ -91,6 +129,7 @@ pub fn execute(
             match msg {
                 ExecuteMsg::UpdateConfig(config) => {
                     require_admin(&router, &deps.querier, &info.sender)?;
+                    router.require_no_timelock()?;
                     router
                         .update_config(deps.api, config)?
                         .config
@@ -98,18 +137,21 @@ pub fn execute(
                     Ok(Response::new().add_attributes(vec![attr_action!("update_config")]))
                 }
                 ExecuteMsg::UpdateRegistry(operation) => {
-                    require_admin(&router, &deps.querier, &info.sender)?;
-                    OracleRouter::resolve_registry_operation(deps.api, deps.storage, operation)?;
+                    require_registry_permission(&router, &deps.querier, &info.sender, &operation)?;
+                    require_registry_timelock(&router, &operation)?;
+                    router.resolve_registry_operation(&mut deps, &env.block.time, operation)?;
                     Ok(Response::new().add_attributes(vec![attr_action!("update_registry")]))
                 }
                 ExecuteMsg::BatchUpdateRegistry(operations) => {
-                    require_admin(&router, &deps.querier, &info.sender)?;
                     for operation in operations {
-                        OracleRouter::resolve_registry_operation(
-                            deps.api,
-                            deps.storage,
-                            operation,
+                        require_registry_permission(
+                            &router,
+                            &deps.querier,
+                            &info.sender,
+                            &operation,
                         )?;
+                        require_registry_timelock(&router, &operation)?;
+                        router.resolve_registry_operation(&mut deps, &env.block.time, operation)?;
                     }
                     Ok(Response::new().add_attributes(vec![attr_action!("batch_update_registry")]))
                 }
@@ -120,6 +162,68 @@ pub fn execute(
                     }
                     Ok(Response::new().add_attributes(vec![attr_action!("update_protected_keys")]))
                 }
+                ExecuteMsg::RecordLastGoodPrices(keys) => {
+                    let mut recorded = vec![];
+                    for key in keys {
+                        let price = fetch_price(deps.as_ref(), &env, &router, key, 0)?;
+                        let key = price.price.key.clone();
+                        if OracleRouter::record_last_good_price(
+                            deps.storage,
+                            price,
+                            &env.block.time,
+                        )? {
+                            recorded.push(key);
+                        }
+                    }
+                    Ok(Response::new().add_attributes(vec![
+                        attr_action!("record_last_good_prices"),
+                        attr("recorded", recorded.join(",")),
+                    ]))
+                }
+                ExecuteMsg::Propose(action) => {
+                    require_admin(&router, &deps.querier, &info.sender)?;
+                    let proposal =
+                        router.propose(deps.storage, action, info.sender, &env.block.time)?;
+                    Ok(Response::new().add_attributes(vec![
+                        attr_action!("propose"),
+                        attr("proposal_id", proposal.id.to_string()),
+                        attr("executable_at", proposal.executable_at.to_string()),
+                    ]))
+                }
+                ExecuteMsg::ExecuteProposal(id) => {
+                    require_admin(&router, &deps.querier, &info.sender)?;
+                    let proposal =
+                        OracleRouter::take_ready_proposal(deps.storage, id, &env.block.time)?;
+                    match proposal.action {
+                        ProposalAction::Registry(operations) => {
+                            for operation in operations {
+                                router.resolve_registry_operation(
+                                    &mut deps,
+                                    &env.block.time,
+                                    operation,
+                                )?;
+                            }
+                        }
+                        ProposalAction::Config(config) => {
+                            router
+                                .update_config(deps.api, config)?
+                                .config
+                                .save(deps.storage)?;
+                        }
+                    }
+                    Ok(Response::new().add_attributes(vec![
+                        attr_action!("execute_proposal"),
+                        attr("proposal_id", id.to_string()),
+                    ]))
+                }
+                ExecuteMsg::CancelProposal(id) => {
+                    require_admin(&router, &deps.querier, &info.sender)?;
+                    OracleRouter::remove_proposal(deps.storage, id)?;
+                    Ok(Response::new().add_attributes(vec![
+                        attr_action!("cancel_proposal"),
+                        attr("proposal_id", id.to_string()),
+                    ]))
+                }
                 ExecuteMsg::SetStatus { .. } => panic!("Code should never get here."),
             }
         }
@@ -127,69 +231,366 @@ pub fn execute(
     Ok(pad_handle_result(result, BLOCK_SIZE)?)
 }
 
-/// Queries the oracle at the key, if no oracle exists at the key, queries the default oracle.
-pub fn get_price(deps: Deps, router: OracleRouter, key: String) -> StdResult<PriceResponse> {
-    let oracle = router.get_oracle(deps.storage, &key)?;
-    let price = if oracle.eq(&router.config.this) {
-        router.query_provider_price(&deps.querier, key)
+/// Queries the price of the key from one oracle, where the router itself stands for its providers.
+///
+/// Routed oracles are queried one level deeper than the depth the router was queried at.
+fn query_oracle_price(
+    deps: Deps,
+    env: &Env,
+    router: &OracleRouter,
+    oracle: &Contract,
+    key: String,
+    depth: u8,
+) -> StdResult<SourcedPrice> {
+    if oracle.eq(&router.config.this) {
+        router.query_provider_price(deps.storage, &deps.querier, key, &env.block.time, depth)
+    } else if OracleRouter::is_routed(deps.storage, oracle)? {
+        query_routed_prices(oracle, &deps.querier, &[key.clone()], depth + 1)?
+            .pop()
+            .map(|price| SourcedPrice::new(price, PriceSource::Oracle(oracle.clone())))
+            .ok_or_else(|| {
+                StdError::generic_err(format!("Oracle didn't return a price for {key}."))
+            })
+    } else {
+        query_price(oracle, &deps.querier, &key)
+            .map(|price| SourcedPrice::new(price, PriceSource::Oracle(oracle.clone())))
+    }
+}
+
+/// Bulk version of `query_oracle_price`.
+fn query_oracle_prices(
+    deps: Deps,
+    env: &Env,
+    router: &OracleRouter,
+    oracle: &Contract,
+    keys: Vec<String>,
+    depth: u8,
+) -> StdResult<Vec<SourcedPrice>> {
+    if keys.len() == 1 {
+        let price = query_oracle_price(deps, env, router, oracle, keys[0].clone(), depth)?;
+        return Ok(vec![price]);
+    }
+    if oracle.eq(&router.config.this) {
+        router.query_provider_prices(deps.storage, &deps.querier, keys, &env.block.time, depth)
     } else {
-        query_price(&oracle, &deps.querier, &key)
-    }?;
-    OracleRouter::try_deviation_test(deps.storage, &price)?;
-    Ok(price)
+        let prices = if OracleRouter::is_routed(deps.storage, oracle)? {
+            query_routed_prices(oracle, &deps.querier, &keys, depth + 1)?
+        } else {
+            query_prices(oracle, &deps.querier, &keys)?
+        };
+        Ok(prices
+            .into_iter()
+            .map(|price| SourcedPrice::new(price, PriceSource::Oracle(oracle.clone())))
+            .collect())
+    }
+}
+
+/// Queries the oracle chain of the key in order, falling back to the next oracle when the price errors,
+/// is stale or fails the deviation test of the key. Returns the index of the oracle used along with its price.
+///
+/// If no oracle passes, the first price that only failed the deviation test is returned so it goes through
+/// the circuit breaker of the key, otherwise the error of the first oracle along with the step that failed.
+pub fn fetch_price_from_chain(
+    deps: Deps,
+    env: &Env,
+    router: &OracleRouter,
+    key: String,
+    depth: u8,
+) -> Result<(usize, SourcedPrice), (PriceErrorKind, StdError)> {
+    OracleRouter::require_not_frozen(deps.storage, &key)
+        .map_err(|err| (PriceErrorKind::Frozen, err))?;
+    let chain = router
+        .get_oracle_chain(deps.storage, &key)
+        .map_err(|err| (PriceErrorKind::Unavailable, err))?;
+    let mut deviating = None;
+    let mut first_err = None;
+    for (index, oracle) in chain.iter().enumerate() {
+        let price = query_oracle_price(deps, env, router, oracle, key.clone(), depth)
+            .map_err(|err| (PriceErrorKind::Unavailable, err))
+            .and_then(|price| {
+                router
+                    .try_staleness_test(deps.storage, &price, &env.block.time)
+                    .map_err(|err| (PriceErrorKind::Stale, err))?;
+                Ok(price)
+            });
+        match price {
+            Ok(price) => match OracleRouter::try_deviation_test(deps.storage, &price.price) {
+                Ok(()) => return Ok((index, price)),
+                Err(err) => {
+                    first_err.get_or_insert((PriceErrorKind::Deviation, err));
+                    deviating.get_or_insert((index, price));
+                }
+            },
+            Err(err) => {
+                first_err.get_or_insert(err);
+            }
+        }
+    }
+    match (deviating, first_err) {
+        (Some(deviating), _) => Ok(deviating),
+        (None, Some(err)) => Err(err),
+        (None, None) => Err((
+            PriceErrorKind::Unavailable,
+            StdError::generic_err(format!("No oracle for {key}.")),
+        )),
+    }
+}
+
+/// Serves the override of the key if it has one, otherwise queries the oracle chain of the key,
+/// starting with its oracle or the providers if it has none.
+///
+/// The price is not checked against the circuit breaker of the key.
+pub fn fetch_price(
+    deps: Deps,
+    env: &Env,
+    router: &OracleRouter,
+    key: String,
+    depth: u8,
+) -> StdResult<SourcedPrice> {
+    OracleRouter::require_not_frozen(deps.storage, &key)?;
+    if let Some(price) = OracleRouter::get_override(deps.storage, &key, &env.block.time)? {
+        return Ok(price);
+    }
+    fetch_price_from_chain(deps, env, router, key, depth)
+        .map(|(_, price)| price)
+        .map_err(|(_, err)| err)
+}
+
+pub fn get_oracle(deps: Deps, router: &OracleRouter, key: String) -> StdResult<OracleResponse> {
+    let chain = router.get_oracle_chain(deps.storage, &key)?;
+    Ok(OracleResponse {
+        key,
+        oracle: chain[0].clone(),
+        chain,
+    })
+}
+
+/// Queries the price at the key, applying the protection of the key if it has one.
+pub fn get_price(
+    deps: Deps,
+    env: &Env,
+    router: OracleRouter,
+    key: String,
+) -> StdResult<PriceResponse> {
+    let price = fetch_price(deps, env, &router, key, 0)?;
+    OracleRouter::try_circuit_breaker(deps.storage, price, &env.block.time).map(|price| price.price)
+}
+
+/// Queries the price at the key like `get_price`, reporting where it came from and how the key is protected.
+pub fn get_price_detailed(
+    deps: Deps,
+    env: &Env,
+    router: OracleRouter,
+    key: String,
+) -> StdResult<PriceDetailedResponse> {
+    OracleRouter::require_not_frozen(deps.storage, &key)?;
+    let (oracle, active, price) =
+        match OracleRouter::get_override(deps.storage, &key, &env.block.time)? {
+            Some(price) => (router.config.this.clone(), None, price),
+            None => {
+                let (index, price) = fetch_price_from_chain(deps, env, &router, key.clone(), 0)
+                    .map_err(|(_, err)| err)?;
+                let oracle = router
+                    .get_oracle_chain(deps.storage, &key)?
+                    .swap_remove(index);
+                (oracle, Some(index as u32), price)
+            }
+        };
+    let protection = OracleRouter::PROTECTED_KEYS.may_load(deps.storage, &key)?;
+    let deviation = protection
+        .as_ref()
+        .map(|info| info.deviation_of(price.price.data.rate));
+    let price = OracleRouter::try_circuit_breaker(deps.storage, price, &env.block.time)?;
+    let providers = price.providers();
+    let age = price.price.age(env.block.time.seconds());
+    Ok(PriceDetailedResponse {
+        price: price.price,
+        source: price.source,
+        oracle,
+        active,
+        providers,
+        protection,
+        deviation,
+        age,
+    })
 }
 
 /// Builds bulk queries using the keys given.
+///
+/// Keys with fallbacks are queried one by one through their oracle chain and overridden keys are served their override.
 pub fn get_prices(
     deps: Deps,
+    env: &Env,
+    router: OracleRouter,
+    keys: Vec<String>,
+) -> StdResult<PricesResponse> {
+    get_prices_at_depth(deps, env, router, keys, 0)
+}
+
+/// Same as `get_prices` for a query made by a routed oracle at that routing depth.
+pub fn get_prices_at_depth(
+    deps: Deps,
+    env: &Env,
     router: OracleRouter,
     keys: Vec<String>,
+    depth: u8,
 ) -> StdResult<PricesResponse> {
-    let map = router.group_keys_by_oracle(deps.storage, keys.as_slice())?;
+    let mut bulk_keys = vec![];
+    let mut chained_keys = vec![];
+    // Temp vector of fetched prices
+    let mut fetched_prices: HashMap<String, SourcedPrice> = HashMap::new();
+    for key in &keys {
+        OracleRouter::require_not_frozen(deps.storage, key)?;
+        if let Some(price) = OracleRouter::get_override(deps.storage, key, &env.block.time)? {
+            fetched_prices.insert(key.clone(), price);
+        } else if OracleRouter::has_fallbacks(deps.storage, key)? {
+            chained_keys.push(key.clone());
+        } else {
+            bulk_keys.push(key.clone());
+        }
+    }
+    let map = router.group_keys_by_oracle(deps.storage, bulk_keys.as_slice())?;
     // Preserve symbol order
     let ordered_prices: Vec<OraclePrice> = keys
         .iter()
         .map(|key| OraclePrice::new(key.clone(), ReferenceData::default()))
         .collect();
-    // Temp vector of fetched prices
-    let mut fetched_prices: HashMap<String, OraclePrice> = HashMap::new();
 
     for (oracle, symbols) in map {
-        let queried_prices: PricesResponse = if oracle.eq(&router.config.this) {
-            if symbols.len() == 1 {
-                let price = router.query_provider_price(&deps.querier, symbols[0].clone())?;
-                Ok(vec![price])
-            } else {
-                router.query_provider_prices(&deps.querier, symbols)
-            }
-        } else {
-            if symbols.len() == 1 {
-                let price = query_price(&oracle, &deps.querier, &symbols[0])?;
-                Ok(vec![price])
-            } else {
-                query_prices(&oracle, &deps.querier, &symbols)
-            }
-        }?;
+        let queried_prices = query_oracle_prices(deps, env, &router, &oracle, symbols, depth)?;
         for price in queried_prices {
-            OracleRouter::try_deviation_test(deps.storage, &price)?;
-            if fetched_prices.get(price.key()).is_none() {
-                fetched_prices.insert(price.key.clone(), price);
+            router.try_staleness_test(deps.storage, &price, &env.block.time)?;
+            let price = OracleRouter::try_circuit_breaker(deps.storage, price, &env.block.time)?;
+            if fetched_prices.get(price.price.key()).is_none() {
+                fetched_prices.insert(price.price.key.clone(), price);
             }
         }
     }
+    for key in chained_keys {
+        if fetched_prices.get(&key).is_none() {
+            let price = fetch_price(deps, env, &router, key, depth)?;
+            let price = OracleRouter::try_circuit_breaker(deps.storage, price, &env.block.time)?;
+            fetched_prices.insert(price.price.key.clone(), price);
+        }
+    }
 
     // For every fetched price, find its position in the original request and replace the placeholder data with the actual data for that symbol.
     let ordered_prices: Vec<OraclePrice> = ordered_prices.into_iter().map(|mut p| {
         if let Some(fetched_price) = fetched_prices.get(p.key()) {
-            p.data = fetched_price.data.clone();
+            p.data = fetched_price.price.data.clone();
         }
         p
 }).collect();
     Ok(ordered_prices)
 }
 
+/// Prices each key independently so a key that can't be priced doesn't fail the others.
+///
+/// Keys are still queried in bulk from their oracle, retrying them one by one if the bulk query fails.
+pub fn get_prices_lenient(
+    deps: Deps,
+    env: &Env,
+    router: OracleRouter,
+    keys: Vec<String>,
+    depth: u8,
+) -> StdResult<LenientPricesResponse> {
+    let now = &env.block.time;
+    let mut results: HashMap<String, Result<SourcedPrice, PriceError>> = HashMap::new();
+    let mut bulk_keys = vec![];
+    for key in &keys {
+        if results.contains_key(key) || bulk_keys.contains(key) {
+            continue;
+        }
+        if let Err(err) = OracleRouter::require_not_frozen(deps.storage, key) {
+            results.insert(
+                key.clone(),
+                Err(PriceError::new(PriceErrorKind::Frozen, err)),
+            );
+        } else if let Some(price) = OracleRouter::get_override(deps.storage, key, now)? {
+            results.insert(key.clone(), Ok(price));
+        } else if OracleRouter::has_fallbacks(deps.storage, key)? {
+            let result = fetch_price_from_chain(deps, env, &router, key.clone(), depth)
+                .map_err(|(kind, err)| PriceError::new(kind, err))
+                .and_then(|(_, price)| {
+                    OracleRouter::try_circuit_breaker(deps.storage, price, now)
+                        .map_err(|err| PriceError::new(PriceErrorKind::Deviation, err))
+                });
+            results.insert(key.clone(), result);
+        } else {
+            bulk_keys.push(key.clone());
+        }
+    }
+
+    for (oracle, symbols) in router.group_keys_by_oracle(deps.storage, &bulk_keys)? {
+        let mut prices: HashMap<String, StdResult<SourcedPrice>> =
+            match query_oracle_prices(deps, env, &router, &oracle, symbols.clone(), depth) {
+                Ok(prices) => prices
+                    .into_iter()
+                    .map(|price| (price.price.key.clone(), Ok(price)))
+                    .collect(),
+                Err(_) => symbols
+                    .iter()
+                    .map(|key| {
+                        let price =
+                            query_oracle_price(deps, env, &router, &oracle, key.clone(), depth);
+                        (key.clone(), price)
+                    })
+                    .collect(),
+            };
+        for key in symbols {
+            let price = prices.remove(&key).unwrap_or_else(|| {
+                Err(StdError::generic_err(format!(
+                    "Oracle {} didn't return a price for {key}.",
+                    oracle.address
+                )))
+            });
+            let result = price
+                .map_err(|err| PriceError::new(PriceErrorKind::Unavailable, err))
+                .and_then(|price| {
+                    router
+                        .try_staleness_test(deps.storage, &price, now)
+                        .map_err(|err| PriceError::new(PriceErrorKind::Stale, err))?;
+                    OracleRouter::try_circuit_breaker(deps.storage, price, now)
+                        .map_err(|err| PriceError::new(PriceErrorKind::Deviation, err))
+                });
+            results.insert(key, result);
+        }
+    }
+
+    Ok(keys
+        .into_iter()
+        .map(|key| {
+            let (price, error) = match results[&key].clone() {
+                Ok(price) => (Some(price.price), None),
+                Err(error) => (None, Some(error)),
+            };
+            LenientPriceResponse { key, price, error }
+        })
+        .collect())
+}
+
+/// Queries the prices of the keys quoted in another key, through the prices of both in the quote symbol of the router.
+pub fn get_prices_in(
+    deps: Deps,
+    env: &Env,
+    router: OracleRouter,
+    mut keys: Vec<String>,
+    quote: String,
+) -> StdResult<PricesResponse> {
+    if quote == router.config.quote_symbol {
+        return get_prices(deps, env, router, keys);
+    }
+    keys.push(quote);
+    let mut prices = get_prices(deps, env, router, keys)?;
+    let quote = prices.pop().unwrap();
+    prices
+        .into_iter()
+        .map(|price| price.cross(quote.clone(), env.block.time.seconds()))
+        .collect()
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     let router = OracleRouter::load(deps.storage)?;
     let resp = match msg {
         QueryMsg::GetConfig {} => to_binary(&ConfigResponse {
@@ -198,30 +599,88 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
         }),
         _ => {
             match msg {
-                QueryMsg::GetOracle { key } => {
-                    let oracle = router.get_oracle(deps.storage, &key)?;
-                    to_binary(&OracleResponse { oracle, key })
-                }
+                QueryMsg::GetOracle { key } => to_binary(&get_oracle(deps, &router, key)?),
                 QueryMsg::GetPrice { key } => {
                     // If deprecated or frozen, throw error so dependencies cannot query it.
                     OracleRouter::require_can_run(deps.storage, true, false, false)?;
-                    to_binary(&get_price(deps, router, key)?)
+                    to_binary(&get_price(deps, &env, router, key)?)
+                }
+                QueryMsg::GetPriceIn { key, quote } => {
+                    OracleRouter::require_can_run(deps.storage, true, false, false)?;
+                    let mut prices = get_prices_in(deps, &env, router, vec![key], quote)?;
+                    to_binary(&prices.remove(0))
                 }
                 QueryMsg::GetOracles { keys } => {
                     let mut oracles = vec![];
                     for key in keys {
-                        let oracle = router.get_oracle(deps.storage, &key)?;
-                        oracles.push(OracleResponse { key, oracle })
+                        oracles.push(get_oracle(deps, &router, key)?);
                     }
                     to_binary(&oracles)
                 }
                 QueryMsg::GetPrices { keys } => {
                     // If deprecated or frozen, throw error so dependencies cannot query it.
                     OracleRouter::require_can_run(deps.storage, true, false, false)?;
-                    to_binary(&get_prices(deps, router, keys)?)
+                    to_binary(&get_prices(deps, &env, router, keys)?)
+                }
+                QueryMsg::GetPricesAtDepth { keys, depth } => {
+                    OracleRouter::require_can_run(deps.storage, true, false, false)?;
+                    OracleRouter::require_routing_depth(depth)?;
+                    to_binary(&get_prices_at_depth(deps, &env, router, keys, depth)?)
+                }
+                QueryMsg::GetPriceDetailed { key } => {
+                    OracleRouter::require_can_run(deps.storage, true, false, false)?;
+                    to_binary(&get_price_detailed(deps, &env, router, key)?)
+                }
+                QueryMsg::GetPricesLenient { keys, depth } => {
+                    OracleRouter::require_can_run(deps.storage, true, false, false)?;
+                    OracleRouter::require_routing_depth(depth)?;
+                    to_binary(&get_prices_lenient(deps, &env, router, keys, depth)?)
+                }
+                QueryMsg::GetPricesIn { keys, quote } => {
+                    OracleRouter::require_can_run(deps.storage, true, false, false)?;
+                    to_binary(&get_prices_in(deps, &env, router, keys, quote)?)
+                }
+                QueryMsg::GetKeys { start_after, limit } => {
+                    Ok(OracleRouter::get_keys(deps, start_after, limit)?)
+                }
+                QueryMsg::GetKeysByOracle {
+                    oracle,
+                    start_after,
+                    limit,
+                } => Ok(OracleRouter::get_keys_by_oracle(
+                    deps,
+                    oracle,
+                    start_after,
+                    limit,
+                )?),
+                QueryMsg::GetProtectedKeys { start_after, limit } => {
+                    Ok(OracleRouter::get_protected_keys(deps, start_after, limit)?)
+                }
+                QueryMsg::GetMaxAges { keys } => Ok(router.get_max_ages(deps, keys)?),
+                QueryMsg::GetSources { key } => Ok(router.get_sources(deps, key)?),
+                QueryMsg::DryRunRegistry { operations } => {
+                    Ok(router.dry_run_registry(deps, &env.block.time, operations)?)
+                }
+                QueryMsg::GetProposals { start_after, limit } => {
+                    Ok(OracleRouter::get_proposals(deps, start_after, limit)?)
+                }
+                QueryMsg::GetProposal { id } => Ok(OracleRouter::get_proposal(deps, id)?),
+                QueryMsg::GetOverrides { start_after, limit } => {
+                    Ok(OracleRouter::get_overrides(deps, start_after, limit)?)
+                }
+                QueryMsg::GetCircuitBreakers { keys } => {
+                    let mut circuit_breakers = vec![];
+                    for key in keys {
+                        let price = fetch_price(deps, &env, &router, key.clone(), 0);
+                        circuit_breakers.push(OracleRouter::get_circuit_breaker(
+                            deps.storage,
+                            key,
+                            price.map(|price| price.price),
+                            &env.block.time,
+                        )?);
+                    }
+                    to_binary(&circuit_breakers)
                 }
-                QueryMsg::GetKeys {} => Ok(OracleRouter::get_keys(deps)?),
-                QueryMsg::GetProtectedKeys {} => Ok(OracleRouter::get_protected_keys(deps)?),
                 _ => panic!("Code should never go here."),
             }
         }
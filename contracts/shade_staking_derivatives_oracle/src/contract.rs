@@ -5,7 +5,10 @@ use cosmwasm_std::{DepsMut, MessageInfo, QueryResponse, Uint128};
 use shade_oracles::better_secret_math::common::exp10;
 use shade_oracles::core::{pad_handle_result, pad_query_result};
 use shade_oracles::interfaces::common::config::{CommonConfig, CommonConfigResponse};
-use shade_oracles::interfaces::common::{OraclePrice, PriceResponse, PricesResponse};
+use shade_oracles::interfaces::common::{
+    DependenciesResponse, OraclePrice, PriceResponse, PricesResponse,
+};
+use shade_oracles::querier::query_price_at_depth;
 use shade_oracles::ssp::ItemStorage;
 use shade_oracles::{create_attr_action, BLOCK_SIZE};
 use shade_oracles::{
@@ -92,6 +95,7 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<QueryResponse> {
                     deps.storage,
                     &deps.querier,
                     key,
+                    0,
                 )?)
             }
             QueryMsg::GetPrices { keys } => {
@@ -102,8 +106,23 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<QueryResponse> {
                     deps.storage,
                     &deps.querier,
                     keys,
+                    0,
                 )?)
             }
+            QueryMsg::GetPricesAtDepth { keys, depth } => {
+                oracle.config.require_enabled()?;
+                to_binary(&query_prices(
+                    &oracle,
+                    &env,
+                    deps.storage,
+                    &deps.querier,
+                    keys,
+                    depth,
+                )?)
+            }
+            QueryMsg::GetDependencies { keys } => {
+                to_binary(&query_dependencies(deps.storage, keys)?)
+            }
             QueryMsg::GetConfig {} => to_binary(&query_config(deps.storage, oracle)?),
             QueryMsg::GetDerivatives {} => to_binary(&query_derivatives(deps.storage)?),
         },
@@ -117,6 +136,7 @@ pub fn query_price(
     storage: &dyn Storage,
     querier: &QuerierWrapper,
     key: String,
+    depth: u8,
 ) -> StdResult<PriceResponse> {
     let original_key = key.clone();
     let (is_rate, key) = StakingDerivativesOracle::process_key(&key);
@@ -134,9 +154,12 @@ pub fn query_price(
     let data = if is_rate {
         ReferenceData::new(normalized_rate.into(), now, now)
     } else {
-        let underlying_price = data
-            .staking_derivative
-            .get_price(querier, &oracle.config.router)?;
+        let underlying_price = query_price_at_depth(
+            &oracle.config.router,
+            querier,
+            &data.staking_derivative.quote_symbol,
+            depth,
+        )?;
 
         let price = underlying_price
             .data()
@@ -158,14 +181,33 @@ pub fn query_prices(
     storage: &dyn Storage,
     querier: &QuerierWrapper,
     keys: Vec<String>,
+    depth: u8,
 ) -> StdResult<PricesResponse> {
     let mut prices = vec![];
     for key in keys {
-        prices.push(query_price(oracle, env, storage, querier, key)?);
+        prices.push(query_price(oracle, env, storage, querier, key, depth)?);
     }
     Ok(prices)
 }
 
+pub fn query_dependencies(
+    storage: &dyn Storage,
+    keys: Vec<String>,
+) -> StdResult<DependenciesResponse> {
+    let mut dependencies = vec![];
+    for key in keys {
+        let (is_rate, derivative_key) = StakingDerivativesOracle::process_key(&key);
+        let data = StakingDerivativesOracle::get_derivative_data_resp(&derivative_key, storage)?;
+        let key_dependencies = if is_rate {
+            vec![]
+        } else {
+            vec![data.staking_derivative.quote_symbol]
+        };
+        dependencies.push((key, key_dependencies));
+    }
+    Ok(dependencies)
+}
+
 pub fn query_config(
     storage: &dyn Storage,
     oracle: StakingDerivativesOracle,
@@ -1,16 +1,17 @@
 use cosmwasm_std::{
     entry_point, to_binary, Deps, Env, QuerierWrapper, Response, StdResult, Storage,
 };
-use cosmwasm_std::{DepsMut, MessageInfo, QueryResponse, Uint128};
+use cosmwasm_std::{Addr, DepsMut, MessageInfo, QueryResponse, StdError, Uint128, Uint256};
 use shade_oracles::core::{pad_handle_result, pad_query_result};
 use shade_oracles::interfaces::common::config::{CommonConfig, CommonConfigResponse};
 use shade_oracles::interfaces::common::{OraclePrice, PriceResponse, PricesResponse};
-use shade_oracles::math::TokenMath;
+use shade_oracles::querier::query_price_at_depth;
 use shade_oracles::ssp::ItemStorage;
 use shade_oracles::{create_attr_action, BLOCK_SIZE};
 use shade_oracles::{
-    interfaces::dex::generic::*, interfaces::providers::ReferenceData,
-    protocols::shadeswap::ShadeSwapQuerier,
+    interfaces::dex::generic::*,
+    interfaces::providers::ReferenceData,
+    protocols::shadeswap::{PairInfo, ShadeSwapQuerier, StablePairInfoResponse},
 };
 
 create_attr_action!("shadeswap-market-oracle_");
@@ -97,14 +98,42 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<QueryResponse> {
         match msg {
             QueryMsg::GetPrice { key } => {
                 oracle.config.require_enabled()?;
-                to_binary(&query_price(&oracle, deps.storage, &deps.querier, key)?)
+                to_binary(&query_price(&oracle, deps.storage, &deps.querier, key, 0)?)
             }
             QueryMsg::GetPrices { keys } => {
                 oracle.config.require_enabled()?;
-                to_binary(&query_prices(&oracle, deps.storage, &deps.querier, keys)?)
+                to_binary(&query_prices(
+                    &oracle,
+                    deps.storage,
+                    &deps.querier,
+                    keys,
+                    0,
+                )?)
             }
+            QueryMsg::GetPricesAtDepth { keys, depth } => {
+                oracle.config.require_enabled()?;
+                to_binary(&query_prices(
+                    &oracle,
+                    deps.storage,
+                    &deps.querier,
+                    keys,
+                    depth,
+                )?)
+            }
+            QueryMsg::GetDependencies { keys } => to_binary(
+                &GenericLiquidityPairOracle::get_dependencies(deps.storage, keys, true)?,
+            ),
             QueryMsg::GetConfig {} => to_binary(&query_config(deps.storage, oracle)?),
             QueryMsg::GetPairs {} => to_binary(&query_pairs(deps.storage)?),
+            QueryMsg::GetReferenceCheck { key } => {
+                oracle.config.require_enabled()?;
+                to_binary(&query_reference_check(
+                    &oracle,
+                    deps.storage,
+                    &deps.querier,
+                    key,
+                )?)
+            }
         },
         BLOCK_SIZE,
     )
@@ -115,24 +144,88 @@ pub fn query_price(
     storage: &dyn Storage,
     querier: &QuerierWrapper,
     key: String,
+    depth: u8,
 ) -> StdResult<PriceResponse> {
     let pair_data = GenericLiquidityPairOracle::get_pair_data_resp(&key, storage)?;
-    // Simulate trade 1 target -> 1 base
-    let sim = ShadeSwapQuerier::query_swap_simulation(
-        querier,
-        &pair_data.pair,
-        &pair_data.target_token.contract,
-        Uint128::from(10u128.pow(pair_data.target_token.decimals.into())),
-    )?;
+    let price = query_market_price(oracle, querier, &pair_data, depth)?;
+    if let Some(reference) = pair_data.reference_check() {
+        reference.enforce(querier, &oracle.config.router, &price, depth)?;
+    }
+    Ok(price)
+}
 
-    // Normalize to 'rate * 10^18'
-    let exchange_rate: Uint128 =
-        TokenMath::normalize_value(sim.result.return_amount, pair_data.base_token.decimals)?.into();
+/// Compares the derived price with its reference price without rejecting it.
+pub fn query_reference_check(
+    oracle: &GenericLiquidityPairOracle,
+    storage: &dyn Storage,
+    querier: &QuerierWrapper,
+    key: String,
+) -> StdResult<ReferenceCheckResponse> {
+    let pair_data = GenericLiquidityPairOracle::get_pair_data_resp(&key, storage)?;
+    let reference = pair_data.reference_check().ok_or_else(|| {
+        StdError::generic_err(format!("No reference check configured for {key}."))
+    })?;
+    let price = query_market_price(oracle, querier, &pair_data, 0)?;
+    reference.check(querier, &oracle.config.router, &price)
+}
+
+fn query_market_price(
+    oracle: &GenericLiquidityPairOracle,
+    querier: &QuerierWrapper,
+    pair_data: &PairData,
+    depth: u8,
+) -> StdResult<PriceResponse> {
+    let pair_info = ShadeSwapQuerier::query_pair_info(querier, &pair_data.pair)?;
+    // Simulate trade of the amount (1 target to price it) -> base, normalized to 'rate * 10^18'
+    let simulate_rate = |amount: Uint128| -> StdResult<Uint256> {
+        let sim = ShadeSwapQuerier::simulate_swap(
+            querier,
+            &pair_data.pair,
+            &pair_info,
+            &pair_data.target_token.contract,
+            amount,
+        )?;
+        pair_data.exchange_rate(sim.result.return_amount, amount)
+    };
+    let one_target = pair_data.one_target_token();
+    let exchange_rate = simulate_rate(one_target)?;
 
     // Query router for base_peg/USD
-    let base_usd_price = pair_data
-        .base_token
-        .get_price(querier, &oracle.config.router)?;
+    let base_usd_price = query_price_at_depth(
+        &oracle.config.router,
+        querier,
+        &pair_data.base_token.quote_symbol,
+        depth,
+    )?;
+
+    if pair_data.market_guard.is_some() {
+        let target = &pair_data.target_token.contract.address;
+        let reserves = if pair_info.pair.0.eq_address(target) {
+            (pair_info.amount_1, pair_info.amount_0)
+        } else {
+            (pair_info.amount_0, pair_info.amount_1)
+        };
+        let spot_rate = match &pair_info.stable_info {
+            Some(stable_info) if pair_info.is_stableswap() => {
+                Some(stable_spot_rate(&pair_info, stable_info, target)?)
+            }
+            _ => None,
+        };
+        // The price impact check simulates its own trade size.
+        let trade_size = pair_data.trade_size();
+        let trade_rate = if trade_size == one_target {
+            exchange_rate
+        } else {
+            simulate_rate(trade_size)?
+        };
+        pair_data.check_market_guard(
+            &base_usd_price,
+            reserves,
+            spot_rate,
+            exchange_rate,
+            trade_rate,
+        )?;
+    }
 
     // Translate price to target/USD
     let price = base_usd_price
@@ -141,7 +234,7 @@ pub fn query_price(
         .multiply_ratio(exchange_rate, 10u128.pow(18));
 
     Ok(OraclePrice::new(
-        key,
+        pair_data.key.clone(),
         ReferenceData {
             rate: price,
             last_updated_base: base_usd_price.data().last_updated_base,
@@ -150,15 +243,35 @@ pub fn query_price(
     ))
 }
 
+/// Spot rate of the stableswap pair as base tokens per target token, based on the price it reports.
+fn stable_spot_rate(
+    pair_info: &PairInfo,
+    stable_info: &StablePairInfoResponse,
+    target: &Addr,
+) -> StdResult<Uint256> {
+    // The pair reports the price of token 0 in token 1.
+    let p = stable_info
+        .p
+        .ok_or_else(|| StdError::generic_err("Stableswap pair did not report its price."))?;
+    let rate = if pair_info.pair.0.eq_address(target) {
+        Some(p)
+    } else {
+        p.inv()
+    };
+    rate.map(|rate| rate.atomics())
+        .ok_or_else(|| StdError::generic_err("Stableswap pair reported a price of 0."))
+}
+
 pub fn query_prices(
     oracle: &GenericLiquidityPairOracle,
     storage: &dyn Storage,
     querier: &QuerierWrapper,
     keys: Vec<String>,
+    depth: u8,
 ) -> StdResult<PricesResponse> {
     let mut prices = vec![];
     for key in keys {
-        prices.push(query_price(oracle, storage, querier, key)?);
+        prices.push(query_price(oracle, storage, querier, key, depth)?);
     }
     Ok(prices)
 }
@@ -8,7 +8,7 @@ use shade_oracles::interfaces::common::config::{CommonConfig, CommonConfigRespon
 use shade_oracles::interfaces::common::{OraclePrice, PriceResponse, PricesResponse};
 use shade_oracles::protocols::shadeswap::ShadeSwapQuerier;
 use shade_oracles::{
-    common::querier::{query_prices as query_router_prices, query_token_info},
+    common::querier::{query_prices_at_depth, query_token_info},
     core::pad_query_result,
     interfaces::dex::generic::*,
     ssp::ItemStorage,
@@ -97,14 +97,36 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<QueryResponse> {
         match msg {
             QueryMsg::GetPrice { key } => {
                 oracle.config.require_enabled()?;
-                to_binary(&query_price(&oracle, deps.storage, &deps.querier, key)?)
+                to_binary(&query_price(&oracle, deps.storage, &deps.querier, key, 0)?)
             }
             QueryMsg::GetPrices { keys } => {
                 oracle.config.require_enabled()?;
-                to_binary(&query_prices(&oracle, deps.storage, &deps.querier, keys)?)
+                to_binary(&query_prices(
+                    &oracle,
+                    deps.storage,
+                    &deps.querier,
+                    keys,
+                    0,
+                )?)
             }
+            QueryMsg::GetPricesAtDepth { keys, depth } => {
+                oracle.config.require_enabled()?;
+                to_binary(&query_prices(
+                    &oracle,
+                    deps.storage,
+                    &deps.querier,
+                    keys,
+                    depth,
+                )?)
+            }
+            QueryMsg::GetDependencies { keys } => to_binary(
+                &GenericLiquidityPairOracle::get_dependencies(deps.storage, keys, false)?,
+            ),
             QueryMsg::GetConfig {} => to_binary(&query_config(deps.storage, oracle)?),
             QueryMsg::GetPairs {} => to_binary(&query_pairs(deps.storage)?),
+            QueryMsg::GetReferenceCheck { .. } => Err(StdError::generic_err(
+                "Reference checks are only supported by market oracles.",
+            )),
         },
         BLOCK_SIZE,
     )
@@ -115,15 +137,17 @@ pub fn query_price(
     storage: &dyn Storage,
     querier: &QuerierWrapper,
     key: String,
+    depth: u8,
 ) -> StdResult<PriceResponse> {
     let data = GenericLiquidityPairOracle::get_pair_data_resp(&key, storage)?;
-    let prices = query_router_prices(
+    let prices = query_prices_at_depth(
         &oracle.config.router,
         querier,
         &[
             data.base_token.quote_symbol.clone(),
             data.target_token.quote_symbol.clone(),
         ],
+        depth,
     )?;
     let pair_resp = ShadeSwapQuerier::query_pair_info(querier, &data.pair)?;
 
@@ -132,13 +156,29 @@ pub fn query_price(
     let reserves_0 = pair_resp.amount_0;
     let reserves_1 = pair_resp.amount_1;
 
-    let data = GenericLiquidityPairOracle::calculate_lp_token_spot_rate(
-        data,
-        lp_token_info,
-        reserves_0,
-        reserves_1,
-        &[&prices[0], &prices[1]],
-    )?;
+    let data = match &pair_resp.stable_info {
+        Some(stable_info) if pair_resp.is_stableswap() => {
+            GenericLiquidityPairOracle::calculate_lp_token_stableswap_rate(
+                data,
+                lp_token_info,
+                reserves_0,
+                reserves_1,
+                &[&prices[0], &prices[1]],
+                stable_info.stable_params.a,
+                stable_info
+                    .stable_params
+                    .gamma1
+                    .max(stable_info.stable_params.gamma2),
+            )?
+        }
+        _ => GenericLiquidityPairOracle::calculate_lp_token_spot_rate(
+            data,
+            lp_token_info,
+            reserves_0,
+            reserves_1,
+            &[&prices[0], &prices[1]],
+        )?,
+    };
     Ok(OraclePrice::new(key, data))
 }
 
@@ -147,10 +187,11 @@ pub fn query_prices(
     storage: &dyn Storage,
     querier: &QuerierWrapper,
     keys: Vec<String>,
+    depth: u8,
 ) -> StdResult<PricesResponse> {
     let mut prices = vec![];
     for key in keys {
-        prices.push(query_price(oracle, storage, querier, key)?);
+        prices.push(query_price(oracle, storage, querier, key, depth)?);
     }
     Ok(prices)
 }
@@ -1,4 +1,4 @@
-use cosmwasm_std::{entry_point, QuerierWrapper, Storage};
+use cosmwasm_std::{entry_point, QuerierWrapper, StdError, Storage};
 use cosmwasm_std::{
     to_binary, Deps, DepsMut, Env, MessageInfo, QueryResponse, Response, StdResult,
 };
@@ -8,7 +8,7 @@ use shade_oracles::interfaces::common::config::{CommonConfig, CommonConfigRespon
 use shade_oracles::interfaces::common::{OraclePrice, PriceResponse, PricesResponse};
 use shade_oracles::protocols::siennaswap::SiennaSwapQuerier;
 use shade_oracles::{
-    common::querier::{query_prices as query_router_prices, query_token_info},
+    common::querier::{query_prices_at_depth, query_token_info},
     core::pad_query_result,
     interfaces::dex::generic::*,
     ssp::ItemStorage,
@@ -101,14 +101,36 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<QueryResponse> {
         match msg {
             QueryMsg::GetPrice { key } => {
                 oracle.config.require_enabled()?;
-                to_binary(&query_price(&oracle, deps.storage, &deps.querier, key)?)
+                to_binary(&query_price(&oracle, deps.storage, &deps.querier, key, 0)?)
             }
             QueryMsg::GetPrices { keys } => {
                 oracle.config.require_enabled()?;
-                to_binary(&query_prices(&oracle, deps.storage, &deps.querier, keys)?)
+                to_binary(&query_prices(
+                    &oracle,
+                    deps.storage,
+                    &deps.querier,
+                    keys,
+                    0,
+                )?)
             }
+            QueryMsg::GetPricesAtDepth { keys, depth } => {
+                oracle.config.require_enabled()?;
+                to_binary(&query_prices(
+                    &oracle,
+                    deps.storage,
+                    &deps.querier,
+                    keys,
+                    depth,
+                )?)
+            }
+            QueryMsg::GetDependencies { keys } => to_binary(
+                &GenericLiquidityPairOracle::get_dependencies(deps.storage, keys, false)?,
+            ),
             QueryMsg::GetConfig {} => to_binary(&query_config(deps.storage, oracle)?),
             QueryMsg::GetPairs {} => to_binary(&query_pairs(deps.storage)?),
+            QueryMsg::GetReferenceCheck { .. } => Err(StdError::generic_err(
+                "Reference checks are only supported by market oracles.",
+            )),
         },
         BLOCK_SIZE,
     )
@@ -119,15 +141,17 @@ pub fn query_price(
     storage: &dyn Storage,
     querier: &QuerierWrapper,
     key: String,
+    depth: u8,
 ) -> StdResult<PriceResponse> {
     let data = GenericLiquidityPairOracle::get_pair_data_resp(&key, storage)?;
-    let prices = query_router_prices(
+    let prices = query_prices_at_depth(
         &oracle.config.router,
         querier,
         &[
             data.base_token.quote_symbol.clone(),
             data.target_token.quote_symbol.clone(),
         ],
+        depth,
     )?;
     let pair_resp = SiennaSwapQuerier::query_pair_info(querier, &data.pair)?.pair_info;
     let lp_token_info = query_token_info(&pair_resp.liquidity_token, querier)?;
@@ -151,10 +175,11 @@ pub fn query_prices(
     storage: &dyn Storage,
     querier: &QuerierWrapper,
     keys: Vec<String>,
+    depth: u8,
 ) -> StdResult<PricesResponse> {
     let mut prices = vec![];
     for key in keys {
-        prices.push(query_price(oracle, storage, querier, key)?);
+        prices.push(query_price(oracle, storage, querier, key, depth)?);
     }
     Ok(prices)
 }
@@ -5,10 +5,10 @@ use cosmwasm_std::{DepsMut, MessageInfo, QueryResponse};
 use shade_oracles::core::{pad_handle_result, pad_query_result};
 use shade_oracles::ssp::ItemStorage;
 use shade_oracles::{
-    common::querier::query_price as query_router_price,
+    common::querier::query_price_at_depth as query_router_price,
     interfaces::common::{
         config::{CommonConfig, CommonConfigResponse},
-        OraclePrice, PriceResponse, PricesResponse,
+        DependenciesResponse, OraclePrice, PriceResponse, PricesResponse,
     },
 };
 use shade_oracles::{create_attr_action, BLOCK_SIZE};
@@ -104,6 +104,7 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<QueryResponse> {
                     deps.storage,
                     &deps.querier,
                     key,
+                    0,
                 )?)
             }
             QueryMsg::GetPrices { keys } => {
@@ -114,10 +115,28 @@ pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<QueryResponse> {
                     deps.storage,
                     &deps.querier,
                     keys,
+                    0,
                 )?)
             }
+            QueryMsg::GetPricesAtDepth { keys, depth } => {
+                oracle.config.require_enabled()?;
+                to_binary(&query_prices(
+                    &oracle,
+                    &env,
+                    deps.storage,
+                    &deps.querier,
+                    keys,
+                    depth,
+                )?)
+            }
+            QueryMsg::GetDependencies { keys } => {
+                to_binary(&query_dependencies(deps.storage, keys)?)
+            }
             QueryMsg::GetConfig {} => to_binary(&query_config(deps.storage, oracle)?),
             QueryMsg::GetDerivatives {} => to_binary(&query_derivatives(deps.storage)?),
+            QueryMsg::GetRateHistory { key } => to_binary(
+                &StrideStakingDerivativesOracle::get_rate_history(deps.storage, &key)?,
+            ),
         },
         BLOCK_SIZE,
     )
@@ -129,6 +148,7 @@ pub fn query_price(
     storage: &dyn Storage,
     querier: &QuerierWrapper,
     key: String,
+    depth: u8,
 ) -> StdResult<PriceResponse> {
     let original_key = key.clone();
     let (is_rate, key) = StrideStakingDerivativesOracle::process_key(&key);
@@ -142,8 +162,12 @@ pub fn query_price(
     let data = if is_rate {
         ReferenceData::new(rate.value.atomics(), rate.last_updated, rate.last_updated)
     } else {
-        let underlying_price =
-            query_router_price(&oracle.config.router, querier, &stored_data.underlying_key)?;
+        let underlying_price = query_router_price(
+            &oracle.config.router,
+            querier,
+            &stored_data.underlying_key,
+            depth,
+        )?;
         let price = rate.value * underlying_price.data().rate;
         ReferenceData::new(
             price,
@@ -161,14 +185,34 @@ pub fn query_prices(
     storage: &dyn Storage,
     querier: &QuerierWrapper,
     keys: Vec<String>,
+    depth: u8,
 ) -> StdResult<PricesResponse> {
     let mut prices = vec![];
     for key in keys {
-        prices.push(query_price(oracle, env, storage, querier, key)?);
+        prices.push(query_price(oracle, env, storage, querier, key, depth)?);
     }
     Ok(prices)
 }
 
+pub fn query_dependencies(
+    storage: &dyn Storage,
+    keys: Vec<String>,
+) -> StdResult<DependenciesResponse> {
+    let mut dependencies = vec![];
+    for key in keys {
+        let (is_rate, derivative_key) = StrideStakingDerivativesOracle::process_key(&key);
+        let stored_data =
+            StrideStakingDerivativesOracle::DERIVATIVES.load(storage, &derivative_key)?;
+        let key_dependencies = if is_rate {
+            vec![]
+        } else {
+            vec![stored_data.underlying_key]
+        };
+        dependencies.push((key, key_dependencies));
+    }
+    Ok(dependencies)
+}
+
 pub fn query_config(
     storage: &dyn Storage,
     oracle: StrideStakingDerivativesOracle,
@@ -0,0 +1,146 @@
+use cosmwasm_std::{entry_point, to_binary, Deps, Env, Response, StdResult, Storage};
+use cosmwasm_std::{DepsMut, MessageInfo, QueryResponse};
+use shade_oracles::core::{pad_handle_result, pad_query_result};
+use shade_oracles::ssp::ItemStorage;
+use shade_oracles::{
+    common::querier::query_prices as query_router_prices,
+    interfaces::common::{
+        config::{CommonConfig, CommonConfigResponse},
+        OraclePrice, PriceResponse, PricesResponse,
+    },
+};
+use shade_oracles::{create_attr_action, interfaces::twap::*, BLOCK_SIZE};
+
+create_attr_action!("twap-oracle_");
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: InstantiateMsg,
+) -> StdResult<Response> {
+    let config = CommonConfig::init(deps.api, deps.storage, msg.router)?;
+    TwapOracle { config }.save(deps.storage)?;
+    Ok(Response::new().add_attribute("action", "instantiate"))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> StdResult<Response> {
+    let mut oracle = TwapOracle::load(deps.storage)?;
+    let resp = Response::new();
+    let resp = match msg {
+        ExecuteMsg::SetStatus(status) => {
+            oracle.config.require_admin(&deps.querier, info)?;
+            oracle.config.update_config(deps.api, Some(status), None)?;
+            oracle.save(deps.storage)?;
+            resp.add_attributes(vec![attr_action!("set_status")])
+        }
+        _ => {
+            oracle.config.require_enabled()?;
+            let now = env.block.time.seconds();
+            match msg {
+                ExecuteMsg::SetTwaps(twaps) => {
+                    oracle.config.require_admin(&deps.querier, info)?;
+                    TwapOracle::set_twaps(deps.storage, twaps)?;
+                    resp.add_attributes(vec![attr_action!("set_twaps")])
+                }
+                ExecuteMsg::RemoveTwaps(keys) => {
+                    oracle.config.require_admin(&deps.querier, info)?;
+                    TwapOracle::remove_keys(deps.storage, keys)?;
+                    resp.add_attributes(vec![attr_action!("remove_twaps")])
+                }
+                ExecuteMsg::PushObservations(observations) => {
+                    oracle.config.require_permission(
+                        &deps.querier,
+                        info,
+                        BotPermission::PushObservations,
+                    )?;
+                    for (key, rate) in observations {
+                        TwapOracle::record_observation(deps.storage, &key, rate, now, 1)?;
+                    }
+                    resp.add_attributes(vec![attr_action!("push_observations")])
+                }
+                ExecuteMsg::PullObservations(keys) => {
+                    let mut source_keys = vec![];
+                    let mut min_spacings = vec![];
+                    for key in &keys {
+                        let config = TwapOracle::load_twap(deps.storage, key)?.config;
+                        min_spacings.push(config.min_spacing());
+                        source_keys.push(config.source_key);
+                    }
+                    let prices =
+                        query_router_prices(&oracle.config.router, &deps.querier, &source_keys)?;
+                    for ((key, price), min_spacing) in keys.iter().zip(prices).zip(min_spacings) {
+                        TwapOracle::record_observation(
+                            deps.storage,
+                            key,
+                            price.data.rate,
+                            now,
+                            min_spacing,
+                        )?;
+                    }
+                    resp.add_attributes(vec![attr_action!("pull_observations")])
+                }
+                ExecuteMsg::UpdateConfig(new_router) => {
+                    oracle.config.require_admin(&deps.querier, info)?;
+                    oracle
+                        .config
+                        .update_config(deps.api, None, Some(new_router))?;
+                    oracle.save(deps.storage)?;
+                    resp.add_attributes(vec![attr_action!("update_config")])
+                }
+                _ => panic!("Code should never go here."),
+            }
+        }
+    };
+    pad_handle_result(Ok(resp), BLOCK_SIZE)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<QueryResponse> {
+    let oracle = TwapOracle::load(deps.storage)?;
+
+    pad_query_result(
+        match msg {
+            QueryMsg::GetPrice { key } => {
+                oracle.config.require_enabled()?;
+                to_binary(&query_price(&env, deps.storage, key)?)
+            }
+            QueryMsg::GetPrices { keys } => {
+                oracle.config.require_enabled()?;
+                to_binary(&query_prices(&env, deps.storage, keys)?)
+            }
+            QueryMsg::GetConfig {} => to_binary(&query_config(deps.storage, oracle)?),
+            QueryMsg::GetTwaps {} => to_binary(&TwapOracle::get_twaps(deps.storage)?),
+            QueryMsg::GetObservations { key } => {
+                let data = TwapOracle::load_twap(deps.storage, &key)?;
+                to_binary(&TwapOracle::get_observations(deps.storage, &data)?)
+            }
+        },
+        BLOCK_SIZE,
+    )
+}
+
+pub fn query_price(env: &Env, storage: &dyn Storage, key: String) -> StdResult<PriceResponse> {
+    let data = TwapOracle::load_twap(storage, &key)?;
+    let observations = TwapOracle::get_observations(storage, &data)?;
+    let twap = data.twap(&observations, env.block.time.seconds())?;
+    Ok(OraclePrice::new(key, twap))
+}
+
+pub fn query_prices(
+    env: &Env,
+    storage: &dyn Storage,
+    keys: Vec<String>,
+) -> StdResult<PricesResponse> {
+    let mut prices = vec![];
+    for key in keys {
+        prices.push(query_price(env, storage, key)?);
+    }
+    Ok(prices)
+}
+
+pub fn query_config(storage: &dyn Storage, oracle: TwapOracle) -> StdResult<CommonConfigResponse> {
+    oracle.config.get_resp(storage)
+}
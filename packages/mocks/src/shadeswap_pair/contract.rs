@@ -1,12 +1,12 @@
 use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{entry_point, DepsMut, MessageInfo, Uint128};
 use cosmwasm_std::{to_binary, Addr, Binary, Deps, Env, Response, StdError, StdResult};
-use shade_oracles::protocols::shadeswap::{PairInfo, ShadeSwapQueryMsgResponse};
+use shade_oracles::protocols::shadeswap::{
+    PairInfo, ShadeSwapQueryMsgResponse, StablePairInfoResponse,
+};
 use shade_oracles::{
     core::{Contract, ExecuteCallback, InstantiateCallback},
-    protocols::shadeswap::{
-        Fee, FeeInfo, QueryMsg, SwapResult, SwapSimulationResponse, TokenPair, TokenType,
-    },
+    protocols::shadeswap::{Fee, FeeInfo, QueryMsg, SwapResult, TokenPair, TokenType},
     ssp::Item,
 };
 use shade_protocol::contract_interfaces::snip20::ExecuteMsg as Snip20ExecuteMsg;
@@ -50,6 +50,16 @@ pub enum ExecuteMsg {
         liquidity_token: Contract,
         liquidity_tokens: Uint128,
     },
+    /// Mocks a stableswap pool, which swaps 1:1 and requires the oracle keys of its tokens.
+    MockStablePool {
+        token_a: Contract,
+        amount_a: Uint128,
+        token_b: Contract,
+        amount_b: Uint128,
+        liquidity_token: Contract,
+        liquidity_tokens: Uint128,
+        stable_info: StablePairInfoResponse,
+    },
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -67,54 +77,103 @@ pub fn execute(
             amount_b,
             liquidity_token,
             liquidity_tokens,
-        } => {
-            PAIR_INFO.save(
-                deps.storage,
-                &PairInfo {
-                    liquidity_token: liquidity_token.clone(),
-                    factory: Some(Contract {
-                        address: Addr::unchecked("".to_string()),
-                        code_hash: "".to_string(),
-                    }),
-                    pair: TokenPair(
-                        TokenType::CustomToken {
-                            contract_addr: token_a.address,
-                            token_code_hash: token_a.code_hash,
-                            oracle_key: None,
-                        },
-                        TokenType::CustomToken {
-                            contract_addr: token_b.address,
-                            token_code_hash: token_b.code_hash,
-                            oracle_key: None,
-                        },
-                        false,
-                    ),
-                    amount_0: amount_a,
-                    amount_1: amount_b,
-                    total_liquidity: liquidity_tokens,
-                    contract_version: 1,
-                    fee_info: FeeInfo {
-                        shade_dao_address: Addr::unchecked("".to_string()),
-                        lp_fee: Fee::default(),
-                        shade_dao_fee: Fee::default(),
-                        stable_lp_fee: Fee::default(),
-                        stable_shade_dao_fee: Fee::default(),
-                    },
-                    stable_info: None,
-                },
-            )?;
+        } => mock_pool(
+            deps,
+            token_a,
+            amount_a,
+            token_b,
+            amount_b,
+            liquidity_token,
+            liquidity_tokens,
+            None,
+        ),
+        ExecuteMsg::MockStablePool {
+            token_a,
+            amount_a,
+            token_b,
+            amount_b,
+            liquidity_token,
+            liquidity_tokens,
+            stable_info,
+        } => mock_pool(
+            deps,
+            token_a,
+            amount_a,
+            token_b,
+            amount_b,
+            liquidity_token,
+            liquidity_tokens,
+            Some(stable_info),
+        ),
+    }
+}
 
-            let mut msgs = vec![];
-            msgs.push(Snip20ExecuteMsg::Mint {
-                recipient: Addr::unchecked("voidvoidvoid").to_string(),
-                amount: amount_a,
-                memo: None,
-                padding: None,
-            }.to_cosmos_msg(&liquidity_token, vec![])?);
+#[allow(clippy::too_many_arguments)]
+fn mock_pool(
+    deps: DepsMut,
+    token_a: Contract,
+    amount_a: Uint128,
+    token_b: Contract,
+    amount_b: Uint128,
+    liquidity_token: Contract,
+    liquidity_tokens: Uint128,
+    stable_info: Option<StablePairInfoResponse>,
+) -> StdResult<Response> {
+    let oracle_keys = match &stable_info {
+        Some(stable_info) => (
+            Some(stable_info.stable_token0_data.oracle_key.clone()),
+            Some(stable_info.stable_token1_data.oracle_key.clone()),
+        ),
+        None => (None, None),
+    };
+    PAIR_INFO.save(
+        deps.storage,
+        &PairInfo {
+            liquidity_token: liquidity_token.clone(),
+            factory: Some(Contract {
+                address: Addr::unchecked("".to_string()),
+                code_hash: "".to_string(),
+            }),
+            pair: TokenPair(
+                TokenType::CustomToken {
+                    contract_addr: token_a.address,
+                    token_code_hash: token_a.code_hash,
+                    oracle_key: oracle_keys.0,
+                },
+                TokenType::CustomToken {
+                    contract_addr: token_b.address,
+                    token_code_hash: token_b.code_hash,
+                    oracle_key: oracle_keys.1,
+                },
+                stable_info.is_some(),
+            ),
+            amount_0: amount_a,
+            amount_1: amount_b,
+            total_liquidity: liquidity_tokens,
+            contract_version: 1,
+            fee_info: FeeInfo {
+                shade_dao_address: Addr::unchecked("".to_string()),
+                lp_fee: Fee::default(),
+                shade_dao_fee: Fee::default(),
+                stable_lp_fee: Fee::default(),
+                stable_shade_dao_fee: Fee::default(),
+            },
+            stable_info,
+        },
+    )?;
 
-            Ok(Response::default().add_messages(msgs))
+    let mut msgs = vec![];
+    msgs.push(
+        Snip20ExecuteMsg::Mint {
+            recipient: Addr::unchecked("voidvoidvoid").to_string(),
+            amount: amount_a,
+            memo: None,
+            padding: None,
         }
-    }
+        .to_cosmos_msg(&liquidity_token, vec![])?,
+    );
+
+    Ok(Response::default().add_messages(msgs))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
@@ -148,15 +207,18 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             offer,
             exclude_fee: _,
         } => {
-            let in_token = match offer.token {
+            let (in_token, oracle_key) = match offer.token {
                 TokenType::CustomToken {
                     contract_addr,
                     token_code_hash,
-                    oracle_key: _,
-                } => Contract {
-                    address: contract_addr,
-                    code_hash: token_code_hash,
-                },
+                    oracle_key,
+                } => (
+                    Contract {
+                        address: contract_addr,
+                        code_hash: token_code_hash,
+                    },
+                    oracle_key,
+                ),
                 _ => {
                     return Err(StdError::generic_err("Only CustomToken supported"));
                 }
@@ -164,6 +226,38 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
 
             let pair_info = PAIR_INFO.load(deps.storage)?;
 
+            if let Some(stable_info) = &pair_info.stable_info {
+                if oracle_key != Some(pair_info.stable_oracle_key(&in_token.address)?) {
+                    return Err(StdError::generic_err(
+                        "Oracle key of the offered token does not match the pair's.",
+                    ));
+                }
+                let (give_decimals, take_decimals) =
+                    if pair_info.pair.0.eq_address(&in_token.address) {
+                        (
+                            stable_info.stable_token0_data.decimals,
+                            stable_info.stable_token1_data.decimals,
+                        )
+                    } else {
+                        (
+                            stable_info.stable_token1_data.decimals,
+                            stable_info.stable_token0_data.decimals,
+                        )
+                    };
+                return to_binary(&ShadeSwapQueryMsgResponse::SwapSimulation {
+                    price: String::default(),
+                    total_fee_amount: Uint128::default(),
+                    lp_fee_amount: Uint128::default(),
+                    shade_dao_fee_amount: Uint128::default(),
+                    result: SwapResult {
+                        return_amount: offer.amount.multiply_ratio(
+                            10u128.pow(take_decimals.into()),
+                            10u128.pow(give_decimals.into()),
+                        ),
+                    },
+                });
+            }
+
             match pair_info.pair.0 {
                 TokenType::CustomToken {
                     contract_addr,
@@ -198,7 +292,7 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
                     oracle_key: None,
                 } => {
                     if in_token.address == contract_addr {
-                        return to_binary(&SwapSimulationResponse {
+                        return to_binary(&ShadeSwapQueryMsgResponse::SwapSimulation {
                             price: String::default(),
                             total_fee_amount: Uint128::default(),
                             lp_fee_amount: Uint128::default(),
@@ -11,6 +11,7 @@ create_harness!(band, MockBand, mock_band);
 create_harness!(ojo, MockOjo, mock_ojo);
 create_harness!(router, OracleRouter, oracle_router);
 create_harness!(index, IndexOracle, index_oracle);
+create_harness!(twap, TwapOracle, twap_oracle);
 
 pub mod dex {
     pub mod siennaswap {
@@ -69,6 +69,12 @@ impl StrideStakingDerivativesOracleHelper {
     pub fn query_config(&self, app: &App) -> StdResult<CommonConfigResponse> {
         QueryMsg::GetConfig {}.test_query(&self.0, app)
     }
+    pub fn query_rate_history(&self, app: &App, key: &str) -> StdResult<RateHistoryResponse> {
+        QueryMsg::GetRateHistory {
+            key: key.to_string(),
+        }
+        .test_query(&self.0, app)
+    }
 }
 
 #[cfg(test)]
@@ -80,6 +86,7 @@ mod test {
     use multi_test_helpers::Asserter;
     use shade_oracles::{
         error::CommonOracleError,
+        interfaces::derivatives::stride::StrideStakingDerivativesOracle as StrideOracle,
         interfaces::{common::OraclePrice, providers::ReferenceData},
         unit_test_interface::prices::PricesFixture,
     };
@@ -105,6 +112,8 @@ mod test {
             initial_rate: Decimal256::from_str(rate).unwrap(),
             rate_timeout,
             rate_max_change: Decimal256::from_str(rate_max_change).unwrap(),
+            rate_max_annual_growth: None,
+            allow_rate_decrease: None,
         }
     }
 
@@ -149,7 +158,7 @@ mod test {
             ),
             (
                 "stkd-FRAX".to_string(),
-                Decimal256::from_str("1.48").unwrap(),
+                Decimal256::from_str("1.52").unwrap(),
             ),
         ];
         let okay_rate_update = DerivativeUpdates::Rates(okay_rates.clone());
@@ -180,6 +189,153 @@ mod test {
         Asserter::equal_vecs(&actual_rates, &expected_rates);
     }
 
+    #[test]
+    fn test_rate_limits() {
+        let prices = PricesFixture::basic_prices_2();
+        let TestScenario {
+            mut app,
+            admin,
+            user,
+            router,
+            admin_auth,
+            ..
+        } = TestScenario::new(prices);
+        let app = &mut app;
+        let oracle = StrideStakingDerivativesOracleHelper::init(&user, app, &router.into());
+        let mut eth = create_derivative_data("stkd-ETH", "ETH", "1.1", 10_000_000, "0.5");
+        eth.rate_max_annual_growth = Some(Decimal256::percent(20));
+        let osmo = create_derivative_data("stkd-OSMO", "OSMO", "1.2", 10_000_000, "0.5");
+        oracle.set_derivatives(&admin, app, &[eth, osmo]).unwrap();
+
+        let rate_bot = User::new("bot");
+        admin_auth.register_admin(&admin, app, rate_bot.str());
+        admin_auth.grant_access(
+            &admin,
+            app,
+            rate_bot.str(),
+            vec![BotPermission::UpdateRates.to_string()],
+        );
+        let update_rate = |app: &mut App, key: &str, rate: &str| {
+            oracle.update_derivatives(
+                &rate_bot,
+                app,
+                DerivativeUpdates::Rates(vec![(
+                    key.to_string(),
+                    Decimal256::from_str(rate).unwrap(),
+                )]),
+            )
+        };
+
+        // 20% a year allows 1.1 to grow by 0.11 in half a year.
+        app.update_block(|b| b.time = b.time.plus_seconds(StrideOracle::YEAR / 2));
+        assert!(update_rate(app, "stkd-ETH", "1.22").is_err());
+        update_rate(app, "stkd-ETH", "1.21").unwrap();
+        // Small updates can't walk the rate up.
+        assert!(update_rate(app, "stkd-ETH", "1.2101").is_err());
+
+        assert!(update_rate(app, "stkd-ETH", "1.2").is_err());
+        let allow_decrease = DerivativeUpdates::Config(vec![(
+            "stkd-ETH".to_string(),
+            DerivativeDataConfigUpdate::new(None, None, None, None, Some(true)),
+        )]);
+        assert!(oracle
+            .update_derivatives(&user, app, allow_decrease.clone())
+            .is_err());
+        oracle
+            .update_derivatives(&admin, app, allow_decrease)
+            .unwrap();
+        update_rate(app, "stkd-ETH", "1.2").unwrap();
+
+        let history = oracle.query_rate_history(app, "stkd-ETH").unwrap();
+        let rates = history
+            .iter()
+            .map(|h| h.value.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(rates, vec!["1.1", "1.21", "1.2"]);
+        assert_eq!(
+            history[0].timestamp + StrideOracle::YEAR / 2,
+            history[2].timestamp
+        );
+
+        // History is bounded.
+        for i in 1..=StrideOracle::MAX_RATE_HISTORY {
+            update_rate(app, "stkd-OSMO", &format!("1.2{i:02}")).unwrap();
+        }
+        let history = oracle.query_rate_history(app, "stkd-OSMO").unwrap();
+        assert_eq!(history.len(), StrideOracle::MAX_RATE_HISTORY);
+        assert_eq!(history[0].value, Decimal256::from_str("1.201").unwrap());
+        assert!(oracle.query_rate_history(app, "stkd-FRAX").is_err());
+
+        // Clearing the growth limit leaves the rate bound by its max change only.
+        assert!(update_rate(app, "stkd-ETH", "1.5").is_err());
+        let clear_growth = DerivativeUpdates::Config(vec![(
+            "stkd-ETH".to_string(),
+            DerivativeDataConfigUpdate::new(None, None, None, Some(None), None),
+        )]);
+        oracle
+            .update_derivatives(&admin, app, clear_growth)
+            .unwrap();
+        update_rate(app, "stkd-ETH", "1.5").unwrap();
+    }
+
+    #[test]
+    fn test_update_rate_max_change() {
+        let prices = PricesFixture::basic_prices_2();
+        let TestScenario {
+            mut app,
+            admin,
+            user,
+            router,
+            admin_auth,
+            ..
+        } = TestScenario::new(prices);
+        let app = &mut app;
+        let oracle = StrideStakingDerivativesOracleHelper::init(&user, app, &router.into());
+        let osmo = create_derivative_data("stkd-OSMO", "OSMO", "1.2", 10_000_000, "0.1");
+        oracle.set_derivatives(&admin, app, &[osmo]).unwrap();
+
+        let rate_bot = User::new("bot");
+        admin_auth.register_admin(&admin, app, rate_bot.str());
+        admin_auth.grant_access(
+            &admin,
+            app,
+            rate_bot.str(),
+            vec![BotPermission::UpdateRates.to_string()],
+        );
+        let update_rate = |app: &mut App, rate: &str| {
+            oracle.update_derivatives(
+                &rate_bot,
+                app,
+                DerivativeUpdates::Rates(vec![(
+                    "stkd-OSMO".to_string(),
+                    Decimal256::from_str(rate).unwrap(),
+                )]),
+            )
+        };
+        assert!(update_rate(app, "1.5").is_err());
+
+        let max_change = DerivativeUpdates::Config(vec![(
+            "stkd-OSMO".to_string(),
+            DerivativeDataConfigUpdate::new(
+                None,
+                None,
+                Some(Decimal256::from_str("0.5").unwrap()),
+                None,
+                None,
+            ),
+        )]);
+        assert!(oracle
+            .update_derivatives(&user, app, max_change.clone())
+            .is_err());
+        oracle.update_derivatives(&admin, app, max_change).unwrap();
+        let derivatives = oracle.query_derivatives(app).unwrap();
+        assert_eq!(
+            derivatives[0].rate.max_change,
+            Decimal256::from_str("0.5").unwrap()
+        );
+        update_rate(app, "1.5").unwrap();
+    }
+
     #[test]
     fn test_registry() {
         let prices = PricesFixture::basic_prices_2();
@@ -248,6 +404,8 @@ mod test {
                 Some(PricesFixture::XAU.to_string()),
                 Some(new_derivative.rate.timeout),
                 Some(new_derivative.rate.max_change),
+                None,
+                None,
             ),
         )]);
         oracle
@@ -354,4 +512,69 @@ mod test {
         app.update_block(|b| b.time = b.time.plus_seconds(3u64));
         assert!(router.query_price(app, "stkd-ETH".into()).is_err());
     }
+
+    #[test]
+    fn test_routing() {
+        let prices = PricesFixture::basic_prices_2();
+        let TestScenario {
+            mut app,
+            admin,
+            user,
+            router,
+            ..
+        } = TestScenario::new(prices);
+        let app = &mut app;
+        let oracle = StrideStakingDerivativesOracleHelper::init(&user, app, &router.clone().into());
+        let derivatives = vec![
+            create_derivative_data("stkd-ETH", "ETH", "1.1", 1, "0.1"),
+            create_derivative_data("stkd2-ETH", "stkd-ETH", "1.1", 1, "0.1"),
+            create_derivative_data("stkd3-ETH", "stkd2-ETH", "1.1", 1, "0.1"),
+            create_derivative_data("stkd4-ETH", "stkd3-ETH", "1.1", 1, "0.1"),
+            create_derivative_data("stkd5-ETH", "stkd4-ETH", "1.1", 1, "0.1"),
+            create_derivative_data("ETH", "stkd-ETH", "1", 1, "0.1"),
+        ];
+        oracle.set_derivatives(&admin, app, &derivatives).unwrap();
+        for key in ["stkd-ETH", "stkd2-ETH", "stkd3-ETH", "stkd4-ETH"] {
+            router
+                .set_keys(&admin, app, oracle.0.clone().into(), vec![key.to_string()])
+                .unwrap();
+        }
+
+        let eth_price = router.query_price(app, PricesFixture::ETH.into()).unwrap();
+        let expected_price = (0..4).fold(eth_price.data.rate, |rate, _| {
+            rate * derivatives[0].initial_rate
+        });
+        let actual_price = router.query_price(app, "stkd4-ETH".into()).unwrap();
+        assert_eq!(expected_price, actual_price.data.rate);
+
+        // ETH -> stkd-ETH -> ETH
+        let err = router
+            .set_keys(
+                &admin,
+                app,
+                oracle.0.clone().into(),
+                vec!["ETH".to_string()],
+            )
+            .unwrap_err();
+        assert!(err.root_cause().to_string().contains("loops back"));
+        // stkd5-ETH -> stkd4-ETH -> stkd3-ETH -> stkd2-ETH -> stkd-ETH -> ETH
+        let err = router
+            .set_keys(
+                &admin,
+                app,
+                oracle.0.clone().into(),
+                vec!["stkd5-ETH".to_string()],
+            )
+            .unwrap_err();
+        assert!(err.root_cause().to_string().contains("max routing depth"));
+
+        // Loops made after the keys are registered are stopped by the depth of the query.
+        let loop_update = DerivativeUpdates::Config(vec![(
+            "stkd-ETH".to_string(),
+            DerivativeDataConfigUpdate::new(Some("stkd2-ETH".to_string()), None, None, None, None),
+        )]);
+        oracle.update_derivatives(&admin, app, loop_update).unwrap();
+        let err = router.query_price(app, "stkd-ETH".into()).unwrap_err();
+        assert!(err.to_string().contains("max routing depth"));
+    }
 }
@@ -126,6 +126,22 @@ impl GenericLiquidityPairOracleHelper {
     pub fn query_config(&self, app: &App) -> StdResult<CommonConfigResponse> {
         QueryMsg::GetConfig {}.test_query(&self.0, app)
     }
+    pub fn query_price(&self, app: &App, key: &str) -> StdResult<PriceResponse> {
+        QueryMsg::GetPrice {
+            key: key.to_string(),
+        }
+        .test_query(&self.0, app)
+    }
+    pub fn query_reference_check(
+        &self,
+        app: &App,
+        key: &str,
+    ) -> StdResult<ReferenceCheckResponse> {
+        QueryMsg::GetReferenceCheck {
+            key: key.to_string(),
+        }
+        .test_query(&self.0, app)
+    }
 }
 
 #[cfg(test)]
@@ -220,6 +236,8 @@ mod test {
                 base_token: RawAsset::new(base_token.0.clone(), base_symbol.clone()),
                 key: symbol.clone(),
                 pair: siennaswap_pair.clone().into(),
+                pricing_mode: None,
+                market_guard: None,
             };
 
             let market_oracle = GenericLiquidityPairOracleHelper::init_siennaswap_market(
@@ -9,9 +9,12 @@ use super::*;
 mod test {
     use super::*;
     use crate::{mocks::MockShadeswapPair, helpers::router::OracleRouterHelper, harness::derivatives::shade};
+    use crate::helpers::index::IndexOracleHelper;
+    use shade_oracles::better_secret_math::asserter::MathAsserter;
     use multi_test_helpers::Asserter;
     use oracle_mocks::shadeswap_pair::contract as mock_shade_pair;
     use shade_oracles::{unit_test_interface::prices::PricesFixture, interfaces::providers::RawProvider};
+    use shade_oracles::protocols::shadeswap::{StablePairInfoResponse, StableParams, StableTokenData};
 
     fn create_mock_lp_token(admin: &User, app: &mut App) -> (String, Snip20Helper) {
         let mock_liquidity_token_key = "MOCKLP";
@@ -76,6 +79,8 @@ mod test {
             base_token: frax.to_raw_asset(token_a_symbol),
             target_token: usdc.to_raw_asset(token_b_symbol),
             pair: shade_pair.clone().into(),
+            pricing_mode: None,
+            market_guard: None,
         };
 
         let (_, lp_token) = create_mock_lp_token(&admin, &mut app);
@@ -101,6 +106,573 @@ mod test {
         assert_eq!(lp_price.data.rate, Uint256::from_u128(2 * 10u128.pow(18)));
     }
 
+    #[test]
+    fn test_fair_spot_oracle() {
+        let TestScenario {
+            mut app,
+            router,
+            admin,
+            user,
+            tokens,
+            provider,
+            ..
+        } = TestScenario::new(PricesFixture::basic_prices_2());
+
+        let spot_oracle = GenericLiquidityPairOracleHelper::init_shadeswap_spot(
+            &admin,
+            &mut app,
+            &router.clone().into(),
+        );
+        let market_oracle = GenericLiquidityPairOracleHelper::init_shadeswap_market(
+            &admin,
+            &mut app,
+            &router.clone().into(),
+        );
+        let shade_pair = mock_shade_pair::InstantiateMsg {}
+            .test_init(
+                MockShadeswapPair::default(),
+                &mut app,
+                admin.addr(),
+                "shade_pair",
+                &[],
+            )
+            .unwrap();
+
+        let new_prices = vec![("FRAX", 10u128.pow(18)), ("USDC", 10u128.pow(18))];
+        let (_, new_prices) = OracleCore::create_prices_hashmap(new_prices);
+        provider.update_band_prices(&admin, &mut app, new_prices, None);
+
+        let frax = tokens.get("FRAX").unwrap();
+        let usdc = tokens.get("USDC").unwrap();
+        let (_, lp_token) = create_mock_lp_token(&admin, &mut app);
+        lp_token.add_minters(&mut app, &admin, vec![shade_pair.address.to_string()]);
+
+        let spot_key = "FRAX-USDC".to_string();
+        let fair_key = "FRAX-USDC (Fair)".to_string();
+        let pair_data = |key: &String, pricing_mode| RawPairData {
+            key: key.clone(),
+            base_token: frax.to_raw_asset("FRAX"),
+            target_token: usdc.to_raw_asset("USDC"),
+            pair: shade_pair.clone().into(),
+            pricing_mode,
+            market_guard: None,
+        };
+        spot_oracle
+            .set_pairs(
+                &admin,
+                &mut app,
+                vec![
+                    pair_data(&spot_key, None),
+                    pair_data(&fair_key, Some(LpPricingMode::Fair)),
+                ],
+            )
+            .unwrap();
+        assert!(market_oracle
+            .set_pairs(
+                &admin,
+                &mut app,
+                vec![pair_data(&fair_key, Some(LpPricingMode::Fair))],
+            )
+            .is_err());
+        let pairs = spot_oracle.query_pairs(&app).unwrap();
+        assert!(pairs
+            .iter()
+            .any(|pair| pair.key == fair_key && pair.pricing_mode == LpPricingMode::Fair));
+        assert!(pairs
+            .iter()
+            .any(|pair| pair.key == spot_key && pair.pricing_mode == LpPricingMode::Spot));
+
+        let mock_pool = |app: &mut App, amount_frax: u128, amount_usdc: u128| {
+            mock_shade_pair::ExecuteMsg::MockPool {
+                token_a: frax.clone().into(),
+                amount_a: Uint128::new(amount_frax),
+                token_b: usdc.clone().into(),
+                amount_b: Uint128::new(amount_usdc),
+                liquidity_token: lp_token.clone().into(),
+                liquidity_tokens: Uint128::new(10u128.pow(6)),
+            }
+            .test_exec(&shade_pair, app, user.addr(), &[])
+            .unwrap();
+        };
+
+        // Balanced reserves are priced the same way by both modes.
+        mock_pool(&mut app, 10u128.pow(6), 10u128.pow(6));
+        let spot_price = spot_oracle.query_price(&app, &spot_key).unwrap();
+        let fair_price = spot_oracle.query_price(&app, &fair_key).unwrap();
+        assert_eq!(spot_price.data.rate, Uint256::from_u128(2 * 10u128.pow(18)));
+        assert_eq!(fair_price.data.rate, Uint256::from_u128(2 * 10u128.pow(18)));
+
+        // Reserves imbalanced by a swap (same k) only inflate the spot price.
+        mock_pool(&mut app, 4 * 10u128.pow(6), 25 * 10u128.pow(4));
+        let spot_price = spot_oracle.query_price(&app, &spot_key).unwrap();
+        let fair_price = spot_oracle.query_price(&app, &fair_key).unwrap();
+        assert_eq!(spot_price.data.rate, Uint256::from_u128(425 * 10u128.pow(16)));
+        assert_eq!(fair_price.data.rate, Uint256::from_u128(2 * 10u128.pow(18)));
+    }
+
+    #[test]
+    fn test_stable_pair_oracles() {
+        let TestScenario {
+            mut app,
+            router,
+            admin,
+            user,
+            tokens,
+            provider,
+            ..
+        } = TestScenario::new(PricesFixture::basic_prices_2());
+
+        let spot_oracle = GenericLiquidityPairOracleHelper::init_shadeswap_spot(
+            &admin,
+            &mut app,
+            &router.clone().into(),
+        );
+        let market_oracle = GenericLiquidityPairOracleHelper::init_shadeswap_market(
+            &admin,
+            &mut app,
+            &router.clone().into(),
+        );
+        let shade_pair = mock_shade_pair::InstantiateMsg {}
+            .test_init(
+                MockShadeswapPair::default(),
+                &mut app,
+                admin.addr(),
+                "shade_pair",
+                &[],
+            )
+            .unwrap();
+
+        let new_prices = vec![("FRAX", 10u128.pow(18)), ("USDC", 10u128.pow(18))];
+        let (_, new_prices) = OracleCore::create_prices_hashmap(new_prices);
+        provider.update_band_prices(&admin, &mut app, new_prices, None);
+
+        let frax = tokens.get("FRAX").unwrap();
+        let usdc = tokens.get("USDC").unwrap();
+        let (_, lp_token) = create_mock_lp_token(&admin, &mut app);
+        lp_token.add_minters(&mut app, &admin, vec![shade_pair.address.to_string()]);
+
+        let stable_token_data = |oracle_key: &str| StableTokenData {
+            oracle_key: oracle_key.to_string(),
+            decimals: 6,
+        };
+        let stable_info = StablePairInfoResponse {
+            stable_params: StableParams {
+                a: Decimal256::from_ratio(10u128, 1u128),
+                gamma1: Uint256::from_u128(4),
+                gamma2: Uint256::from_u128(6),
+                oracle: router.clone().into(),
+                min_trade_size_x_for_y: Decimal256::percent(1),
+                min_trade_size_y_for_x: Decimal256::percent(1),
+                max_price_impact_allowed: Decimal256::percent(50),
+                custom_iteration_controls: None,
+            },
+            stable_token0_data: stable_token_data("FRAX"),
+            stable_token1_data: stable_token_data("USDC"),
+            p: None,
+        };
+        // Imbalanced reserves worth $2 in total.
+        mock_shade_pair::ExecuteMsg::MockStablePool {
+            token_a: frax.clone().into(),
+            amount_a: Uint128::new(19 * 10u128.pow(5)),
+            token_b: usdc.clone().into(),
+            amount_b: Uint128::new(10u128.pow(5)),
+            liquidity_token: lp_token.clone().into(),
+            liquidity_tokens: Uint128::new(10u128.pow(6)),
+            stable_info,
+        }
+        .test_exec(&shade_pair, &mut app, user.addr(), &[])
+        .unwrap();
+
+        let lp_key = "FRAX-USDC".to_string();
+        let market_key = "USDC (Stable)".to_string();
+        let pair_data = |key: &String, pricing_mode| RawPairData {
+            key: key.clone(),
+            base_token: frax.to_raw_asset("FRAX"),
+            target_token: usdc.to_raw_asset("USDC"),
+            pair: shade_pair.clone().into(),
+            pricing_mode,
+            market_guard: None,
+        };
+        spot_oracle
+            .set_pairs(
+                &admin,
+                &mut app,
+                vec![pair_data(&lp_key, Some(LpPricingMode::Fair))],
+            )
+            .unwrap();
+        market_oracle
+            .set_pairs(&admin, &mut app, vec![pair_data(&market_key, None)])
+            .unwrap();
+
+        // The LP token is valued by the invariant regardless of its pricing mode,
+        // which is less than the sum of the reserves while the pool is imbalanced.
+        // The larger gamma of the pair lowers it further.
+        let lp_price = spot_oracle.query_price(&app, &lp_key).unwrap();
+        assert_eq!(lp_price.data.rate, Uint256::from_u128(1172264652063830665));
+
+        // Stableswap simulation swaps 1:1 instead of following the constant product curve.
+        let price = market_oracle.query_price(&app, &market_key).unwrap();
+        assert_eq!(price.data.rate, Uint256::from_u128(10u128.pow(18)));
+    }
+
+    #[test]
+    fn test_market_guard() {
+        let TestScenario {
+            mut app,
+            router,
+            admin,
+            user,
+            tokens,
+            provider,
+            ..
+        } = TestScenario::new(PricesFixture::basic_prices_2());
+
+        let spot_oracle = GenericLiquidityPairOracleHelper::init_shadeswap_spot(
+            &admin,
+            &mut app,
+            &router.clone().into(),
+        );
+        let market_oracle = GenericLiquidityPairOracleHelper::init_shadeswap_market(
+            &admin,
+            &mut app,
+            &router.clone().into(),
+        );
+        let shade_pair = mock_shade_pair::InstantiateMsg {}
+            .test_init(
+                MockShadeswapPair::default(),
+                &mut app,
+                admin.addr(),
+                "shade_pair",
+                &[],
+            )
+            .unwrap();
+
+        let new_prices = vec![("USDC", 10u128.pow(18))];
+        let (_, new_prices) = OracleCore::create_prices_hashmap(new_prices);
+        provider.update_band_prices(&admin, &mut app, new_prices, None);
+
+        let frax = tokens.get("FRAX").unwrap();
+        let usdc = tokens.get("USDC").unwrap();
+        let (_, lp_token) = create_mock_lp_token(&admin, &mut app);
+        lp_token.add_minters(&mut app, &admin, vec![shade_pair.address.to_string()]);
+
+        // $100 of USDC liquidity.
+        mock_shade_pair::ExecuteMsg::MockPool {
+            token_a: frax.clone().into(),
+            amount_a: Uint128::new(100 * 10u128.pow(6)),
+            token_b: usdc.clone().into(),
+            amount_b: Uint128::new(100 * 10u128.pow(6)),
+            liquidity_token: lp_token.clone().into(),
+            liquidity_tokens: Uint128::new(10u128.pow(6)),
+        }
+        .test_exec(&shade_pair, &mut app, user.addr(), &[])
+        .unwrap();
+
+        let key = "FRAX".to_string();
+        let pair_data = |market_guard| RawPairData {
+            key: key.clone(),
+            base_token: usdc.to_raw_asset("USDC"),
+            target_token: frax.to_raw_asset("FRAX"),
+            pair: shade_pair.clone().into(),
+            pricing_mode: None,
+            market_guard,
+        };
+        let guard = |min_liquidity: u128, trade_size: u128| MarketGuard {
+            min_liquidity: Some(Uint256::from_u128(min_liquidity * 10u128.pow(18))),
+            max_price_impact: Some(Decimal256::percent(5)),
+            trade_size: Some(Uint128::new(trade_size * 10u128.pow(6))),
+            reference: None,
+        };
+
+        assert!(spot_oracle
+            .set_pairs(&admin, &mut app, vec![pair_data(Some(guard(50, 1)))])
+            .is_err());
+        let invalid_guard = MarketGuard {
+            trade_size: Some(Uint128::zero()),
+            ..Default::default()
+        };
+        assert!(market_oracle
+            .set_pairs(&admin, &mut app, vec![pair_data(Some(invalid_guard))])
+            .is_err());
+
+        // Pool is too thin.
+        market_oracle
+            .set_pairs(&admin, &mut app, vec![pair_data(Some(guard(1000, 1)))])
+            .unwrap();
+        let err = market_oracle.query_price(&app, &key).unwrap_err();
+        assert!(err.to_string().contains("below the minimum"));
+
+        // Selling 10 FRAX moves the price by ~9%.
+        market_oracle
+            .set_pairs(&admin, &mut app, vec![pair_data(Some(guard(50, 10)))])
+            .unwrap();
+        let err = market_oracle.query_price(&app, &key).unwrap_err();
+        assert!(err.to_string().contains("price impact"));
+
+        // Selling 1 FRAX moves the price by ~1%.
+        market_oracle
+            .set_pairs(&admin, &mut app, vec![pair_data(Some(guard(50, 1)))])
+            .unwrap();
+        let price = market_oracle.query_price(&app, &key).unwrap();
+        assert_eq!(price.data.rate, Uint256::from_u128(990100 * 10u128.pow(12)));
+        let pairs = market_oracle.query_pairs(&app).unwrap();
+        assert_eq!(pairs[0].market_guard, Some(guard(50, 1)));
+
+        // Only the price impact check sells the trade size, the price is still derived from selling 1 FRAX.
+        let large_trade = MarketGuard {
+            max_price_impact: Some(Decimal256::percent(10)),
+            ..guard(50, 10)
+        };
+        market_oracle
+            .set_pairs(&admin, &mut app, vec![pair_data(Some(large_trade))])
+            .unwrap();
+        let price = market_oracle.query_price(&app, &key).unwrap();
+        assert_eq!(price.data.rate, Uint256::from_u128(990100 * 10u128.pow(12)));
+
+        // $2000 of USDC against 1 FRAX worth ~$1000, so the FRAX side is the thin one.
+        mock_shade_pair::ExecuteMsg::MockPool {
+            token_a: frax.clone().into(),
+            amount_a: Uint128::new(10u128.pow(6)),
+            token_b: usdc.clone().into(),
+            amount_b: Uint128::new(2000 * 10u128.pow(6)),
+            liquidity_token: lp_token.clone().into(),
+            liquidity_tokens: Uint128::new(10u128.pow(6)),
+        }
+        .test_exec(&shade_pair, &mut app, user.addr(), &[])
+        .unwrap();
+        let min_liquidity = |min_liquidity: u128| MarketGuard {
+            min_liquidity: Some(Uint256::from_u128(min_liquidity * 10u128.pow(18))),
+            ..Default::default()
+        };
+        market_oracle
+            .set_pairs(&admin, &mut app, vec![pair_data(Some(min_liquidity(1500)))])
+            .unwrap();
+        let err = market_oracle.query_price(&app, &key).unwrap_err();
+        assert!(err.to_string().contains("below the minimum"));
+        market_oracle
+            .set_pairs(&admin, &mut app, vec![pair_data(Some(min_liquidity(900)))])
+            .unwrap();
+        assert!(market_oracle.query_price(&app, &key).is_ok());
+    }
+
+    #[test]
+    fn test_routing_cycle() {
+        let TestScenario {
+            mut app,
+            router,
+            admin,
+            user,
+            tokens,
+            ..
+        } = TestScenario::new(PricesFixture::basic_prices_2());
+
+        let market_oracle = GenericLiquidityPairOracleHelper::init_shadeswap_market(
+            &admin,
+            &mut app,
+            &router.clone().into(),
+        );
+        let shade_pair = mock_shade_pair::InstantiateMsg {}
+            .test_init(
+                MockShadeswapPair::default(),
+                &mut app,
+                admin.addr(),
+                "shade_pair",
+                &[],
+            )
+            .unwrap();
+
+        let frax = tokens.get("FRAX").unwrap();
+        let usdc = tokens.get("USDC").unwrap();
+        let (_, lp_token) = create_mock_lp_token(&admin, &mut app);
+        lp_token.add_minters(&mut app, &admin, vec![shade_pair.address.to_string()]);
+        mock_shade_pair::ExecuteMsg::MockPool {
+            token_a: frax.clone().into(),
+            amount_a: Uint128::new(100 * 10u128.pow(6)),
+            token_b: usdc.clone().into(),
+            amount_b: Uint128::new(100 * 10u128.pow(6)),
+            liquidity_token: lp_token.clone().into(),
+            liquidity_tokens: Uint128::new(10u128.pow(6)),
+        }
+        .test_exec(&shade_pair, &mut app, user.addr(), &[])
+        .unwrap();
+
+        // FRAX is priced with USDC through the router.
+        let key = "FRAX".to_string();
+        market_oracle
+            .set_pairs(
+                &admin,
+                &mut app,
+                vec![RawPairData {
+                    key: key.clone(),
+                    base_token: usdc.to_raw_asset("USDC"),
+                    target_token: frax.to_raw_asset("FRAX"),
+                    pair: shade_pair.clone().into(),
+                    pricing_mode: None,
+                    market_guard: None,
+                }],
+            )
+            .unwrap();
+        router
+            .set_keys(
+                &admin,
+                &mut app,
+                market_oracle.0.clone().into(),
+                vec![key.clone()],
+            )
+            .unwrap();
+
+        // Pricing USDC with an index of FRAX would route USDC through itself.
+        let index_oracle = IndexOracleHelper::init(
+            &admin,
+            &mut app,
+            &router.clone().into(),
+            &[(key.clone(), Decimal256::one())],
+            Uint256::from_u128(10u128.pow(18)),
+            "USDC",
+            3600,
+            Decimal256::percent(10),
+        );
+        let err = router
+            .set_keys(
+                &admin,
+                &mut app,
+                index_oracle.0.clone().into(),
+                vec!["USDC".to_string()],
+            )
+            .unwrap_err();
+        assert!(err.root_cause().to_string().contains("loops back"));
+        let err = router
+            .set_sources(
+                &admin,
+                &mut app,
+                "USDC",
+                vec![index_oracle.0.clone().into()],
+            )
+            .unwrap_err();
+        assert!(err.root_cause().to_string().contains("loops back"));
+        assert!(router.query_price(&app, key.clone()).is_ok());
+
+        // Routed sources are queried with the routing depth like any other routed oracle.
+        let index_oracle = IndexOracleHelper::init(
+            &admin,
+            &mut app,
+            &router.clone().into(),
+            &[(key.clone(), Decimal256::one())],
+            Uint256::from_u128(10u128.pow(18)),
+            "FRAX INDEX",
+            3600,
+            Decimal256::percent(10),
+        );
+        router
+            .set_sources(
+                &admin,
+                &mut app,
+                "FRAX INDEX",
+                vec![index_oracle.0.clone().into()],
+            )
+            .unwrap();
+        let price = router.query_price(&app, "FRAX INDEX".to_string()).unwrap();
+        MathAsserter::within_deviation(
+            Uint256::from_u128(10u128.pow(18)),
+            price.data.rate,
+            TestScenario::ERROR,
+        );
+    }
+
+    #[test]
+    fn test_reference_check() {
+        let TestScenario {
+            mut app,
+            router,
+            admin,
+            user,
+            tokens,
+            provider,
+            ..
+        } = TestScenario::new(PricesFixture::basic_prices_2());
+
+        let market_oracle = GenericLiquidityPairOracleHelper::init_shadeswap_market(
+            &admin,
+            &mut app,
+            &router.clone().into(),
+        );
+        let shade_pair = mock_shade_pair::InstantiateMsg {}
+            .test_init(
+                MockShadeswapPair::default(),
+                &mut app,
+                admin.addr(),
+                "shade_pair",
+                &[],
+            )
+            .unwrap();
+
+        // Band reports FRAX at $1.
+        let new_prices = vec![("USDC", 10u128.pow(18)), ("FRAX", 10u128.pow(18))];
+        let (_, new_prices) = OracleCore::create_prices_hashmap(new_prices);
+        provider.update_band_prices(&admin, &mut app, new_prices, None);
+
+        let frax = tokens.get("FRAX").unwrap();
+        let usdc = tokens.get("USDC").unwrap();
+        let (_, lp_token) = create_mock_lp_token(&admin, &mut app);
+        lp_token.add_minters(&mut app, &admin, vec![shade_pair.address.to_string()]);
+
+        let mock_pool = |app: &mut App, amount_frax: u128, amount_usdc: u128| {
+            mock_shade_pair::ExecuteMsg::MockPool {
+                token_a: frax.clone().into(),
+                amount_a: Uint128::new(amount_frax * 10u128.pow(6)),
+                token_b: usdc.clone().into(),
+                amount_b: Uint128::new(amount_usdc * 10u128.pow(6)),
+                liquidity_token: lp_token.clone().into(),
+                liquidity_tokens: Uint128::new(10u128.pow(6)),
+            }
+            .test_exec(&shade_pair, app, user.addr(), &[])
+            .unwrap();
+        };
+
+        let key = "FRAX (ShadeSwap FRAX/USDC)".to_string();
+        let pair_data = |reject| RawPairData {
+            key: key.clone(),
+            base_token: usdc.to_raw_asset("USDC"),
+            target_token: frax.to_raw_asset("FRAX"),
+            pair: shade_pair.clone().into(),
+            pricing_mode: None,
+            market_guard: Some(MarketGuard {
+                trade_size: Some(Uint128::new(10u128.pow(3))),
+                reference: Some(ReferenceCheck {
+                    key: "FRAX".to_string(),
+                    max_deviation: Decimal256::percent(10),
+                    reject,
+                }),
+                ..Default::default()
+            }),
+        };
+
+        market_oracle
+            .set_pairs(&admin, &mut app, vec![pair_data(true)])
+            .unwrap();
+        mock_pool(&mut app, 1_000_000, 1_000_000);
+        assert!(market_oracle.query_price(&app, &key).is_ok());
+        let check = market_oracle.query_reference_check(&app, &key).unwrap();
+        assert_eq!(check.reference_key, "FRAX");
+        assert_eq!(check.reference_price, Uint256::from_u128(10u128.pow(18)));
+        assert!(!check.flagged);
+
+        // Pool manipulated so FRAX trades at $2.
+        mock_pool(&mut app, 500_000, 1_000_000);
+        assert!(market_oracle.query_price(&app, &key).is_err());
+        let check = market_oracle.query_reference_check(&app, &key).unwrap();
+        assert!(check.flagged);
+        assert!(check.deviation > Decimal256::percent(99));
+
+        // Flagged prices are still served when the check doesn't reject them.
+        market_oracle
+            .set_pairs(&admin, &mut app, vec![pair_data(false)])
+            .unwrap();
+        let price = market_oracle.query_price(&app, &key).unwrap();
+        assert_eq!(price.data.rate, check.price);
+        assert!(market_oracle.query_reference_check(&app, &key).unwrap().flagged);
+    }
+
     #[test]
     fn test_registry() {
         let TestScenario {
@@ -163,6 +735,8 @@ mod test {
                 base_token: frax.to_raw_asset(&keys[0]),
                 target_token: usdc.to_raw_asset(&keys[1]),
                 pair: shade_pair_a.clone().into(),
+                pricing_mode: None,
+                market_guard: None,
             };
     
             let shade_pair_data_b = RawPairData {
@@ -170,6 +744,8 @@ mod test {
                 base_token: usdc.to_raw_asset(&keys[1]),
                 target_token: frax.to_raw_asset(&keys[0]),
                 pair: shade_pair_b.clone().into(),
+                pricing_mode: None,
+                market_guard: None,
             };
 
             let (_, lp_token) = create_mock_lp_token(&admin, &mut app);
@@ -345,6 +921,8 @@ mod test {
             base_token: RawAsset::new(base_token.0.clone(), base_symbol.clone()),
             key: symbol.clone(),
             pair: shade_pair.clone().into(),
+            pricing_mode: None,
+            market_guard: None,
         };
 
         market_oracle
@@ -94,10 +94,61 @@ impl IndexOracleHelper {
         )
     }
 
+    pub fn ramp_target(
+        &self,
+        sender: &User,
+        app: &mut App,
+        new_target: Uint256,
+        duration: u64,
+    ) -> AnyResult<AppResponse> {
+        sender.exec(
+            app,
+            &ExecuteMsg::Admin(AdminMsg::RampTarget {
+                new_target,
+                duration: duration.into(),
+            }),
+            &self.0,
+        )
+    }
+
+    pub fn cancel_ramp(&self, sender: &User, app: &mut App) -> AnyResult<AppResponse> {
+        sender.exec(app, &ExecuteMsg::Admin(AdminMsg::CancelRamp {}), &self.0)
+    }
+
     pub fn unfreeze(&self, sender: &User, app: &mut App) -> AnyResult<AppResponse> {
         sender.exec(app, &ExecuteMsg::Admin(AdminMsg::UnfreezePeg {}), &self.0)
     }
 
+    pub fn freeze(&self, sender: &User, app: &mut App) -> AnyResult<AppResponse> {
+        sender.exec(app, &ExecuteMsg::Admin(AdminMsg::FreezePeg {}), &self.0)
+    }
+
+    pub fn set_auto_unfreeze(
+        &self,
+        sender: &User,
+        app: &mut App,
+        policy: Option<AutoUnfreeze>,
+    ) -> AnyResult<AppResponse> {
+        sender.exec(
+            app,
+            &ExecuteMsg::Admin(AdminMsg::SetAutoUnfreeze(policy)),
+            &self.0,
+        )
+    }
+
+    pub fn set_stale_components(
+        &self,
+        sender: &User,
+        app: &mut App,
+        policy: Option<StaleComponents>,
+    ) -> AnyResult<AppResponse> {
+        sender.exec(
+            app,
+            &ExecuteMsg::Admin(AdminMsg::SetStaleComponents(policy)),
+            &self.0,
+        )
+    }
+
     pub fn compute_index(&self, sender: &User, app: &mut App) -> AnyResult<AppResponse> {
         sender.exec(app, &ExecuteMsg::ComputeIndex {}, &self.0)
     }
@@ -106,6 +157,25 @@ impl IndexOracleHelper {
         QueryMsg::GetBasket {}.test_query(&self.0, app)
     }
 
+    pub fn query_simulate_mod_basket(
+        &self,
+        app: &App,
+        basket: &[(String, Decimal256)],
+    ) -> StdResult<SimulationResponse> {
+        QueryMsg::SimulateModBasket {
+            basket: basket.to_vec(),
+        }
+        .test_query(&self.0, app)
+    }
+
+    pub fn query_simulate_update_target(
+        &self,
+        app: &App,
+        target: Uint256,
+    ) -> StdResult<SimulationResponse> {
+        QueryMsg::SimulateUpdateTarget { target }.test_query(&self.0, app)
+    }
+
     pub fn query_index_data(&self, app: &App) -> StdResult<IndexDataResponse> {
         QueryMsg::GetIndexData {}.test_query(&self.0, app)
     }
@@ -132,7 +202,13 @@ mod test {
     use super::*;
     use shade_oracles::{
         better_secret_math::asserter::MathAsserter,
-        interfaces::{common::OraclePrice, index::SIX_HOURS},
+        interfaces::{
+            common::OraclePrice,
+            index::{
+                AutoUnfreeze, ComponentHealth, FreezeReason, Recovery, StaleComponents, StaleMode,
+                SIX_HOURS,
+            },
+        },
         unit_test_interface::prices::generate_price_feed,
     };
     use std::{convert::TryInto, str::FromStr};
@@ -234,8 +310,12 @@ mod test {
         index_oracle.compute_index(&admin, &mut app).unwrap();
         let price = router.query_price(&app, symbol.clone()).unwrap();
         MathAsserter::within_deviation(target, price.data.rate, TestScenario::ERROR);
+        // Frozen price keeps the timestamps of when it was last computed.
+        assert_eq!(price.data.last_updated_base, 0);
+        assert_eq!(price.data.last_updated_quote, 0);
         let info = index_oracle.query_index_data(&app).unwrap();
         assert!(info.peg.frozen);
+        assert_eq!(info.peg.frozen_reason, Some(FreezeReason::StaleFeeds));
         assert_eq!(info.peg.last_updated, Uint64::zero());
         assert!(index_oracle.unfreeze(&admin, &mut app).is_err());
 
@@ -249,9 +329,95 @@ mod test {
 
         let price = router.query_price(&app, symbol.clone()).unwrap();
         MathAsserter::within_deviation(target, price.data.rate, TestScenario::ERROR);
+        assert_eq!(price.data.last_updated_base, t2);
         let info = index_oracle.query_index_data(&app).unwrap();
         assert!(!info.peg.frozen);
+        assert_eq!(info.peg.frozen_reason, None);
         assert_eq!(info.peg.last_updated, Uint64::new(t2));
+
+        // Admin can freeze the peg manually
+        assert!(index_oracle.freeze(&user, &mut app).is_err());
+        index_oracle.freeze(&admin, &mut app).unwrap();
+        app.update_block(|b| b.time = b.time.plus_seconds(60));
+        let price = router.query_price(&app, symbol.clone()).unwrap();
+        assert_eq!(price.data.last_updated_base, t2);
+        let info = index_oracle.query_index_data(&app).unwrap();
+        assert!(info.peg.frozen);
+        assert_eq!(info.peg.frozen_reason, Some(FreezeReason::Manual));
+    }
+
+    #[test]
+    fn test_auto_unfreeze() {
+        let prices: Vec<(String, Uint128)> = feed_0()
+            .iter()
+            .map(|p| (p.key.clone(), p.data.rate.try_into().unwrap()))
+            .collect();
+        let TestScenario {
+            mut app,
+            router,
+            admin,
+            provider,
+            user,
+            ..
+        } = TestScenario::new(prices);
+        let target = Uint256::from_u128(105 * 10u128.pow(16));
+        let symbol = "SILK".to_string();
+        let basket = basic_basket();
+        let index_oracle = IndexOracleHelper::init(
+            &admin,
+            &mut app,
+            &router.into(),
+            &basket,
+            target,
+            &symbol,
+            SIX_HOURS,
+            Decimal256::percent(10),
+        );
+
+        let policy = AutoUnfreeze {
+            computations: 2,
+            cooldown: 60,
+        };
+        assert!(index_oracle
+            .set_auto_unfreeze(&user, &mut app, Some(policy.clone()))
+            .is_err());
+        assert!(index_oracle
+            .set_auto_unfreeze(
+                &admin,
+                &mut app,
+                Some(AutoUnfreeze {
+                    computations: 0,
+                    cooldown: 60,
+                })
+            )
+            .is_err());
+        index_oracle
+            .set_auto_unfreeze(&admin, &mut app, Some(policy.clone()))
+            .unwrap();
+
+        // Band blip freezes the peg
+        let euro = |rate: u128| HashMap::from([("EURO".to_string(), Uint128::new(rate))]);
+        provider.update_band_prices(&admin, &mut app, euro(1_000_000 * 10u128.pow(18)), None);
+        index_oracle.compute_index(&user, &mut app).unwrap();
+        let info = index_oracle.query_index_data(&app).unwrap();
+        assert_eq!(info.peg.frozen_reason, Some(FreezeReason::Deviation));
+        assert_eq!(info.auto_unfreeze, Some(policy));
+
+        // Peg is unfrozen once prices have recovered for long enough
+        provider.update_band_prices(&admin, &mut app, euro(10196 * 10u128.pow(14)), None);
+        app.update_block(|b| b.time = b.time.plus_seconds(30));
+        index_oracle.compute_index(&user, &mut app).unwrap();
+        index_oracle.compute_index(&user, &mut app).unwrap();
+        let info = index_oracle.query_index_data(&app).unwrap();
+        assert!(info.peg.frozen);
+        assert_eq!(info.recovery.computations, 1);
+
+        app.update_block(|b| b.time = b.time.plus_seconds(30));
+        index_oracle.compute_index(&user, &mut app).unwrap();
+        let info = index_oracle.query_index_data(&app).unwrap();
+        assert!(!info.peg.frozen);
+        assert_eq!(info.recovery, Recovery::default());
+        MathAsserter::within_deviation(target, info.peg.value, TestScenario::ERROR);
     }
 
     #[test]
@@ -612,6 +778,16 @@ mod test {
 
         MathAsserter::within_deviation(expected_final, data.rate, TestScenario::ERROR);
 
+        let simulation = index_oracle
+            .query_simulate_mod_basket(&app, &mod_basket)
+            .unwrap();
+        assert_eq!(simulation.error, None);
+        MathAsserter::within_deviation(
+            expected_final,
+            simulation.peg.unwrap(),
+            TestScenario::ERROR,
+        );
+
         // Update basket
         assert!(index_oracle
             .mod_basket(&user, &mut app, &mod_basket)
@@ -624,6 +800,9 @@ mod test {
         let BasketResponse { mut basket } = index_oracle.query_basket(&app).unwrap();
         {
             basket.sort();
+            let mut simulated_basket = simulation.basket;
+            simulated_basket.sort();
+            assert_eq!(basket, simulated_basket);
             for (sym, w, _) in basket {
                 assert!(
                     expected_weights.contains(&(sym.clone(), w)),
@@ -640,6 +819,75 @@ mod test {
         MathAsserter::within_deviation(expected_final, price.data().rate, TestScenario::ERROR);
     }
 
+    #[test]
+    fn test_simulations() {
+        let prices: Vec<(String, Uint128)> = feed_0()
+            .iter()
+            .map(|p| (p.key.clone(), p.data.rate.try_into().unwrap()))
+            .collect();
+        let TestScenario {
+            mut app,
+            router,
+            admin,
+            ..
+        } = TestScenario::new(prices);
+        let target = Uint256::from_u128(105 * 10u128.pow(16));
+        let symbol = "SILK".to_string();
+        let basket = basic_basket();
+        let index_oracle = IndexOracleHelper::init(
+            &admin,
+            &mut app,
+            &router.into(),
+            &basket,
+            target,
+            &symbol,
+            SIX_HOURS,
+            Decimal256::percent(10),
+        );
+        let BasketResponse { basket } = index_oracle.query_basket(&app).unwrap();
+
+        let simulation = index_oracle
+            .query_simulate_mod_basket(&app, &[(symbol.clone(), Decimal256::percent(10))])
+            .unwrap();
+        assert!(simulation.basket.is_empty());
+        assert_eq!(simulation.peg, None);
+        assert!(simulation.error.unwrap().contains("Recursive symbol"));
+        let simulation = index_oracle
+            .query_simulate_mod_basket(&app, &[("USD".to_string(), Decimal256::percent(50))])
+            .unwrap();
+        assert!(simulation
+            .error
+            .unwrap()
+            .contains("Initial basket weights cannot be greater than 100%"));
+
+        // Fixed weights are scaled to the new target
+        let new_target = Uint256::from_u128(210 * 10u128.pow(16));
+        let simulation = index_oracle
+            .query_simulate_update_target(&app, new_target)
+            .unwrap();
+        assert_eq!(simulation.error, None);
+        assert_eq!(simulation.peg, Some(new_target));
+        for (symbol, initial, fixed) in &basket {
+            let (_, new_initial, new_fixed) = simulation
+                .basket
+                .iter()
+                .find(|(s, _, _)| s == symbol)
+                .unwrap();
+            assert_eq!(new_initial, initial);
+            let expected = *fixed * Decimal256::percent(200);
+            let error = Decimal256::new(Uint256::from_u128(10));
+            assert!(*new_fixed >= expected - error && *new_fixed <= expected + error);
+        }
+        // Nothing is saved
+        assert_eq!(index_oracle.query_basket(&app).unwrap().basket, basket);
+
+        index_oracle.freeze(&admin, &mut app).unwrap();
+        let simulation = index_oracle
+            .query_simulate_update_target(&app, new_target)
+            .unwrap();
+        assert!(simulation.error.unwrap().contains("frozen"));
+    }
+
     #[test]
     fn test_peg_deviation() {
         let prices: Vec<(String, Uint128)> = feed_2()
@@ -697,6 +945,189 @@ mod test {
         assert_eq!(resp.peg.last_value, price.data.rate);
         assert_eq!(resp.peg.value, price.data.rate);
         assert_eq!(resp.peg.frozen, true);
+        assert_eq!(resp.peg.frozen_reason, Some(FreezeReason::Deviation));
+    }
+
+    #[test]
+    fn test_stale_components() {
+        let prices: Vec<(String, Uint128)> = feed_0()
+            .iter()
+            .map(|p| (p.key.clone(), p.data.rate.try_into().unwrap()))
+            .collect();
+        let fresh_prices = prices
+            .iter()
+            .filter(|(symbol, _)| symbol != "JPY")
+            .cloned()
+            .collect::<HashMap<String, Uint128>>();
+        let TestScenario {
+            mut app,
+            router,
+            admin,
+            provider,
+            user,
+            ..
+        } = TestScenario::new(prices);
+        let target = Uint256::from_u128(105 * 10u128.pow(16));
+        let symbol = "SILK".to_string();
+        let basket = basic_basket();
+        let index_oracle = IndexOracleHelper::init(
+            &admin,
+            &mut app,
+            &router.clone().into(),
+            &basket,
+            target,
+            &symbol,
+            SIX_HOURS,
+            Decimal256::percent(10),
+        );
+
+        let policy = StaleComponents {
+            mode: StaleMode::Exclude,
+            max_stale_weight: Decimal256::percent(25),
+        };
+        assert!(index_oracle
+            .set_stale_components(&user, &mut app, Some(policy.clone()))
+            .is_err());
+        assert!(index_oracle
+            .set_stale_components(
+                &admin,
+                &mut app,
+                Some(StaleComponents {
+                    mode: StaleMode::Exclude,
+                    max_stale_weight: Decimal256::percent(100),
+                })
+            )
+            .is_err());
+        index_oracle
+            .set_stale_components(&admin, &mut app, Some(policy.clone()))
+            .unwrap();
+
+        // Every price but JPY gets updated
+        app.update_block(|b| b.time = b.time.plus_seconds(SIX_HOURS + 10));
+        provider.update_band_prices(&admin, &mut app, fresh_prices, None);
+        index_oracle.compute_index(&user, &mut app).unwrap();
+        let info = index_oracle.query_index_data(&app).unwrap();
+        assert!(!info.peg.frozen);
+        assert_eq!(info.stale_components, Some(policy));
+        MathAsserter::within_deviation(target, info.peg.value, TestScenario::ERROR);
+        for asset in info.basket {
+            if asset.symbol == "JPY" {
+                assert!(matches!(asset.health, ComponentHealth::Stale { .. }));
+            } else {
+                assert_eq!(asset.health, ComponentHealth::Fresh);
+            }
+        }
+
+        // JPY rejected by the router is excluded the same way
+        router
+            .set_max_ages(&admin, &mut app, vec![("JPY".to_string(), 3_600)])
+            .unwrap();
+        index_oracle.compute_index(&user, &mut app).unwrap();
+        let info = index_oracle.query_index_data(&app).unwrap();
+        assert!(!info.peg.frozen);
+        MathAsserter::within_deviation(target, info.peg.value, TestScenario::ERROR);
+        let jpy = info
+            .basket
+            .iter()
+            .find(|asset| asset.symbol == "JPY")
+            .unwrap();
+        assert_eq!(jpy.health, ComponentHealth::Unavailable);
+
+        // Without the policy the whole peg freezes
+        index_oracle
+            .set_stale_components(&admin, &mut app, None)
+            .unwrap();
+        index_oracle.compute_index(&user, &mut app).unwrap();
+        let info = index_oracle.query_index_data(&app).unwrap();
+        assert_eq!(info.peg.frozen_reason, Some(FreezeReason::StaleFeeds));
+    }
+
+    #[test]
+    fn test_ramp_target() {
+        let prices: Vec<(String, Uint128)> = feed_0()
+            .iter()
+            .map(|p| (p.key.clone(), p.data.rate.try_into().unwrap()))
+            .collect();
+        let TestScenario {
+            mut app,
+            router,
+            admin,
+            user,
+            ..
+        } = TestScenario::new(prices);
+        let target = Uint256::from_u128(105 * 10u128.pow(16));
+        let symbol = "SILK".to_string();
+        let basket = basic_basket();
+        let index_oracle = IndexOracleHelper::init(
+            &admin,
+            &mut app,
+            &router.clone().into(),
+            &basket,
+            target,
+            &symbol,
+            SIX_HOURS,
+            Decimal256::percent(10),
+        );
+        router
+            .set_keys(
+                &admin,
+                &mut app,
+                index_oracle.0.clone().into(),
+                vec![symbol.clone()],
+            )
+            .unwrap();
+
+        let new_target = Uint256::from_u128(110 * 10u128.pow(16));
+        assert!(index_oracle
+            .ramp_target(&user, &mut app, new_target, 100)
+            .is_err());
+        assert!(index_oracle
+            .ramp_target(&admin, &mut app, new_target, 0)
+            .is_err());
+        index_oracle
+            .ramp_target(&admin, &mut app, new_target, 100)
+            .unwrap();
+
+        app.update_block(|b| b.time = b.time.plus_seconds(50));
+        let halfway = Uint256::from_u128(1075 * 10u128.pow(15));
+        let price = router.query_price(&app, symbol.clone()).unwrap();
+        MathAsserter::within_deviation(halfway, price.data.rate, TestScenario::ERROR);
+        let info = index_oracle.query_index_data(&app).unwrap();
+        assert_eq!(info.peg.target, halfway);
+        assert_eq!(info.ramp.unwrap().progress, Decimal256::percent(50));
+
+        // New ramp starts from the target reached so far
+        let lower_target = Uint256::from_u128(100 * 10u128.pow(16));
+        index_oracle
+            .ramp_target(&admin, &mut app, lower_target, 100)
+            .unwrap();
+        app.update_block(|b| b.time = b.time.plus_seconds(50));
+        let info = index_oracle.query_index_data(&app).unwrap();
+        assert_eq!(info.ramp.unwrap().ramp.from, halfway);
+        let reached = Uint256::from_u128(10375 * 10u128.pow(14));
+        assert_eq!(info.peg.target, reached);
+
+        // Cancelling keeps the target reached
+        assert!(index_oracle.cancel_ramp(&user, &mut app).is_err());
+        index_oracle.cancel_ramp(&admin, &mut app).unwrap();
+        app.update_block(|b| b.time = b.time.plus_seconds(50));
+        let info = index_oracle.query_index_data(&app).unwrap();
+        assert!(info.ramp.is_none());
+        assert_eq!(info.peg.target, reached);
+        let price = router.query_price(&app, symbol.clone()).unwrap();
+        MathAsserter::within_deviation(reached, price.data.rate, TestScenario::ERROR);
+
+        // Updating the target cancels the ramp
+        index_oracle
+            .ramp_target(&admin, &mut app, new_target, 100)
+            .unwrap();
+        index_oracle
+            .update_target(&admin, &mut app, target)
+            .unwrap();
+        app.update_block(|b| b.time = b.time.plus_seconds(50));
+        let info = index_oracle.query_index_data(&app).unwrap();
+        assert!(info.ramp.is_none());
+        assert_eq!(info.peg.target, target);
     }
 
     #[test]
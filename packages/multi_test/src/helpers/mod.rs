@@ -10,4 +10,5 @@ pub mod derivatives;
 pub mod dex;
 pub mod index;
 pub mod router;
+pub mod twap;
 pub use common::*;
@@ -2,7 +2,7 @@ use super::*;
 use shade_oracles::{
     interfaces::{
         providers::RawProvider,
-        router::{msg::*, registry::ProtectedKeyInfo},
+        router::{aggregation::Aggregation, msg::*, registry::ProtectedKeyInfo},
     },
     status::ContractStatus,
 };
@@ -16,14 +16,26 @@ impl OracleRouterHelper {
         admin_auth: &Contract,
         provider: RawProvider,
         quote_symbol: &str,
+    ) -> Self {
+        Self::init_with_providers(user, app, admin_auth, vec![provider], None, quote_symbol)
+    }
+    pub fn init_with_providers(
+        user: &User,
+        app: &mut App,
+        admin_auth: &Contract,
+        providers: Vec<RawProvider>,
+        aggregation: Option<Aggregation>,
+        quote_symbol: &str,
     ) -> Self {
         let contract = user
             .init(
                 app,
                 &InstantiateMsg {
                     admin_auth: admin_auth.clone().into(),
-                    provider,
+                    providers,
                     quote_symbol: quote_symbol.to_string(),
+                    max_age: None,
+                    aggregation,
                 },
                 OracleRouter::default(),
                 "oracle_router",
@@ -84,6 +96,7 @@ impl OracleRouterHelper {
             RegistryOperation::SetKeys {
                 oracle: oracle.into(),
                 keys,
+                max_deviation: None,
             },
         )
     }
@@ -113,6 +126,70 @@ impl OracleRouterHelper {
     ) -> AnyResult<AppResponse> {
         self.update_registry(sender, app, RegistryOperation::RemoveProtection { keys })
     }
+    pub fn set_max_ages(
+        &self,
+        sender: &User,
+        app: &mut App,
+        max_ages: Vec<(String, u64)>,
+    ) -> AnyResult<AppResponse> {
+        self.update_registry(sender, app, RegistryOperation::SetMaxAges { max_ages })
+    }
+    pub fn remove_max_ages(
+        &self,
+        sender: &User,
+        app: &mut App,
+        keys: Vec<String>,
+    ) -> AnyResult<AppResponse> {
+        self.update_registry(sender, app, RegistryOperation::RemoveMaxAges { keys })
+    }
+    pub fn set_median_keys(
+        &self,
+        sender: &User,
+        app: &mut App,
+        keys: Vec<String>,
+    ) -> AnyResult<AppResponse> {
+        self.update_registry(sender, app, RegistryOperation::SetMedianKeys { keys })
+    }
+    pub fn remove_median_keys(
+        &self,
+        sender: &User,
+        app: &mut App,
+        keys: Vec<String>,
+    ) -> AnyResult<AppResponse> {
+        self.update_registry(sender, app, RegistryOperation::RemoveMedianKeys { keys })
+    }
+    pub fn set_sources(
+        &self,
+        sender: &User,
+        app: &mut App,
+        key: &str,
+        oracles: Vec<Contract>,
+    ) -> AnyResult<AppResponse> {
+        self.update_registry(
+            sender,
+            app,
+            RegistryOperation::SetSources {
+                key: key.to_string(),
+                oracles: oracles.into_iter().map(|oracle| oracle.into()).collect(),
+            },
+        )
+    }
+    pub fn set_fallbacks(
+        &self,
+        sender: &User,
+        app: &mut App,
+        key: &str,
+        oracles: Vec<Contract>,
+    ) -> AnyResult<AppResponse> {
+        self.update_registry(
+            sender,
+            app,
+            RegistryOperation::SetFallbacks {
+                key: key.to_string(),
+                oracles: oracles.into_iter().map(|oracle| oracle.into()).collect(),
+            },
+        )
+    }
     pub fn update_protected_keys(
         &self,
         sender: &User,
@@ -121,6 +198,73 @@ impl OracleRouterHelper {
     ) -> AnyResult<AppResponse> {
         sender.exec(app, &ExecuteMsg::UpdateProtectedKeys(updates), &self.0)
     }
+    pub fn record_last_good_prices(
+        &self,
+        sender: &User,
+        app: &mut App,
+        keys: Vec<String>,
+    ) -> AnyResult<AppResponse> {
+        sender.exec(app, &ExecuteMsg::RecordLastGoodPrices(keys), &self.0)
+    }
+    pub fn reset_circuit_breaker(
+        &self,
+        sender: &User,
+        app: &mut App,
+        key: &str,
+        price: Uint256,
+    ) -> AnyResult<AppResponse> {
+        self.update_registry(
+            sender,
+            app,
+            RegistryOperation::ResetCircuitBreaker {
+                key: key.to_string(),
+                price,
+            },
+        )
+    }
+    pub fn freeze_keys(
+        &self,
+        sender: &User,
+        app: &mut App,
+        keys: Vec<String>,
+    ) -> AnyResult<AppResponse> {
+        self.update_registry(sender, app, RegistryOperation::FreezeKeys { keys })
+    }
+    pub fn unfreeze_keys(
+        &self,
+        sender: &User,
+        app: &mut App,
+        keys: Vec<String>,
+    ) -> AnyResult<AppResponse> {
+        self.update_registry(sender, app, RegistryOperation::UnfreezeKeys { keys })
+    }
+    pub fn remove_overrides(
+        &self,
+        sender: &User,
+        app: &mut App,
+        keys: Vec<String>,
+    ) -> AnyResult<AppResponse> {
+        self.update_registry(sender, app, RegistryOperation::RemoveOverrides { keys })
+    }
+    pub fn propose(
+        &self,
+        sender: &User,
+        app: &mut App,
+        action: ProposalAction,
+    ) -> AnyResult<AppResponse> {
+        sender.exec(app, &ExecuteMsg::Propose(action), &self.0)
+    }
+    pub fn execute_proposal(
+        &self,
+        sender: &User,
+        app: &mut App,
+        id: u64,
+    ) -> AnyResult<AppResponse> {
+        sender.exec(app, &ExecuteMsg::ExecuteProposal(id), &self.0)
+    }
+    pub fn cancel_proposal(&self, sender: &User, app: &mut App, id: u64) -> AnyResult<AppResponse> {
+        sender.exec(app, &ExecuteMsg::CancelProposal(id), &self.0)
+    }
     pub fn query_config(&self, app: &App) -> StdResult<ConfigResponse> {
         QueryMsg::GetConfig {}.test_query(&self.0, app)
     }
@@ -135,6 +279,27 @@ impl OracleRouterHelper {
         QueryMsg::GetPrice { key }.test_query(&self.0, app)
     }
 
+    pub fn query_price_in(&self, app: &App, key: &str, quote: &str) -> StdResult<PriceResponse> {
+        QueryMsg::GetPriceIn {
+            key: key.to_string(),
+            quote: quote.to_string(),
+        }
+        .test_query(&self.0, app)
+    }
+
+    pub fn query_prices_in(
+        &self,
+        app: &App,
+        keys: Vec<String>,
+        quote: &str,
+    ) -> StdResult<PricesResponse> {
+        QueryMsg::GetPricesIn {
+            keys,
+            quote: quote.to_string(),
+        }
+        .test_query(&self.0, app)
+    }
+
     pub fn query_oracles(&self, app: &App, keys: Vec<String>) -> StdResult<OraclesResponse> {
         QueryMsg::GetOracles { keys }.test_query(&self.0, app)
     }
@@ -143,12 +308,102 @@ impl OracleRouterHelper {
         QueryMsg::GetPrices { keys }.test_query(&self.0, app)
     }
 
+    pub fn query_price_detailed(&self, app: &App, key: &str) -> StdResult<PriceDetailedResponse> {
+        QueryMsg::GetPriceDetailed {
+            key: key.to_string(),
+        }
+        .test_query(&self.0, app)
+    }
+
+    pub fn query_prices_lenient(
+        &self,
+        app: &App,
+        keys: Vec<String>,
+    ) -> StdResult<LenientPricesResponse> {
+        QueryMsg::GetPricesLenient { keys, depth: 0 }.test_query(&self.0, app)
+    }
+
     pub fn query_keys(&self, app: &App) -> StdResult<KeysResponse> {
-        QueryMsg::GetKeys {}.test_query(&self.0, app)
+        self.query_keys_page(app, None, None)
+    }
+
+    pub fn query_keys_page(
+        &self,
+        app: &App,
+        start_after: Option<&str>,
+        limit: Option<u32>,
+    ) -> StdResult<KeysResponse> {
+        QueryMsg::GetKeys {
+            start_after: start_after.map(|key| key.to_string()),
+            limit,
+        }
+        .test_query(&self.0, app)
+    }
+
+    pub fn query_keys_by_oracle(
+        &self,
+        app: &App,
+        oracle: &Contract,
+        start_after: Option<&str>,
+        limit: Option<u32>,
+    ) -> StdResult<KeysResponse> {
+        QueryMsg::GetKeysByOracle {
+            oracle: oracle.address.to_string(),
+            start_after: start_after.map(|key| key.to_string()),
+            limit,
+        }
+        .test_query(&self.0, app)
     }
 
     pub fn query_protected_keys(&self, app: &App) -> StdResult<ProtectedKeysResponse> {
-        QueryMsg::GetProtectedKeys {}.test_query(&self.0, app)
+        QueryMsg::GetProtectedKeys {
+            start_after: None,
+            limit: None,
+        }
+        .test_query(&self.0, app)
+    }
+
+    pub fn query_max_ages(&self, app: &App, keys: Vec<String>) -> StdResult<MaxAgesResponse> {
+        QueryMsg::GetMaxAges { keys }.test_query(&self.0, app)
+    }
+
+    pub fn query_sources(&self, app: &App, key: &str) -> StdResult<SourcesResponse> {
+        QueryMsg::GetSources {
+            key: key.to_string(),
+        }
+        .test_query(&self.0, app)
+    }
+
+    pub fn query_dry_run_registry(
+        &self,
+        app: &App,
+        operations: Vec<RegistryOperation>,
+    ) -> StdResult<DryRunRegistryResponse> {
+        QueryMsg::DryRunRegistry { operations }.test_query(&self.0, app)
+    }
+
+    pub fn query_proposals(&self, app: &App) -> StdResult<ProposalsResponse> {
+        QueryMsg::GetProposals {
+            start_after: None,
+            limit: None,
+        }
+        .test_query(&self.0, app)
+    }
+
+    pub fn query_circuit_breakers(
+        &self,
+        app: &App,
+        keys: Vec<String>,
+    ) -> StdResult<CircuitBreakersResponse> {
+        QueryMsg::GetCircuitBreakers { keys }.test_query(&self.0, app)
+    }
+
+    pub fn query_overrides(&self, app: &App) -> StdResult<OverridesResponse> {
+        QueryMsg::GetOverrides {
+            start_after: None,
+            limit: None,
+        }
+        .test_query(&self.0, app)
     }
 }
 
@@ -157,7 +412,16 @@ mod test {
     use super::*;
     use multi_test_helpers::Asserter;
     use shade_oracles::{
-        core::admin::helpers::AdminPermissions, unit_test_interface::prices::PricesFixture,
+        core::admin::helpers::AdminPermissions,
+        interfaces::{
+            common::PriceSource,
+            providers::{
+                mock::{BandExecuteMsg, MockPrice, OjoExecuteMsg},
+                Provider,
+            },
+            router::aggregation::AggregationMethod,
+        },
+        unit_test_interface::prices::PricesFixture,
     };
 
     /// Tests set protection, remove key protection, update protection, query protected keys.
@@ -239,6 +503,1409 @@ mod test {
         assert!(router.query_prices(app, vec!["USD".to_string()]).is_ok());
     }
 
+    /// Tests serving the last good price of a tripped protected key during its grace period and resetting it.
+    #[test]
+    fn circuit_breaker_tests() {
+        let prices = vec![("USD", 1_00 * 10u128.pow(16))];
+
+        let TestScenario {
+            mut app,
+            router,
+            admin,
+            user,
+            bot,
+            provider,
+            admin_auth,
+            ..
+        } = TestScenario::new(prices);
+        let app = &mut app;
+        let key = "USD".to_string();
+        let set_usd_price = |app: &mut App, rate: u128| {
+            let prices = OracleCore::create_prices_hashmap(vec![("USD", rate)]).1;
+            provider.update_band_prices(&admin, app, prices, Some(app.block_info().time.seconds()));
+        };
+
+        let usd_protection = ProtectedKeyInfo::new(
+            key.clone(),
+            Decimal256::percent(4),
+            Uint256::from_u128(1_00 * 10u128.pow(16)),
+        )
+        .with_grace_period(600);
+        router
+            .set_protection(&admin, app, vec![usd_protection])
+            .unwrap();
+        assert!(router
+            .record_last_good_prices(&user, app, vec!["BTC".to_string()])
+            .is_err());
+        router
+            .record_last_good_prices(&user, app, vec![key.clone()])
+            .unwrap();
+        let recorded_at = app.block_info().time.seconds();
+
+        let resp = router
+            .query_circuit_breakers(app, vec![key.clone()])
+            .unwrap();
+        assert!(!resp[0].tripped);
+        assert_eq!(
+            resp[0].last_good_price.as_ref().unwrap().recorded_at,
+            recorded_at
+        );
+
+        // Price deviates by 5% so the last good price is served for the grace period.
+        app.update_block(|b| b.time = b.time.plus_seconds(300));
+        set_usd_price(app, 1_05 * 10u128.pow(16));
+        let resp = router.query_price(app, key.clone()).unwrap();
+        assert_eq!(resp.data.rate, Uint256::from_u128(1_00 * 10u128.pow(16)));
+        let resp = router.query_price_detailed(app, &key).unwrap();
+        assert!(matches!(
+            resp.source,
+            Some(PriceSource::CircuitBreaker { recorded_at: r, .. }) if r == recorded_at
+        ));
+        let resp = router.query_prices(app, vec![key.clone()]).unwrap();
+        assert_eq!(resp[0].data.rate, Uint256::from_u128(1_00 * 10u128.pow(16)));
+
+        let resp = router
+            .query_circuit_breakers(app, vec![key.clone()])
+            .unwrap();
+        assert_eq!(resp[0].expires_at, Some(recorded_at + 600));
+
+        // Deviating prices don't replace the last good price, the grace period runs from when the trip is recorded.
+        router
+            .record_last_good_prices(&user, app, vec![key.clone()])
+            .unwrap();
+        let tripped_at = app.block_info().time.seconds();
+        app.update_block(|b| b.time = b.time.plus_seconds(301));
+        set_usd_price(app, 1_05 * 10u128.pow(16));
+        router
+            .record_last_good_prices(&user, app, vec![key.clone()])
+            .unwrap();
+        let resp = router
+            .query_circuit_breakers(app, vec![key.clone()])
+            .unwrap();
+        assert!(resp[0].tripped);
+        assert!(resp[0].serving);
+        let last_good = resp[0].last_good_price.as_ref().unwrap();
+        assert_eq!(last_good.recorded_at, recorded_at);
+        assert_eq!(last_good.tripped_at, Some(tripped_at));
+        assert_eq!(resp[0].expires_at, Some(tripped_at + 600));
+        let resp = router.query_price(app, key.clone()).unwrap();
+        assert_eq!(resp.data.rate, Uint256::from_u128(1_00 * 10u128.pow(16)));
+
+        // Unprotected keys and keys without a price are reported per key.
+        router
+            .set_protection(
+                &admin,
+                app,
+                vec![ProtectedKeyInfo::new(
+                    "FOO".to_string(),
+                    Decimal256::percent(4),
+                    Uint256::from_u128(10u128.pow(18)),
+                )],
+            )
+            .unwrap();
+        let resp = router
+            .query_circuit_breakers(app, vec!["BTC".to_string(), "FOO".to_string(), key.clone()])
+            .unwrap();
+        assert!(resp[0].error.as_ref().unwrap().contains("not protected"));
+        assert!(resp[1].error.is_some());
+        assert!(!resp[1].tripped);
+        assert!(resp[2].error.is_none());
+        assert!(resp[2].serving);
+
+        // Grace period is over.
+        app.update_block(|b| b.time = b.time.plus_seconds(301));
+        set_usd_price(app, 1_05 * 10u128.pow(16));
+        assert!(router.query_price(app, key.clone()).is_err());
+        assert!(router.query_prices(app, vec![key.clone()]).is_err());
+        assert!(
+            !router
+                .query_circuit_breakers(app, vec![key.clone()])
+                .unwrap()[0]
+                .serving
+        );
+
+        // Only the bot or an admin can reset the circuit breaker.
+        let new_price = Uint256::from_u128(1_05 * 10u128.pow(16));
+        assert!(router
+            .reset_circuit_breaker(&bot, app, &key, new_price)
+            .is_err());
+        admin_auth.register_admin(&admin, app, bot.clone().into());
+        admin_auth.grant_access(
+            &admin,
+            app,
+            bot.clone().into(),
+            vec![AdminPermissions::OraclesPriceBot.into_string()],
+        );
+        router
+            .reset_circuit_breaker(&bot, app, &key, new_price)
+            .unwrap();
+        let resp = router.query_price(app, key.clone()).unwrap();
+        assert_eq!(resp.data.rate, new_price);
+        let resp = router
+            .query_circuit_breakers(app, vec![key.clone()])
+            .unwrap();
+        assert!(!resp[0].tripped);
+        assert!(resp[0].last_good_price.is_none());
+    }
+
+    /// Tests triangulating prices through the quote symbol and that both legs are protected.
+    #[test]
+    fn cross_rate_tests() {
+        let prices = vec![
+            ("USD", 1_00 * 10u128.pow(16)),
+            ("SHD", 10 * 10u128.pow(18)),
+            ("SCRT", 2 * 10u128.pow(18)),
+            ("SILK", 0),
+        ];
+
+        let TestScenario {
+            mut app,
+            router,
+            admin,
+            provider,
+            ..
+        } = TestScenario::new(prices);
+        let app = &mut app;
+        let now = app.block_info().time.seconds();
+
+        let resp = router.query_price_in(app, "SHD", "SCRT").unwrap();
+        assert_eq!(resp.key, "SHD");
+        assert_eq!(resp.data.rate, Uint256::from_u128(5 * 10u128.pow(18)));
+        let resp = router
+            .query_prices_in(app, vec!["SCRT".to_string(), "SHD".to_string()], "SHD")
+            .unwrap();
+        assert_eq!(resp[0].data.rate, Uint256::from_u128(2 * 10u128.pow(17)));
+        assert_eq!(resp[1].data.rate, Uint256::from_u128(10u128.pow(18)));
+        // Quoting in the quote symbol of the router is the same as the regular price.
+        assert_eq!(
+            router.query_price_in(app, "SHD", "USD").unwrap(),
+            router.query_price(app, "SHD".to_string()).unwrap()
+        );
+        assert!(router.query_price_in(app, "SHD", "SILK").is_err());
+
+        // A stale leg makes both timestamps stale, whichever side it is on.
+        app.update_block(|b| b.time = b.time.plus_seconds(100));
+        let prices = OracleCore::create_prices_hashmap(vec![("SHD", 12 * 10u128.pow(18))]).1;
+        provider.update_band_prices(&admin, app, prices, Some(now + 100));
+        let resp = router.query_price_in(app, "SHD", "SCRT").unwrap();
+        assert_eq!(resp.data.rate, Uint256::from_u128(6 * 10u128.pow(18)));
+        assert_eq!(resp.data.last_updated_base, now);
+        assert_eq!(resp.data.last_updated_quote, now);
+        let resp = router.query_price_in(app, "SCRT", "SHD").unwrap();
+        assert_eq!(resp.data.last_updated_base, now);
+        assert_eq!(resp.data.last_updated_quote, now);
+
+        // Both legs go through deviation protection.
+        router
+            .set_protection(
+                &admin,
+                app,
+                vec![ProtectedKeyInfo::new(
+                    "SCRT".to_string(),
+                    Decimal256::percent(5),
+                    Uint256::from_u128(3 * 10u128.pow(18)),
+                )],
+            )
+            .unwrap();
+        assert!(router.query_price(app, "SHD".to_string()).is_ok());
+        assert!(router.query_price_in(app, "SHD", "SCRT").is_err());
+        assert!(router
+            .query_prices_in(app, vec!["SCRT".to_string()], "SHD")
+            .is_err());
+    }
+
+    /// Tests paginating the registered and protected keys and listing the keys of each oracle.
+    #[test]
+    fn key_index_tests() {
+        let keys: Vec<String> = ('A'..='G').map(|c| format!("KEY{c}")).collect();
+        let prices: Vec<(String, u128)> = keys
+            .iter()
+            .map(|key| (key.clone(), 10u128.pow(18)))
+            .collect();
+        let TestScenario {
+            mut app,
+            router,
+            admin,
+            provider,
+            admin_auth,
+            ..
+        } = TestScenario::new(prices);
+        let app = &mut app;
+        let provider: Contract = provider.into();
+        let ojo_prices = keys
+            .iter()
+            .map(|key| {
+                (
+                    key.clone(),
+                    "USD".to_string(),
+                    Uint256::from(10u128.pow(18)),
+                )
+            })
+            .collect();
+        let other: Contract =
+            MockProviderHelper::init_ojo(&admin, app, ojo_prices, admin_auth.into(), None).into();
+
+        router
+            .set_keys(&admin, app, provider.clone(), keys[..5].to_vec())
+            .unwrap();
+        router
+            .set_keys(&admin, app, other.clone(), keys[5..].to_vec())
+            .unwrap();
+        assert_eq!(router.query_keys(app).unwrap(), keys);
+        assert_eq!(
+            router.query_keys_page(app, None, Some(2)).unwrap(),
+            keys[..2]
+        );
+        assert_eq!(
+            router.query_keys_page(app, Some("KEYB"), Some(3)).unwrap(),
+            keys[2..5]
+        );
+        assert!(router
+            .query_keys_page(app, Some("KEYG"), None)
+            .unwrap()
+            .is_empty());
+        assert!(router.query_keys_page(app, Some("BTC"), None).is_err());
+        assert_eq!(
+            router
+                .query_keys_by_oracle(app, &provider, Some("KEYC"), None)
+                .unwrap(),
+            keys[3..5]
+        );
+
+        // Moving a key to another oracle updates the keys of both,
+        // the last key of the set taking the place of the removed one.
+        router
+            .set_keys(&admin, app, other.clone(), vec![keys[0].clone()])
+            .unwrap();
+        assert_eq!(
+            router.query_keys(app).unwrap(),
+            vec!["KEYG", "KEYB", "KEYC", "KEYD", "KEYE", "KEYF", "KEYA"]
+        );
+        assert_eq!(
+            router
+                .query_keys_by_oracle(app, &provider, None, None)
+                .unwrap(),
+            vec!["KEYE", "KEYB", "KEYC", "KEYD"]
+        );
+        assert_eq!(
+            router
+                .query_keys_by_oracle(app, &other, None, None)
+                .unwrap(),
+            vec!["KEYF", "KEYG", "KEYA"]
+        );
+
+        router
+            .remove_keys(&admin, app, vec![keys[6].clone(), "BTC".to_string()])
+            .unwrap();
+        assert_eq!(
+            router
+                .query_keys_by_oracle(app, &other, None, None)
+                .unwrap(),
+            vec!["KEYF", "KEYA"]
+        );
+        assert!(!router.query_keys(app).unwrap().contains(&keys[6]));
+
+        let infos: Vec<ProtectedKeyInfo> = keys
+            .iter()
+            .map(|key| ProtectedKeyInfo::new(key.clone(), Decimal256::percent(5), Uint256::one()))
+            .collect();
+        router.set_protection(&admin, app, infos.clone()).unwrap();
+        router
+            .remove_key_protections(&admin, app, vec![keys[0].clone()])
+            .unwrap();
+        // Pages can still start after removed keys, from the key moved into their slot.
+        let resp: ProtectedKeysResponse = QueryMsg::GetProtectedKeys {
+            start_after: Some("KEYA".to_string()),
+            limit: Some(3),
+        }
+        .test_query(&router.0, app)
+        .unwrap();
+        assert_eq!(
+            resp,
+            vec![infos[6].clone(), infos[1].clone(), infos[2].clone()]
+        );
+        let resp: ProtectedKeysResponse = QueryMsg::GetProtectedKeys {
+            start_after: Some("KEYC".to_string()),
+            limit: None,
+        }
+        .test_query(&router.0, app)
+        .unwrap();
+        assert_eq!(
+            resp,
+            vec![infos[3].clone(), infos[4].clone(), infos[5].clone()]
+        );
+        // Adding the key back appends it.
+        router
+            .set_protection(&admin, app, vec![infos[0].clone()])
+            .unwrap();
+        assert_eq!(
+            router.query_protected_keys(app).unwrap().last(),
+            Some(&infos[0])
+        );
+    }
+
+    /// Tests that new oracles must price their keys, optionally close to their current price, and dry running operations.
+    #[test]
+    fn set_keys_validation_tests() {
+        let prices = vec![
+            ("USD", 1_00 * 10u128.pow(16)),
+            ("BTC", 30_000 * 10u128.pow(18)),
+            ("ETH", 2_000 * 10u128.pow(18)),
+        ];
+
+        let TestScenario {
+            mut app,
+            router,
+            admin,
+            provider,
+            admin_auth,
+            ..
+        } = TestScenario::new(prices);
+        let app = &mut app;
+        let band: Contract = provider.into();
+        let ojo: Contract = MockProviderHelper::init_ojo(
+            &admin,
+            app,
+            vec![
+                (
+                    "BTC".into(),
+                    "USD".into(),
+                    Uint256::from(31_000 * 10u128.pow(18)),
+                ),
+                (
+                    "ETH".into(),
+                    "USD".into(),
+                    Uint256::from(2_500 * 10u128.pow(18)),
+                ),
+            ],
+            admin_auth.into(),
+            None,
+        )
+        .into();
+        let set_keys = |keys: &[&str], max_deviation: Option<u64>| RegistryOperation::SetKeys {
+            oracle: ojo.clone().into(),
+            keys: keys.iter().map(|key| key.to_string()).collect(),
+            max_deviation: max_deviation.map(Decimal256::percent),
+        };
+
+        // Ojo doesn't have SHD.
+        assert!(router
+            .update_registry(&admin, app, set_keys(&["BTC", "SHD"], None))
+            .is_err());
+        assert_eq!(
+            router.query_oracle(app, "BTC").unwrap().oracle,
+            router.clone().into()
+        );
+        // Ojo BTC is 3.33% away from Band.
+        assert!(router
+            .update_registry(&admin, app, set_keys(&["BTC"], Some(2)))
+            .is_err());
+        router
+            .update_registry(&admin, app, set_keys(&["BTC"], Some(5)))
+            .unwrap();
+        assert_eq!(router.query_oracle(app, "BTC").unwrap().oracle, ojo);
+        assert!(router
+            .update_registry(&admin, app, set_keys(&["ETH"], Some(10)))
+            .is_err());
+
+        let operations = vec![
+            RegistryOperation::SetKeys {
+                oracle: band.clone().into(),
+                keys: vec!["BTC".to_string(), "ETH".to_string()],
+                max_deviation: None,
+            },
+            RegistryOperation::RemoveKeys {
+                keys: vec!["BTC".to_string()],
+            },
+            RegistryOperation::SetMaxAges {
+                max_ages: vec![("ETH".to_string(), 60)],
+            },
+        ];
+        let changes = router.query_dry_run_registry(app, operations).unwrap();
+        assert_eq!(
+            changes,
+            vec![
+                RegistryChange::Oracle {
+                    key: "BTC".to_string(),
+                    before: Some(ojo.clone()),
+                    after: Some(band.clone()),
+                },
+                RegistryChange::Oracle {
+                    key: "ETH".to_string(),
+                    before: None,
+                    after: Some(band.clone()),
+                },
+                RegistryChange::Oracle {
+                    key: "BTC".to_string(),
+                    before: Some(band),
+                    after: None,
+                },
+                RegistryChange::MaxAge {
+                    key: "ETH".to_string(),
+                    before: None,
+                    after: Some(60),
+                },
+            ]
+        );
+        // Nothing was written.
+        assert_eq!(router.query_oracle(app, "BTC").unwrap().oracle, ojo);
+        assert_eq!(
+            router.query_max_ages(app, vec!["ETH".to_string()]).unwrap(),
+            vec![("ETH".to_string(), None)]
+        );
+        assert!(router
+            .query_dry_run_registry(app, vec![set_keys(&["SHD"], None)])
+            .is_err());
+    }
+
+    /// Tests that changes wait for the timelock while emergency operations don't, and freezing keys.
+    #[test]
+    fn timelock_tests() {
+        let prices = vec![
+            ("USD", 1_00 * 10u128.pow(16)),
+            ("BTC", 30_000 * 10u128.pow(18)),
+            ("ETH", 2_000 * 10u128.pow(18)),
+        ];
+
+        let TestScenario {
+            mut app,
+            router,
+            admin,
+            user,
+            provider,
+            ..
+        } = TestScenario::new(prices);
+        let app = &mut app;
+        let keys = vec!["BTC".to_string(), "ETH".to_string()];
+        let config = |max_age: Option<u64>, timelock: Option<u64>| UpdateConfig {
+            admin_auth: None,
+            providers: None,
+            quote_symbol: None,
+            max_age: max_age.map(Some),
+            aggregation: None,
+            timelock,
+        };
+
+        router
+            .update_config(&admin, app, config(None, Some(3_600)))
+            .unwrap();
+        assert_eq!(router.query_config(app).unwrap().config.timelock, 3_600);
+        assert!(router
+            .update_config(&admin, app, config(Some(600), None))
+            .is_err());
+        assert!(router
+            .set_keys(&admin, app, provider.clone().into(), keys.clone())
+            .is_err());
+
+        let set_keys = ProposalAction::Registry(vec![RegistryOperation::SetKeys {
+            oracle: provider.clone().into(),
+            keys: keys.clone(),
+            max_deviation: None,
+        }]);
+        assert!(router.propose(&user, app, set_keys.clone()).is_err());
+        router.propose(&admin, app, set_keys).unwrap();
+        router
+            .propose(&admin, app, ProposalAction::Config(config(Some(600), None)))
+            .unwrap();
+        let proposals = router.query_proposals(app).unwrap();
+        assert_eq!(proposals.len(), 2);
+        assert_eq!(proposals[0].id, 0);
+        assert_eq!(proposals[0].executable_at, 3_600);
+
+        app.update_block(|b| b.time = b.time.plus_seconds(1_800));
+        assert!(router.execute_proposal(&admin, app, 0).is_err());
+        assert!(router.cancel_proposal(&user, app, 1).is_err());
+        router.cancel_proposal(&admin, app, 1).unwrap();
+        assert!(router.execute_proposal(&admin, app, 1).is_err());
+
+        app.update_block(|b| b.time = b.time.plus_seconds(1_800));
+        assert!(router.execute_proposal(&user, app, 0).is_err());
+        router.execute_proposal(&admin, app, 0).unwrap();
+        assert!(router.execute_proposal(&admin, app, 0).is_err());
+        assert_eq!(router.query_keys(app).unwrap(), keys);
+        assert!(router.query_proposals(app).unwrap().is_empty());
+
+        // Emergency operations skip the timelock.
+        router
+            .freeze_keys(&admin, app, vec!["BTC".to_string()])
+            .unwrap();
+        assert!(router.query_price(app, "BTC".to_string()).is_err());
+        assert!(router.query_prices(app, keys.clone()).is_err());
+        router.query_price(app, "ETH".to_string()).unwrap();
+        assert!(router
+            .unfreeze_keys(&admin, app, vec!["BTC".to_string()])
+            .is_err());
+        // Compromised oracles can be removed right away, but registering keys still needs a proposal.
+        router
+            .remove_keys(&admin, app, vec!["ETH".to_string()])
+            .unwrap();
+        assert_eq!(router.query_keys(app).unwrap(), vec!["BTC".to_string()]);
+        assert!(router
+            .set_keys(
+                &admin,
+                app,
+                provider.clone().into(),
+                vec!["ETH".to_string()]
+            )
+            .is_err());
+
+        let btc_protection = ProtectedKeyInfo::new(
+            "BTC".to_string(),
+            Decimal256::percent(5),
+            Uint256::from_u128(30_000 * 10u128.pow(18)),
+        );
+        router
+            .propose(
+                &admin,
+                app,
+                ProposalAction::Registry(vec![
+                    RegistryOperation::UnfreezeKeys {
+                        keys: vec!["BTC".to_string()],
+                    },
+                    RegistryOperation::SetProtection {
+                        infos: vec![btc_protection],
+                    },
+                ]),
+            )
+            .unwrap();
+        app.update_block(|b| b.time = b.time.plus_seconds(3_600));
+        router.execute_proposal(&admin, app, 2).unwrap();
+        assert_eq!(router.query_keys(app).unwrap(), vec!["BTC".to_string()]);
+        router.query_prices(app, keys).unwrap();
+
+        // Resetting a circuit breaker moves the protected price so it has to wait too.
+        assert!(router
+            .reset_circuit_breaker(
+                &admin,
+                app,
+                "BTC",
+                Uint256::from_u128(20_000 * 10u128.pow(18))
+            )
+            .is_err());
+
+        // Proposals can't overflow their execution time.
+        router
+            .propose(
+                &admin,
+                app,
+                ProposalAction::Config(config(None, Some(u64::MAX))),
+            )
+            .unwrap();
+        app.update_block(|b| b.time = b.time.plus_seconds(3_600));
+        router.execute_proposal(&admin, app, 3).unwrap();
+        router
+            .propose(&admin, app, ProposalAction::Config(config(None, Some(0))))
+            .unwrap();
+        let proposals = router.query_proposals(app).unwrap();
+        assert_eq!(proposals[0].executable_at, u64::MAX);
+    }
+
+    /// Tests falling back along the oracle chain of a key when prices error, are stale or deviate.
+    #[test]
+    fn fallback_oracle_tests() {
+        let prices = vec![
+            ("USD", 1_00 * 10u128.pow(16)),
+            ("BTC", 30_000 * 10u128.pow(18)),
+        ];
+
+        let TestScenario {
+            mut app,
+            router,
+            admin,
+            admin_auth,
+            ..
+        } = TestScenario::new(prices);
+        let app = &mut app;
+        let btc = |rate: u128| Uint256::from(rate * 10u128.pow(18));
+        let ojo_helper = MockProviderHelper::init_ojo(
+            &admin,
+            app,
+            vec![("BTC".into(), "USD".into(), btc(31_000))],
+            admin_auth.clone().into(),
+            None,
+        );
+        let ojo: Contract = ojo_helper.clone().into();
+        let band = MockProviderHelper::init_band(
+            &admin,
+            app,
+            vec![(
+                "BTC".into(),
+                "USD".into(),
+                Uint128::from(29_000 * 10u128.pow(18)),
+            )],
+            admin_auth.into(),
+            None,
+        );
+        let band_contract: Contract = band.clone().into();
+        let active = |app: &App| router.query_price_detailed(app, "BTC").unwrap().active;
+
+        router
+            .set_keys(&admin, app, ojo.clone(), vec!["BTC".to_string()])
+            .unwrap();
+        // The band mock doesn't have USD.
+        assert!(router
+            .set_fallbacks(&admin, app, "USD", vec![band_contract.clone()])
+            .is_err());
+        router
+            .set_fallbacks(&admin, app, "BTC", vec![band_contract.clone()])
+            .unwrap();
+        let resp = router.query_oracle(app, "BTC").unwrap();
+        assert_eq!(resp.oracle, ojo);
+        assert_eq!(resp.chain, vec![ojo.clone(), band_contract.clone()]);
+        assert_eq!(active(app), Some(0));
+        assert_eq!(
+            router
+                .query_price(app, "BTC".to_string())
+                .unwrap()
+                .data
+                .rate,
+            btc(31_000)
+        );
+
+        // Ojo goes stale.
+        router
+            .set_max_ages(&admin, app, vec![("BTC".to_string(), 600)])
+            .unwrap();
+        app.update_block(|b| b.time = b.time.plus_seconds(1_000));
+        band.update_band_prices(
+            &admin,
+            app,
+            HashMap::from([("BTC".to_string(), Uint128::from(29_000 * 10u128.pow(18)))]),
+            None,
+        );
+        assert_eq!(active(app), Some(1));
+        let price = router.query_price(app, "BTC".to_string()).unwrap();
+        assert_eq!(price.data.rate, btc(29_000));
+        let resp = router.query_price_detailed(app, "BTC").unwrap();
+        assert_eq!(resp.source, Some(PriceSource::Oracle(band_contract)));
+        let prices = router
+            .query_prices(app, vec!["USD".to_string(), "BTC".to_string()])
+            .unwrap();
+        assert_eq!(prices[1].data.rate, btc(29_000));
+
+        // Ojo is fresh again but deviates from the protected price.
+        ojo_helper.update_ojo_prices(
+            &admin,
+            app,
+            HashMap::from([("BTC".to_string(), btc(31_000))]),
+            None,
+        );
+        assert_eq!(active(app), Some(0));
+        router
+            .set_protection(
+                &admin,
+                app,
+                vec![ProtectedKeyInfo::new(
+                    "BTC".to_string(),
+                    Decimal256::percent(5),
+                    btc(29_000),
+                )],
+            )
+            .unwrap();
+        assert_eq!(active(app), Some(1));
+        assert_eq!(
+            router.query_prices(app, vec!["BTC".to_string()]).unwrap()[0]
+                .data
+                .rate,
+            btc(29_000)
+        );
+
+        // Every oracle fails so the circuit breaker of the key rejects the price.
+        app.update_block(|b| b.time = b.time.plus_seconds(1_000));
+        ojo_helper.update_ojo_prices(
+            &admin,
+            app,
+            HashMap::from([("BTC".to_string(), btc(31_000))]),
+            None,
+        );
+        assert!(router.query_price(app, "BTC".to_string()).is_err());
+
+        router
+            .remove_keys(&admin, app, vec!["BTC".to_string()])
+            .unwrap();
+        let resp = router.query_oracle(app, "BTC").unwrap();
+        assert_eq!(resp.chain, vec![router.clone().into()]);
+    }
+
+    /// Tests the provenance, protection and age reported with detailed prices.
+    #[test]
+    fn price_detailed_tests() {
+        let prices = vec![
+            ("USD", 1_00 * 10u128.pow(16)),
+            ("BTC", 30_000 * 10u128.pow(18)),
+        ];
+
+        let TestScenario {
+            mut app,
+            router,
+            admin,
+            provider,
+            admin_auth,
+            ..
+        } = TestScenario::new(prices);
+        let app = &mut app;
+        let ojo: Contract = MockProviderHelper::init_ojo(
+            &admin,
+            app,
+            vec![(
+                "ATOM".into(),
+                "USD".into(),
+                Uint256::from(10 * 10u128.pow(18)),
+            )],
+            admin_auth.into(),
+            None,
+        )
+        .into();
+        router
+            .set_keys(&admin, app, ojo.clone(), vec!["ATOM".to_string()])
+            .unwrap();
+        app.update_block(|b| b.time = b.time.plus_seconds(100));
+
+        let resp = router.query_price_detailed(app, "BTC").unwrap();
+        assert_eq!(
+            resp.price,
+            router.query_price(app, "BTC".to_string()).unwrap()
+        );
+        assert_eq!(resp.oracle, router.clone().into());
+        assert_eq!(resp.providers, vec![Provider::Band(provider.into())]);
+        assert_eq!(resp.protection, None);
+        assert_eq!(resp.deviation, None);
+        assert_eq!(resp.age, 100);
+
+        let resp = router.query_price_detailed(app, "ATOM").unwrap();
+        assert_eq!(resp.oracle, ojo);
+        assert!(resp.providers.is_empty());
+        assert_eq!(resp.age, 100);
+
+        let protection = ProtectedKeyInfo::new(
+            "BTC".to_string(),
+            Decimal256::percent(10),
+            Uint256::from(32_000 * 10u128.pow(18)),
+        );
+        router
+            .set_protection(&admin, app, vec![protection.clone()])
+            .unwrap();
+        let resp = router.query_price_detailed(app, "BTC").unwrap();
+        assert_eq!(resp.protection, Some(protection));
+        assert_eq!(resp.deviation, Some(Decimal256::from_ratio(1u128, 16u128)));
+    }
+
+    /// Tests that overrides are served until they expire and are bounded by the protection of the key.
+    #[test]
+    fn override_tests() {
+        let prices = vec![
+            ("USD", 1_00 * 10u128.pow(16)),
+            ("BTC", 30_000 * 10u128.pow(18)),
+        ];
+
+        let TestScenario {
+            mut app,
+            router,
+            admin,
+            user,
+            bot,
+            admin_auth,
+            ..
+        } = TestScenario::new(prices);
+        let app = &mut app;
+        let btc = |rate: u128| Uint256::from(rate * 10u128.pow(18));
+        let set_override =
+            |app: &mut App, sender: &User, key: &str, price: Uint256, expires_at: u64, force| {
+                router.update_registry(
+                    sender,
+                    app,
+                    RegistryOperation::SetOverride {
+                        key: key.to_string(),
+                        price,
+                        expires_at,
+                        reason: "Band is listing a wrong price.".to_string(),
+                        force,
+                    },
+                )
+            };
+        admin_auth.register_admin(&admin, app, bot.clone().into());
+        admin_auth.grant_access(
+            &admin,
+            app,
+            bot.clone().into(),
+            vec![AdminPermissions::OraclesPriceBot.into_string()],
+        );
+        router
+            .set_protection(
+                &admin,
+                app,
+                vec![ProtectedKeyInfo::new(
+                    "BTC".to_string(),
+                    Decimal256::percent(5),
+                    btc(30_000),
+                )],
+            )
+            .unwrap();
+        app.update_block(|b| b.time = b.time.plus_seconds(100));
+
+        assert!(set_override(app, &user, "BTC", btc(31_000), 700, false).is_err());
+        assert!(set_override(app, &bot, "BTC", btc(31_000), 100, false).is_err());
+        // Unprotected keys and prices outside the deviation of the key have to be forced by an admin.
+        let usd = Uint256::from(10u128.pow(18));
+        assert!(set_override(app, &bot, "USD", usd, 700, false).is_err());
+        assert!(set_override(app, &bot, "BTC", btc(40_000), 700, false).is_err());
+        assert!(set_override(app, &bot, "BTC", btc(40_000), 700, true).is_err());
+        set_override(app, &bot, "BTC", btc(31_000), 700, false).unwrap();
+
+        let resp = router.query_price(app, "BTC".to_string()).unwrap();
+        assert_eq!(resp.data.rate, btc(31_000));
+        assert_eq!(resp.data.last_updated_base, 100);
+        let resp = router.query_price_detailed(app, "BTC").unwrap();
+        assert_eq!(
+            resp.source,
+            Some(PriceSource::Override {
+                reason: "Band is listing a wrong price.".to_string(),
+                expires_at: 700,
+                forced: false,
+            })
+        );
+        let resp = router
+            .query_prices(app, vec!["USD".to_string(), "BTC".to_string()])
+            .unwrap();
+        assert_eq!(resp[0].data.rate, usd);
+        assert_eq!(resp[1].data.rate, btc(31_000));
+
+        set_override(app, &admin, "BTC", btc(40_000), 700, true).unwrap();
+        let resp = router.query_price(app, "BTC".to_string()).unwrap();
+        assert_eq!(resp.data.rate, btc(40_000));
+        let overrides = router.query_overrides(app).unwrap();
+        assert_eq!(overrides.len(), 1);
+        assert!(overrides[0].forced);
+        let resp = router.query_price_detailed(app, "BTC").unwrap();
+        assert_eq!(resp.oracle, router.clone().into());
+
+        // Expired overrides are no longer served.
+        app.update_block(|b| b.time = b.time.plus_seconds(600));
+        let resp = router.query_price(app, "BTC".to_string()).unwrap();
+        assert_eq!(resp.data.rate, btc(30_000));
+        let resp = router.query_price_detailed(app, "BTC").unwrap();
+        assert!(!matches!(resp.source, Some(PriceSource::Override { .. })));
+        assert_eq!(router.query_overrides(app).unwrap().len(), 1);
+        assert!(router
+            .remove_overrides(&user, app, vec!["BTC".to_string()])
+            .is_err());
+        router
+            .remove_overrides(&bot, app, vec!["BTC".to_string()])
+            .unwrap();
+        assert!(router.query_overrides(app).unwrap().is_empty());
+
+        // Only overrides bounded by the protection of the key skip the timelock.
+        router
+            .update_config(
+                &admin,
+                app,
+                UpdateConfig {
+                    admin_auth: None,
+                    providers: None,
+                    quote_symbol: None,
+                    max_age: None,
+                    aggregation: None,
+                    timelock: Some(3_600),
+                },
+            )
+            .unwrap();
+        assert!(set_override(app, &admin, "BTC", btc(40_000), 1_400, true).is_err());
+        set_override(app, &bot, "BTC", btc(29_000), 1_400, false).unwrap();
+        let resp = router.query_price(app, "BTC".to_string()).unwrap();
+        assert_eq!(resp.data.rate, btc(29_000));
+        // Overrides that aren't forced last a day at most.
+        let day = 24 * 60 * 60;
+        assert!(set_override(app, &bot, "BTC", btc(29_000), 700 + day + 1, false).is_err());
+        set_override(app, &bot, "BTC", btc(29_000), 700 + day, false).unwrap();
+
+        // Overrides that aren't forced stop being served once they leave the deviation of the key.
+        router
+            .update_protected_keys(&bot, app, vec![("BTC".to_string(), btc(35_000))])
+            .unwrap();
+        let err = router.query_price(app, "BTC".to_string()).unwrap_err();
+        assert!(err.to_string().contains("not within expected deviation"));
+        router
+            .update_protected_keys(&bot, app, vec![("BTC".to_string(), btc(29_500))])
+            .unwrap();
+        let resp = router.query_price(app, "BTC".to_string()).unwrap();
+        assert_eq!(resp.data.rate, btc(29_000));
+    }
+
+    /// Tests that keys failing at any step are reported without failing the other keys.
+    #[test]
+    fn lenient_prices_tests() {
+        let prices = vec![
+            ("USD", 1_00 * 10u128.pow(16)),
+            ("BTC", 30_000 * 10u128.pow(18)),
+            ("ETH", 2_000 * 10u128.pow(18)),
+        ];
+
+        let TestScenario {
+            mut app,
+            router,
+            admin,
+            admin_auth,
+            ..
+        } = TestScenario::new(prices);
+        let app = &mut app;
+        let ojo = MockProviderHelper::init_ojo(
+            &admin,
+            app,
+            vec![(
+                "ATOM".into(),
+                "USD".into(),
+                Uint256::from(10 * 10u128.pow(18)),
+            )],
+            admin_auth.into(),
+            None,
+        );
+        router
+            .set_keys(&admin, app, ojo.into(), vec!["ATOM".to_string()])
+            .unwrap();
+        router
+            .set_max_ages(&admin, app, vec![("ATOM".to_string(), 600)])
+            .unwrap();
+        router
+            .freeze_keys(&admin, app, vec!["ETH".to_string()])
+            .unwrap();
+        router
+            .set_protection(
+                &admin,
+                app,
+                vec![ProtectedKeyInfo::new(
+                    "USD".to_string(),
+                    Decimal256::percent(5),
+                    Uint256::from(2 * 10u128.pow(18)),
+                )],
+            )
+            .unwrap();
+        app.update_block(|b| b.time = b.time.plus_seconds(1_000));
+
+        let keys: Vec<String> = ["BTC", "ETH", "LP", "USD", "ATOM", "BTC"]
+            .iter()
+            .map(|key| key.to_string())
+            .collect();
+        assert!(router.query_prices(app, keys.clone()).is_err());
+        let resp = router.query_prices_lenient(app, keys.clone()).unwrap();
+        assert_eq!(resp.iter().map(|r| r.key.clone()).collect::<Vec<_>>(), keys);
+        let kinds: Vec<Option<PriceErrorKind>> = resp
+            .iter()
+            .map(|r| r.error.as_ref().map(|error| error.kind.clone()))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                None,
+                Some(PriceErrorKind::Frozen),
+                Some(PriceErrorKind::Unavailable),
+                Some(PriceErrorKind::Deviation),
+                Some(PriceErrorKind::Stale),
+                None,
+            ]
+        );
+        assert_eq!(
+            resp[0].price.as_ref().unwrap().data.rate,
+            Uint256::from(30_000 * 10u128.pow(18))
+        );
+        assert!(resp[0].error.is_none() && resp[5].price.is_some());
+        assert!(resp[1].price.is_none());
+    }
+
+    /// Tests the default max age, key specific max ages, and that fresh prices are served again.
+    #[test]
+    fn stale_price_tests() {
+        let prices = vec![
+            ("USD", 1_00 * 10u128.pow(16)),
+            ("BTC", 30_000 * 10u128.pow(18)),
+        ];
+
+        let TestScenario {
+            mut app,
+            router,
+            admin,
+            provider,
+            keys,
+            prices,
+            ..
+        } = TestScenario::new(prices);
+        let user = admin;
+        let app = &mut app;
+
+        // No max age is enforced by default.
+        app.update_block(|b| b.time = b.time.plus_seconds(1_000));
+        assert!(router.query_prices(app, keys.clone()).is_ok());
+        assert_eq!(
+            router.query_max_ages(app, keys.clone()).unwrap(),
+            vec![("USD".to_string(), None), ("BTC".to_string(), None)]
+        );
+
+        router
+            .update_config(
+                &user,
+                app,
+                UpdateConfig {
+                    admin_auth: None,
+                    providers: None,
+                    quote_symbol: None,
+                    max_age: Some(Some(600)),
+                    aggregation: None,
+                    timelock: None,
+                },
+            )
+            .unwrap();
+        assert!(router.query_price(app, "USD".to_string()).is_err());
+        assert!(router.query_prices(app, keys.clone()).is_err());
+
+        // BTC gets a longer max age than the default.
+        router
+            .set_max_ages(&user, app, vec![("BTC".to_string(), 3_600)])
+            .unwrap();
+        assert_eq!(
+            router.query_max_ages(app, keys.clone()).unwrap(),
+            vec![
+                ("USD".to_string(), Some(600)),
+                ("BTC".to_string(), Some(3_600))
+            ]
+        );
+        assert!(router.query_price(app, "BTC".to_string()).is_ok());
+        assert!(router.query_price(app, "USD".to_string()).is_err());
+        assert!(router.query_prices(app, keys.clone()).is_err());
+
+        // Refreshing the prices makes the keys valid again.
+        let now = app.block_info().time.seconds();
+        provider.update_band_prices(&user, app, prices, Some(now));
+        assert!(router.query_prices(app, keys.clone()).is_ok());
+
+        app.update_block(|b| b.time = b.time.plus_seconds(601));
+        assert!(router.query_price(app, "BTC".to_string()).is_ok());
+        // Stale provider data isn't used so there are no sources left for USD.
+        let err = router.query_price(app, "USD".to_string()).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Only 0 sources could be used to price USD"));
+        assert!(err.to_string().contains("Reported data is stale."));
+
+        // Removing the key specific max age falls back to the default.
+        router
+            .remove_max_ages(&user, app, vec!["BTC".to_string()])
+            .unwrap();
+        assert!(router.query_price(app, "BTC".to_string()).is_err());
+
+        // Leaving the max age out keeps the default, setting it to null removes it.
+        let config = |max_age| UpdateConfig {
+            admin_auth: None,
+            providers: None,
+            quote_symbol: None,
+            max_age,
+            aggregation: None,
+            timelock: None,
+        };
+        router.update_config(&user, app, config(None)).unwrap();
+        assert!(router.query_price(app, "BTC".to_string()).is_err());
+        router
+            .update_config(&user, app, config(Some(None)))
+            .unwrap();
+        assert_eq!(router.query_config(app).unwrap().config.max_age, None);
+        assert!(router.query_prices(app, keys).is_ok());
+    }
+
+    /// Tests aggregating the prices of Band, Ojo, and an extra oracle source with each method.
+    #[test]
+    fn aggregation_tests() {
+        let prices = vec![
+            ("USD", 1_00 * 10u128.pow(16)),
+            ("BTC", 30_000 * 10u128.pow(18)),
+        ];
+
+        let TestScenario {
+            mut app,
+            router,
+            admin,
+            provider,
+            admin_auth,
+            keys,
+            ..
+        } = TestScenario::new(prices);
+        let user = admin;
+        let app = &mut app;
+
+        let ojo = MockProviderHelper::init_ojo(
+            &user,
+            app,
+            vec![
+                (
+                    "USD".into(),
+                    "USD".into(),
+                    Uint256::from(1_02 * 10u128.pow(16)),
+                ),
+                (
+                    "BTC".into(),
+                    "USD".into(),
+                    Uint256::from(30_300 * 10u128.pow(18)),
+                ),
+            ],
+            admin_auth.clone().into(),
+            Some("USD".into()),
+        );
+        // Mock band supports the oracle query API so it is used as an extra source.
+        let source = MockProviderHelper::init_band(
+            &user,
+            app,
+            vec![(
+                "USD".into(),
+                "USD".into(),
+                Uint128::new(1_50 * 10u128.pow(16)),
+            )],
+            admin_auth.clone().into(),
+            Some("USD".into()),
+        );
+
+        let update_aggregation = |app: &mut App, aggregation: Aggregation| {
+            router.update_config(
+                &user,
+                app,
+                UpdateConfig {
+                    admin_auth: None,
+                    providers: Some(vec![
+                        RawProvider::Band(provider.clone().into()),
+                        RawProvider::Ojo(ojo.clone().into()),
+                    ]),
+                    quote_symbol: None,
+                    max_age: None,
+                    aggregation: Some(aggregation),
+                    timelock: None,
+                },
+            )
+        };
+        let usd_rate = |app: &App| router.query_price(app, "USD".into()).unwrap().data.rate;
+
+        assert!(update_aggregation(app, Aggregation::new(AggregationMethod::Median, 0)).is_err());
+        update_aggregation(app, Aggregation::new(AggregationMethod::Median, 2)).unwrap();
+        assert_eq!(usd_rate(app), Uint256::from(1_01 * 10u128.pow(16)));
+
+        router
+            .set_sources(&user, app, "USD", vec![source.clone().into()])
+            .unwrap();
+        let sources = router.query_sources(app, "USD").unwrap();
+        assert_eq!(sources.providers.len(), 2);
+        assert_eq!(sources.oracles, vec![source.clone().into()]);
+        assert_eq!(usd_rate(app), Uint256::from(1_02 * 10u128.pow(16)));
+        let resp = router.query_prices(app, keys.clone()).unwrap();
+        assert_eq!(resp[0].data.rate, Uint256::from(1_02 * 10u128.pow(16)));
+        assert_eq!(resp[1].data.rate, Uint256::from(30_150 * 10u128.pow(18)));
+
+        // The extra source is an outlier so it gets rejected.
+        let mean = AggregationMethod::Mean {
+            max_deviation: Decimal256::percent(5),
+        };
+        update_aggregation(app, Aggregation::new(mean.clone(), 2)).unwrap();
+        assert_eq!(usd_rate(app), Uint256::from(1_01 * 10u128.pow(16)));
+        update_aggregation(app, Aggregation::new(mean, 3)).unwrap();
+        assert!(router.query_price(app, "USD".into()).is_err());
+        assert!(router.query_prices(app, keys.clone()).is_err());
+
+        update_aggregation(app, Aggregation::new(AggregationMethod::FirstHealthy, 1)).unwrap();
+        assert_eq!(usd_rate(app), Uint256::from(1_00 * 10u128.pow(16)));
+
+        // Ojo answers while Band is down.
+        user.exec(app, &BandExecuteMsg::SetStatus(false), &provider.0)
+            .unwrap();
+        assert_eq!(usd_rate(app), Uint256::from(1_02 * 10u128.pow(16)));
+        let resp = router.query_prices(app, keys.clone()).unwrap();
+        assert_eq!(resp[1].data.rate, Uint256::from(30_300 * 10u128.pow(18)));
+
+        update_aggregation(app, Aggregation::new(AggregationMethod::Median, 3)).unwrap();
+        assert!(router.query_price(app, "USD".into()).is_err());
+        update_aggregation(app, Aggregation::new(AggregationMethod::Median, 2)).unwrap();
+        assert_eq!(usd_rate(app), Uint256::from(1_26 * 10u128.pow(16)));
+    }
+
+    /// Tests that each key fails over from Band to Ojo independently and reports the provider that answered.
+    #[test]
+    fn provider_failover_tests() {
+        let prices = vec![
+            ("USD", 1_00 * 10u128.pow(16)),
+            ("BTC", 30_000 * 10u128.pow(18)),
+        ];
+
+        let TestScenario {
+            mut app,
+            router,
+            admin,
+            provider,
+            admin_auth,
+            ..
+        } = TestScenario::new(prices);
+        let user = admin;
+        let app = &mut app;
+
+        // Ojo is the only provider with ETH.
+        let ojo = MockProviderHelper::init_ojo(
+            &user,
+            app,
+            vec![
+                (
+                    "USD".into(),
+                    "USD".into(),
+                    Uint256::from(1_02 * 10u128.pow(16)),
+                ),
+                (
+                    "ETH".into(),
+                    "USD".into(),
+                    Uint256::from(2_000 * 10u128.pow(18)),
+                ),
+            ],
+            admin_auth.clone().into(),
+            Some("USD".into()),
+        );
+        let band_source = PriceSource::Provider(Provider::Band(provider.clone().into()));
+        let ojo_source = PriceSource::Provider(Provider::Ojo(ojo.clone().into()));
+
+        assert!(router.query_price(app, "ETH".into()).is_err());
+        router
+            .update_config(
+                &user,
+                app,
+                UpdateConfig {
+                    admin_auth: None,
+                    providers: Some(vec![
+                        RawProvider::Band(provider.clone().into()),
+                        RawProvider::Ojo(ojo.clone().into()),
+                    ]),
+                    quote_symbol: None,
+                    max_age: None,
+                    aggregation: None,
+                    timelock: None,
+                },
+            )
+            .unwrap();
+
+        let price = router.query_price_detailed(app, "ETH").unwrap();
+        assert_eq!(price.price.data.rate, Uint256::from(2_000 * 10u128.pow(18)));
+        assert_eq!(price.source, Some(ojo_source.clone()));
+
+        // Band fails the bulk query because it is missing ETH, but still answers for USD and BTC.
+        let keys = vec!["USD".to_string(), "ETH".to_string(), "BTC".to_string()];
+        let resp = router.query_prices(app, keys.clone()).unwrap();
+        assert_eq!(resp[0].data.rate, Uint256::from(1_00 * 10u128.pow(16)));
+        assert_eq!(resp[1].data.rate, Uint256::from(2_000 * 10u128.pow(18)));
+        let price = router.query_price_detailed(app, "BTC").unwrap();
+        assert_eq!(price.source, Some(band_source));
+
+        // Stale data also fails over to the next provider.
+        router
+            .set_max_ages(&user, app, vec![("USD".to_string(), 100)])
+            .unwrap();
+        app.update_block(|b| b.time = b.time.plus_seconds(200));
+        let now = app.block_info().time.seconds();
+        user.exec(
+            app,
+            &OjoExecuteMsg::SetPrice(MockPrice {
+                base_symbol: "USD".into(),
+                quote_symbol: "USD".into(),
+                rate: Uint256::from(1_01 * 10u128.pow(16)),
+                last_updated: Some(now),
+            }),
+            &ojo.0,
+        )
+        .unwrap();
+        let resp = router.query_prices(app, keys.clone()).unwrap();
+        assert_eq!(resp[0].data.rate, Uint256::from(1_01 * 10u128.pow(16)));
+        let price = router.query_price_detailed(app, "USD").unwrap();
+        assert_eq!(price.source, Some(ojo_source.clone()));
+
+        // Every provider is down for BTC.
+        user.exec(app, &BandExecuteMsg::SetStatus(false), &provider.0)
+            .unwrap();
+        assert!(router.query_price(app, "BTC".into()).is_err());
+        assert!(router.query_prices(app, keys).is_err());
+        let price = router.query_price_detailed(app, "USD").unwrap();
+        assert_eq!(price.source, Some(ojo_source));
+    }
+
+    #[test]
+    fn median_keys_tests() {
+        let prices = vec![
+            ("USD", 1_00 * 10u128.pow(16)),
+            ("BTC", 30_000 * 10u128.pow(18)),
+        ];
+
+        let TestScenario {
+            mut app,
+            router,
+            admin,
+            user,
+            provider,
+            admin_auth,
+            ..
+        } = TestScenario::new(prices);
+        let app = &mut app;
+
+        let ojo = MockProviderHelper::init_ojo(
+            &admin,
+            app,
+            vec![(
+                "USD".into(),
+                "USD".into(),
+                Uint256::from(1_02 * 10u128.pow(16)),
+            )],
+            admin_auth.clone().into(),
+            Some("USD".into()),
+        );
+        let band_source = PriceSource::Provider(Provider::Band(provider.clone().into()));
+        let ojo_source = PriceSource::Provider(Provider::Ojo(ojo.clone().into()));
+        router
+            .update_config(
+                &admin,
+                app,
+                UpdateConfig {
+                    admin_auth: None,
+                    providers: Some(vec![
+                        RawProvider::Band(provider.clone().into()),
+                        RawProvider::Ojo(ojo.clone().into()),
+                    ]),
+                    quote_symbol: None,
+                    max_age: None,
+                    aggregation: None,
+                    timelock: None,
+                },
+            )
+            .unwrap();
+
+        let price = router.query_price_detailed(app, "USD").unwrap();
+        assert_eq!(price.price.data.rate, Uint256::from(1_00 * 10u128.pow(16)));
+        assert_eq!(price.source, Some(band_source.clone()));
+
+        assert!(router
+            .set_median_keys(&user, app, vec!["USD".to_string()])
+            .is_err());
+        router
+            .set_median_keys(&admin, app, vec!["USD".to_string()])
+            .unwrap();
+        assert!(router.query_sources(app, "USD").unwrap().median);
+        assert!(!router.query_sources(app, "BTC").unwrap().median);
+
+        // Band has no median data, so USD is served by Ojo.
+        let price = router.query_price_detailed(app, "USD").unwrap();
+        assert_eq!(price.price.data.rate, Uint256::from(1_02 * 10u128.pow(16)));
+        assert_eq!(price.source, Some(ojo_source));
+
+        let keys = vec!["BTC".to_string(), "USD".to_string()];
+        let resp = router.query_prices(app, keys.clone()).unwrap();
+        assert_eq!(resp[0].key(), "BTC");
+        assert_eq!(resp[0].data.rate, Uint256::from(30_000 * 10u128.pow(18)));
+        assert_eq!(resp[1].key(), "USD");
+        assert_eq!(resp[1].data.rate, Uint256::from(1_02 * 10u128.pow(16)));
+
+        router
+            .remove_median_keys(&admin, app, vec!["USD".to_string()])
+            .unwrap();
+        let resp = router.query_prices(app, keys).unwrap();
+        assert_eq!(resp[1].data.rate, Uint256::from(1_00 * 10u128.pow(16)));
+        let price = router.query_price_detailed(app, "USD").unwrap();
+        assert_eq!(price.source, Some(band_source));
+    }
+
     #[rstest]
     #[case(PricesFixture::basic_prices_1())]
     #[case(PricesFixture::basic_prices_2())]
@@ -306,8 +1973,11 @@ mod test {
                 &mut app,
                 UpdateConfig {
                     admin_auth: None,
-                    provider: None,
+                    providers: None,
                     quote_symbol: Some("JPY".to_string()),
+                    max_age: None,
+                    aggregation: None,
+                    timelock: None,
                 },
             )
             .unwrap();
@@ -322,8 +1992,11 @@ mod test {
                 &mut app,
                 UpdateConfig {
                     admin_auth: None,
-                    provider: None,
-                    quote_symbol: Some("USD".to_string())
+                    providers: None,
+                    quote_symbol: Some("USD".to_string()),
+                    max_age: None,
+                    aggregation: None,
+                    timelock: None,
                 }
             )
             .is_err());
@@ -337,8 +2010,11 @@ mod test {
                 &mut app,
                 UpdateConfig {
                     admin_auth: None,
-                    provider: None,
-                    quote_symbol: Some("USD".to_string())
+                    providers: None,
+                    quote_symbol: Some("USD".to_string()),
+                    max_age: None,
+                    aggregation: None,
+                    timelock: None,
                 }
             )
             .is_ok());
@@ -380,6 +2056,7 @@ mod test {
             RegistryOperation::SetKeys {
                 oracle: provider.clone().into(),
                 keys: keys_to_remove.clone(),
+                max_deviation: None,
             },
             RegistryOperation::RemoveKeys {
                 keys: keys_to_remove.clone(),
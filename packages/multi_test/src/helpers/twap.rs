@@ -0,0 +1,244 @@
+use super::*;
+use crate::harness::twap::TwapOracle;
+use shade_oracles::interfaces::{common::config::CommonConfigResponse, twap::*};
+
+create_test_helper!(TwapOracleHelper);
+
+impl TwapOracleHelper {
+    pub fn init(sender: &User, app: &mut App, router: &Contract) -> Self {
+        let contract = sender
+            .init(
+                app,
+                &InstantiateMsg {
+                    router: router.clone().into(),
+                },
+                TwapOracle::default(),
+                "twap_oracle",
+            )
+            .unwrap();
+        Self(contract)
+    }
+
+    pub fn set_twaps(
+        &self,
+        sender: &User,
+        app: &mut App,
+        twaps: &[TwapConfig],
+    ) -> AnyResult<AppResponse> {
+        sender.exec(app, &ExecuteMsg::SetTwaps(twaps.to_vec()), &self.0)
+    }
+
+    pub fn remove_twaps(
+        &self,
+        sender: &User,
+        app: &mut App,
+        keys: &[String],
+    ) -> AnyResult<AppResponse> {
+        sender.exec(app, &ExecuteMsg::RemoveTwaps(keys.to_vec()), &self.0)
+    }
+
+    pub fn push_observations(
+        &self,
+        sender: &User,
+        app: &mut App,
+        observations: &[(String, Uint256)],
+    ) -> AnyResult<AppResponse> {
+        sender.exec(
+            app,
+            &ExecuteMsg::PushObservations(observations.to_vec()),
+            &self.0,
+        )
+    }
+
+    pub fn pull_observations(
+        &self,
+        sender: &User,
+        app: &mut App,
+        keys: &[String],
+    ) -> AnyResult<AppResponse> {
+        sender.exec(app, &ExecuteMsg::PullObservations(keys.to_vec()), &self.0)
+    }
+
+    pub fn set_status(&self, sender: &User, app: &mut App, status: bool) -> AnyResult<AppResponse> {
+        sender.exec(app, &ExecuteMsg::SetStatus(status), &self.0)
+    }
+
+    pub fn query_price(&self, app: &App, key: &str) -> StdResult<PriceResponse> {
+        QueryMsg::GetPrice {
+            key: key.to_string(),
+        }
+        .test_query(&self.0, app)
+    }
+    pub fn query_twaps(&self, app: &App) -> StdResult<TwapsResponse> {
+        QueryMsg::GetTwaps {}.test_query(&self.0, app)
+    }
+    pub fn query_observations(&self, app: &App, key: &str) -> StdResult<ObservationsResponse> {
+        QueryMsg::GetObservations {
+            key: key.to_string(),
+        }
+        .test_query(&self.0, app)
+    }
+    pub fn query_config(&self, app: &App) -> StdResult<CommonConfigResponse> {
+        QueryMsg::GetConfig {}.test_query(&self.0, app)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::Timestamp;
+
+    const KEY: &str = "SHD (TWAP 1h)";
+
+    fn twap_config(min_observations: u32, max_observations: u32) -> TwapConfig {
+        TwapConfig {
+            key: KEY.to_string(),
+            source_key: "SHD".to_string(),
+            window: 3600,
+            min_observations,
+            max_observations,
+        }
+    }
+
+    fn price(rate: u128) -> Uint256 {
+        Uint256::from(rate * 10u128.pow(18))
+    }
+
+    #[test]
+    fn twap_push_tests() {
+        let prices = vec![("USD", 1_00 * 10u128.pow(16)), ("SHD", 10 * 10u128.pow(18))];
+        let TestScenario {
+            mut app,
+            admin,
+            bot,
+            user,
+            router,
+            admin_auth,
+            ..
+        } = TestScenario::new(prices);
+        let app = &mut app;
+        let oracle = TwapOracleHelper::init(&user, app, &router.clone().into());
+
+        assert!(oracle.set_twaps(&user, app, &[twap_config(3, 4)]).is_err());
+        assert!(oracle.set_twaps(&admin, app, &[twap_config(5, 4)]).is_err());
+        oracle.set_twaps(&admin, app, &[twap_config(3, 4)]).unwrap();
+        assert_eq!(oracle.query_config(app).unwrap().supported_keys, vec![KEY]);
+
+        let push = |app: &mut App, time: u64, rate: u128| {
+            app.update_block(|b| b.time = Timestamp::from_seconds(time));
+            oracle.push_observations(&bot, app, &[(KEY.to_string(), price(rate))])
+        };
+        assert!(push(app, 0, 10).is_err());
+        admin_auth.register_admin(&admin, app, bot.str());
+        admin_auth.grant_access(
+            &admin,
+            app,
+            bot.str(),
+            vec![BotPermission::PushObservations.to_string()],
+        );
+        push(app, 0, 10).unwrap();
+        assert!(oracle.query_price(app, KEY).is_err());
+        push(app, 600, 12).unwrap();
+        push(app, 1200, 11).unwrap();
+
+        app.update_block(|b| b.time = Timestamp::from_seconds(1800));
+        let resp = oracle.query_price(app, KEY).unwrap();
+        assert_eq!(resp.data.rate, price(11));
+        assert_eq!(resp.data.last_updated_base, 1200);
+
+        // The 10 observed at 0 falls out of the window.
+        push(app, 3600, 14).unwrap();
+        app.update_block(|b| b.time = Timestamp::from_seconds(4200));
+        let resp = oracle.query_price(app, KEY).unwrap();
+        assert_eq!(
+            resp.data.rate,
+            price(12 * 600 + 11 * 2400 + 14 * 600) / Uint256::from(3600u128)
+        );
+
+        // Ring buffer is full so the oldest observation gets overwritten.
+        push(app, 4200, 13).unwrap();
+        // The latest observation can't be replaced within the same block.
+        assert!(push(app, 4200, 15).is_err());
+        let observations = oracle.query_observations(app, KEY).unwrap();
+        assert_eq!(observations.len(), 4);
+        assert_eq!(observations[0].timestamp, 600);
+        assert_eq!(observations[3].rate, price(13));
+
+        router
+            .set_keys(&admin, app, oracle.clone().into(), vec![KEY.to_string()])
+            .unwrap();
+        let resp = router.query_price(app, KEY.to_string()).unwrap();
+        assert_eq!(
+            resp.data.rate,
+            oracle.query_price(app, KEY).unwrap().data.rate
+        );
+
+        // Not enough recent observations.
+        app.update_block(|b| b.time = Timestamp::from_seconds(8000));
+        assert!(oracle.query_price(app, KEY).is_err());
+
+        oracle
+            .remove_twaps(&admin, app, &[KEY.to_string()])
+            .unwrap();
+        assert!(oracle.query_twaps(app).unwrap().is_empty());
+        assert!(oracle.query_observations(app, KEY).is_err());
+    }
+
+    #[test]
+    fn twap_pull_tests() {
+        let prices = vec![("USD", 1_00 * 10u128.pow(16)), ("SHD", 10 * 10u128.pow(18))];
+        let TestScenario {
+            mut app,
+            admin,
+            user,
+            router,
+            provider,
+            ..
+        } = TestScenario::new(prices);
+        let app = &mut app;
+        let oracle = TwapOracleHelper::init(&user, app, &router.into());
+        oracle
+            .set_twaps(&admin, app, &[twap_config(2, 10)])
+            .unwrap();
+
+        oracle
+            .pull_observations(&user, app, &[KEY.to_string()])
+            .unwrap();
+        app.update_block(|b| b.time = Timestamp::from_seconds(1800));
+        provider.update_band_prices(
+            &admin,
+            app,
+            HashMap::from([("SHD".to_string(), Uint128::from(20 * 10u128.pow(18)))]),
+            Some(1800),
+        );
+        oracle
+            .pull_observations(&user, app, &[KEY.to_string()])
+            .unwrap();
+        // Pulls have to be a tenth of the window apart.
+        assert!(oracle
+            .pull_observations(&user, app, &[KEY.to_string()])
+            .is_err());
+        app.update_block(|b| b.time = Timestamp::from_seconds(2159));
+        assert!(oracle
+            .pull_observations(&user, app, &[KEY.to_string()])
+            .is_err());
+        app.update_block(|b| b.time = Timestamp::from_seconds(2160));
+        oracle
+            .pull_observations(&user, app, &[KEY.to_string()])
+            .unwrap();
+        assert_eq!(oracle.query_observations(app, KEY).unwrap().len(), 3);
+
+        app.update_block(|b| b.time = Timestamp::from_seconds(3600));
+        let resp = oracle.query_price(app, KEY).unwrap();
+        assert_eq!(resp.data.rate, price(15));
+        assert!(oracle
+            .pull_observations(&user, app, &["SHD".to_string()])
+            .is_err());
+
+        oracle.set_status(&admin, app, false).unwrap();
+        assert!(oracle.query_price(app, KEY).is_err());
+        assert!(oracle
+            .pull_observations(&user, app, &[KEY.to_string()])
+            .is_err());
+    }
+}
@@ -6,6 +6,7 @@ use better_secret_math::{
     ud60x18::{mul, sqrt},
     U256,
 };
+use cosmwasm_std::Decimal256;
 
 /// Provides methods that deal with token prices, values, and amounts.
 pub struct TokenMath;
@@ -89,6 +90,96 @@ impl LiquidityPoolMath {
         let rp2 = muldiv(r, p, normalized_supply)? * U256::from(2u128);
         Ok(rp2.into())
     }
+
+    /// Calculates the price of a stableswap LP token from the invariant of the pool,
+    /// which doesn't change when the reserves are imbalanced by a swap.
+    ///
+    /// Assumes token prices are normalized to 10^18.
+    pub fn get_stableswap_lp_token_price(
+        a: FairLpPriceInfo,
+        b: FairLpPriceInfo,
+        amp: Decimal256,
+        gamma: u32,
+        total_supply: u128,
+        lp_token_decimals: u8,
+    ) -> StdResult<Uint256> {
+        let value_a = muldiv(
+            TokenMath::normalize_value(a.reserve, a.decimals)?,
+            a.price,
+            exp10(18),
+        )?;
+        let value_b = muldiv(
+            TokenMath::normalize_value(b.reserve, b.decimals)?,
+            b.price,
+            exp10(18),
+        )?;
+        let invariant = Self::get_stableswap_invariant(value_a.into(), value_b.into(), amp, gamma)?;
+        let normalized_supply: Uint256 =
+            TokenMath::normalize_value(total_supply, lp_token_decimals)?.into();
+        if normalized_supply.is_zero() {
+            return Err(StdError::generic_err("LP token has no supply."));
+        }
+        Ok(invariant.multiply_ratio(10u128.pow(18), normalized_supply))
+    }
+
+    /// Calculates the invariant D of a 2 token StableSwap pool (https://curve.fi/files/stableswap-paper.pdf),
+    /// which is the total value of the reserves once balanced.
+    ///
+    /// The amplification is scaled by (4xy / D^2)^gamma as ShadeSwap pairs do, a gamma of 0 being the Curve invariant.
+    /// D is found by bisection between 2 * min(x, y) and x + y and rounded down.
+    pub fn get_stableswap_invariant(
+        x: Uint256,
+        y: Uint256,
+        amp: Decimal256,
+        gamma: u32,
+    ) -> StdResult<Uint256> {
+        if x.is_zero() || y.is_zero() {
+            return Ok(Uint256::zero());
+        }
+        if amp <= Decimal256::percent(25) {
+            return Err(StdError::generic_err(
+                "StableSwap amplification must be greater than 0.25.",
+            ));
+        }
+        let mut low = x.min(y).checked_mul(Uint256::from(2u128))?;
+        let mut high = x.checked_add(y)?;
+        while high - low > Uint256::one() {
+            let mid = low + (high - low) / Uint256::from(2u128);
+            if Self::is_below_stableswap_invariant(x, y, mid, amp, gamma) {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        Ok(low)
+    }
+
+    /// Whether D is below the invariant of the reserves, i.e.
+    /// Ann * (x + y) + D > Ann * D + D^3 / (4xy) with Ann = 4A * (4xy / D^2)^gamma.
+    fn is_below_stableswap_invariant(
+        x: Uint256,
+        y: Uint256,
+        d: Uint256,
+        amp: Decimal256,
+        gamma: u32,
+    ) -> bool {
+        let one = Uint256::from(10u128.pow(18));
+        let two = Uint256::from(2u128);
+        // 4xy / D^2 is 1 for a balanced pool and shrinks as the pool gets imbalanced.
+        let balance = x
+            .multiply_ratio(y * Uint256::from(4u128), d)
+            .multiply_ratio(one, d)
+            .min(one);
+        let mut scale = one;
+        for _ in 0..gamma {
+            scale = scale.multiply_ratio(balance, one);
+        }
+        let ann = amp.atomics().multiply_ratio(scale, one) * Uint256::from(4u128);
+        let lhs = (x + y).multiply_ratio(ann, one) + d;
+        let rhs =
+            d.multiply_ratio(ann, one) + d.multiply_ratio(d, x * two).multiply_ratio(d, y * two);
+        lhs > rhs
+    }
 }
 
 #[cfg(test)]
@@ -131,4 +222,83 @@ pub mod test {
         let deviation = Decimal256::from_ratio(diff, Uint256::from_u128(expected));
         assert!(deviation < Decimal256::from_ratio(1u128, 10u128.pow(10)))
     }
+
+    #[test]
+    fn test_get_stableswap_invariant() {
+        let amp = Decimal256::from_ratio(10u128, 1u128);
+        let balanced = LiquidityPoolMath::get_stableswap_invariant(
+            Uint256::from(10u128.pow(18)),
+            Uint256::from(10u128.pow(18)),
+            amp,
+            0,
+        )
+        .unwrap();
+        assert_eq!(balanced, Uint256::from(2 * 10u128.pow(18)));
+
+        let imbalanced = LiquidityPoolMath::get_stableswap_invariant(
+            Uint256::from(19 * 10u128.pow(17)),
+            Uint256::from(10u128.pow(17)),
+            amp,
+            0,
+        )
+        .unwrap();
+        assert_eq!(imbalanced, Uint256::from(1840826504358923674u128));
+
+        let empty =
+            LiquidityPoolMath::get_stableswap_invariant(Uint256::zero(), Uint256::one(), amp, 0)
+                .unwrap();
+        assert!(empty.is_zero());
+        assert!(LiquidityPoolMath::get_stableswap_invariant(
+            Uint256::one(),
+            Uint256::one(),
+            Decimal256::percent(25),
+            0
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_stableswap_invariant_gamma() {
+        let amp = Decimal256::from_ratio(10u128, 1u128);
+        let x = Uint256::from(19 * 10u128.pow(17));
+        let y = Uint256::from(10u128.pow(17));
+        // 2 * sqrt(xy)
+        let constant_product: Uint256 =
+            (sqrt(mul(x.into(), y.into()).unwrap()).unwrap() * U256::from(2u128)).into();
+
+        // Gamma lowers the amplification of imbalanced pools towards the constant product.
+        let mut previous = LiquidityPoolMath::get_stableswap_invariant(x, y, amp, 0).unwrap();
+        for gamma in 1..=8 {
+            let invariant = LiquidityPoolMath::get_stableswap_invariant(x, y, amp, gamma).unwrap();
+            assert!(invariant < previous);
+            assert!(invariant > constant_product);
+            previous = invariant;
+        }
+
+        let invariant = LiquidityPoolMath::get_stableswap_invariant(x, y, amp, 6).unwrap();
+        assert_eq!(invariant, Uint256::from(1172264652063830665u128));
+        // The invariant is the largest D that is not above the reserves' invariant.
+        assert!(LiquidityPoolMath::is_below_stableswap_invariant(
+            x, y, invariant, amp, 6
+        ));
+        assert!(!LiquidityPoolMath::is_below_stableswap_invariant(
+            x,
+            y,
+            invariant + Uint256::one(),
+            amp,
+            6
+        ));
+
+        // Balanced pools are unaffected by gamma.
+        for gamma in [1, 4, 6] {
+            let invariant = LiquidityPoolMath::get_stableswap_invariant(
+                Uint256::from(10u128.pow(18)),
+                Uint256::from(10u128.pow(18)),
+                amp,
+                gamma,
+            )
+            .unwrap();
+            assert_eq!(invariant, Uint256::from(2 * 10u128.pow(18)));
+        }
+    }
 }
@@ -1,6 +1,8 @@
 use crate::interfaces::{
-    common::{OracleQuery, PriceResponse, PricesResponse},
-    router::msg::{ConfigResponse as RouterConfigResponse, QueryMsg as RouterQueryMsg},
+    common::{DependenciesResponse, OracleQuery, PriceResponse, PricesResponse, RoutedOracleQuery},
+    router::msg::{
+        ConfigResponse as RouterConfigResponse, LenientPricesResponse, QueryMsg as RouterQueryMsg,
+    },
 };
 use cosmwasm_std::{QuerierWrapper, StdError, StdResult, Uint128};
 use shade_protocol::{
@@ -32,6 +34,72 @@ pub fn query_prices(
     .query(querier, oracle)
 }
 
+/// Queries the router for the price of the key on behalf of a query made at that routing depth.
+pub fn query_price_at_depth(
+    router: &Contract,
+    querier: &QuerierWrapper,
+    key: impl Into<String>,
+    depth: u8,
+) -> StdResult<PriceResponse> {
+    let key = key.into();
+    let mut prices = query_prices_at_depth(router, querier, &[key.clone()], depth)?;
+    prices
+        .pop()
+        .ok_or_else(|| StdError::generic_err(format!("Router didn't return a price for {key}.")))
+}
+
+pub fn query_prices_at_depth(
+    router: &Contract,
+    querier: &QuerierWrapper,
+    keys: &[String],
+    depth: u8,
+) -> StdResult<PricesResponse> {
+    RouterQueryMsg::GetPricesAtDepth {
+        keys: keys.to_vec(),
+        depth,
+    }
+    .query(querier, router)
+}
+
+/// Queries the router for the price of each key, or why it can't be priced, at the routing depth.
+pub fn query_prices_lenient(
+    router: &Contract,
+    querier: &QuerierWrapper,
+    keys: &[String],
+    depth: u8,
+) -> StdResult<LenientPricesResponse> {
+    RouterQueryMsg::GetPricesLenient {
+        keys: keys.to_vec(),
+        depth,
+    }
+    .query(querier, router)
+}
+
+/// Queries the routed oracle for the prices of the keys, passing on the routing depth.
+pub fn query_routed_prices(
+    oracle: &Contract,
+    querier: &QuerierWrapper,
+    keys: &[String],
+    depth: u8,
+) -> StdResult<PricesResponse> {
+    RoutedOracleQuery::GetPricesAtDepth {
+        keys: keys.to_vec(),
+        depth,
+    }
+    .query(querier, oracle)
+}
+
+pub fn query_dependencies(
+    oracle: &Contract,
+    querier: &QuerierWrapper,
+    keys: &[String],
+) -> StdResult<DependenciesResponse> {
+    RoutedOracleQuery::GetDependencies {
+        keys: keys.to_vec(),
+    }
+    .query(querier, oracle)
+}
+
 /// Gets the admin auth contract from the router and uses it to check if the user is an oracle admiin.
 pub fn require_admin(
     contract: &Contract,
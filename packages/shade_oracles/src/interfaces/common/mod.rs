@@ -1,5 +1,3 @@
-use std::cmp::{max, min};
-
 use super::*;
 use crate::BLOCK_SIZE;
 use better_secret_math::common::{exp10, muldiv};
@@ -9,7 +7,7 @@ use shade_protocol::utils::Query;
 pub mod config;
 
 mod error;
-use super::providers::{BtrReferenceData, ReferenceData};
+use super::providers::{BtrReferenceData, Provider, ReferenceData};
 
 /// Default Query API for all oracles.
 ///
@@ -32,6 +30,25 @@ impl Query for OracleQuery {
     const BLOCK_SIZE: usize = BLOCK_SIZE;
 }
 
+/// Queries supported by oracles that price their keys using other keys of the router,
+/// so the router can follow how keys are routed and stop routing loops.
+#[cw_serde]
+#[derive(QueryResponses)]
+pub enum RoutedOracleQuery {
+    /// Returns the router keys queried to price each key.
+    #[returns(DependenciesResponse)]
+    GetDependencies { keys: Vec<String> },
+    /// Same as GetPrices, passing on the depth to the router when querying it.
+    #[returns(PricesResponse)]
+    GetPricesAtDepth { keys: Vec<String>, depth: u8 },
+}
+
+pub type DependenciesResponse = Vec<(String, Vec<String>)>;
+
+impl Query for RoutedOracleQuery {
+    const BLOCK_SIZE: usize = BLOCK_SIZE;
+}
+
 #[cw_serde]
 #[derive(Default)]
 pub struct OraclePrice {
@@ -39,6 +56,51 @@ pub struct OraclePrice {
     pub data: ReferenceData,
 }
 
+/// Price along with where the router got it from.
+///
+/// Kept apart from `OraclePrice` so the price format consumers rely on doesn't change.
+#[cw_serde]
+pub struct SourcedPrice {
+    pub price: OraclePrice,
+    pub source: Option<PriceSource>,
+}
+
+/// Where the router got a price from.
+#[cw_serde]
+pub enum PriceSource {
+    Provider(Provider),
+    Oracle(Contract),
+    /// Price was aggregated from the data of multiple sources.
+    Aggregate(Vec<PriceSource>),
+    /// Last good price of a protected key, served while its circuit breaker is tripped.
+    CircuitBreaker {
+        recorded_at: u64,
+        source: Option<Box<PriceSource>>,
+    },
+    /// Price was set manually and is served in place of the queried price until it expires.
+    Override {
+        reason: String,
+        expires_at: u64,
+        /// Whether the price skipped the deviation test of the protected key.
+        forced: bool,
+    },
+}
+
+impl PriceSource {
+    /// Providers the price was taken from, in the order they appear in the source.
+    pub fn providers(&self) -> Vec<Provider> {
+        match self {
+            PriceSource::Provider(provider) => vec![provider.clone()],
+            PriceSource::Oracle(_) | PriceSource::Override { .. } => vec![],
+            PriceSource::Aggregate(sources) => sources.iter().flat_map(Self::providers).collect(),
+            PriceSource::CircuitBreaker { source, .. } => source
+                .iter()
+                .flat_map(|source| source.providers())
+                .collect(),
+        }
+    }
+}
+
 impl OraclePrice {
     pub fn new(key: String, reference_data: ReferenceData) -> Self {
         OraclePrice {
@@ -52,6 +114,49 @@ impl OraclePrice {
     pub fn data(&self) -> &ReferenceData {
         &self.data
     }
+    /// Seconds since the price was last updated, which is what its staleness is checked against.
+    pub fn age(&self, now: u64) -> u64 {
+        time_since_updated(
+            now,
+            self.data.last_updated_base,
+            self.data.last_updated_quote,
+        )
+    }
+    /// Price of the key quoted in the key of the quote price, given both prices share the same quote.
+    ///
+    /// Each price is as old as its `age`, and both timestamps of the result are when the older of the two
+    /// was last updated, so a stale leg makes the result look stale whichever timestamp is checked.
+    pub fn cross(self, quote: OraclePrice, now: u64) -> StdResult<OraclePrice> {
+        if quote.data.rate.is_zero() {
+            return Err(StdError::generic_err(format!(
+                "Can't quote {} in {} because the price of {} is zero.",
+                self.key, quote.key, quote.key
+            )));
+        }
+        let rate = muldiv(self.data.rate.into(), exp10(18), quote.data.rate.into())?;
+        let updated = now - self.age(now).max(quote.age(now));
+        let data = ReferenceData::new(rate.into(), updated, updated);
+        Ok(OraclePrice::new(self.key, data))
+    }
+}
+
+impl SourcedPrice {
+    pub fn new(price: OraclePrice, source: PriceSource) -> Self {
+        SourcedPrice {
+            price,
+            source: Some(source),
+        }
+    }
+    pub fn is_override(&self) -> bool {
+        matches!(self.source, Some(PriceSource::Override { .. }))
+    }
+    /// Providers the price was taken from, if any.
+    pub fn providers(&self) -> Vec<Provider> {
+        self.source
+            .as_ref()
+            .map(PriceSource::providers)
+            .unwrap_or_default()
+    }
 }
 
 /// Variant of OraclePrice that is optimized for math.
@@ -88,15 +193,11 @@ impl BtrOraclePrice {
         &self.data
     }
     pub fn time_since_updated(&self, time: &Timestamp) -> StdResult<u64> {
-        let now = time.seconds();
-        let base = self.data().last_updated_base;
-        let quote = self.data().last_updated_quote;
-        let time_since_base = now - base;
-        let time_since_quote = now - quote;
-        // Band randomly started setting the quote of USD to the max u64 value rather than
-        // never updating it.
-        let time_since_updated = min(time_since_base, time_since_quote);
-        Ok(time_since_updated)
+        Ok(time_since_updated(
+            time.seconds(),
+            self.data().last_updated_base,
+            self.data().last_updated_quote,
+        ))
     }
     /// Allows us to pass a variable amount of precision decimals in the future
     /// in case our oracles lose their constant decimal precision (currently 18).
@@ -132,6 +233,27 @@ impl BtrOraclePrice {
     }
 }
 
+/// Seconds since the freshest of the base and quote timestamps.
+fn time_since_updated(now: u64, base: u64, quote: u64) -> u64 {
+    // Band randomly started setting the quote of USD to the max u64 value rather than
+    // never updating it, so timestamps in the future are ignored.
+    [base, quote]
+        .into_iter()
+        .filter_map(|updated| now.checked_sub(updated))
+        .min()
+        .unwrap_or_default()
+}
+
+/// Deserializes a field that is present as `Some`, even if it is null,
+/// so an `Option<Option<T>>` can tell a null field apart from a missing one.
+pub fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: serde::Deserialize<'de>,
+    D: serde::Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
 pub fn throw_unsupported_symbol_error(key: String) -> StdError {
     StdError::generic_err(format!("{key} is not supported as a key."))
 }
@@ -143,3 +265,59 @@ pub fn is_disabled(enabled: bool) -> StdResult<()> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn age_matches_staleness() {
+        let now = Timestamp::from_seconds(1_000);
+        for (base, quote) in [
+            (900, 900),
+            (600, 950),
+            (950, u64::MAX),
+            (u64::MAX, u64::MAX),
+        ] {
+            let price = OraclePrice::new(
+                "BTC".to_string(),
+                ReferenceData::new(Uint256::one(), base, quote),
+            );
+            let age = price.age(now.seconds());
+            let btr = BtrOraclePrice::from(price);
+            assert_eq!(age, btr.time_since_updated(&now).unwrap());
+            assert!(!btr.is_stale_price(age, &now).unwrap());
+            if age > 0 {
+                assert!(btr.is_stale_price(age - 1, &now).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn cross_takes_the_older_leg() {
+        let now = 1_000;
+        let price = |key: &str, rate: u128, base, quote| {
+            OraclePrice::new(
+                key.to_string(),
+                ReferenceData::new(Uint256::from_u128(rate), base, quote),
+            )
+        };
+        // Band never updates the quote timestamp of USD, which doesn't make either leg stale.
+        let shd = price("SHD", 10 * 10u128.pow(18), 950, u64::MAX);
+        let scrt = price("SCRT", 2 * 10u128.pow(18), 900, 0);
+        let resp = shd.clone().cross(scrt.clone(), now).unwrap();
+        assert_eq!(resp.key, "SHD");
+        assert_eq!(resp.data.rate, Uint256::from_u128(5 * 10u128.pow(18)));
+        assert_eq!(resp.data.last_updated_base, 900);
+        assert_eq!(resp.data.last_updated_quote, 900);
+        let resp = scrt.cross(shd.clone(), now).unwrap();
+        assert_eq!(resp.data.last_updated_base, 900);
+        assert_eq!(resp.data.last_updated_quote, 900);
+        assert_eq!(resp.age(now), 100);
+
+        let usd = price("USD", 10u128.pow(18), u64::MAX, u64::MAX);
+        let resp = shd.clone().cross(usd, now).unwrap();
+        assert_eq!(resp.data.last_updated_base, 950);
+        assert!(shd.cross(price("SILK", 0, now, now), now).is_err());
+    }
+}
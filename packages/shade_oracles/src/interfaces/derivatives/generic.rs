@@ -6,7 +6,9 @@ use super::*;
 pub mod msg {
     use crate::{
         asset::{Asset, RawAsset},
-        interfaces::common::{config::CommonConfigResponse, PriceResponse, PricesResponse},
+        interfaces::common::{
+            config::CommonConfigResponse, DependenciesResponse, PriceResponse, PricesResponse,
+        },
     };
 
     use super::*;
@@ -37,6 +39,11 @@ pub mod msg {
         GetConfig {},
         #[returns(DerivativesResponse)]
         GetDerivatives {},
+        /// Derivative keys are priced with the quote symbol of the derivative, rate keys don't depend on any key.
+        #[returns(DependenciesResponse)]
+        GetDependencies { keys: Vec<String> },
+        #[returns(PricesResponse)]
+        GetPricesAtDepth { keys: Vec<String>, depth: u8 },
     }
 
     pub type DerivativesResponse = Vec<DerivativeData>;
@@ -4,7 +4,10 @@
 use super::*;
 
 pub mod msg {
-    use crate::interfaces::common::{config::CommonConfigResponse, PriceResponse, PricesResponse};
+    use crate::interfaces::common::{
+        config::CommonConfigResponse, deserialize_some, DependenciesResponse, PriceResponse,
+        PricesResponse,
+    };
     use cosmwasm_std::Decimal256;
 
     use super::*;
@@ -43,9 +46,18 @@ pub mod msg {
         GetConfig {},
         #[returns(DerivativesResponse)]
         GetDerivatives {},
+        #[returns(RateHistoryResponse)]
+        GetRateHistory { key: String },
+        /// Derivative keys are priced with their underlying key, rate keys don't depend on any key.
+        #[returns(DependenciesResponse)]
+        GetDependencies { keys: Vec<String> },
+        #[returns(PricesResponse)]
+        GetPricesAtDepth { keys: Vec<String>, depth: u8 },
     }
 
     pub type DerivativesResponse = Vec<DerivativeData>;
+    /// Ordered from oldest to latest.
+    pub type RateHistoryResponse = Vec<HistoricalRate>;
 
     #[cw_serde]
     pub struct RawDerivativeData {
@@ -56,6 +68,10 @@ pub mod msg {
         pub initial_rate: Decimal256,
         pub rate_timeout: u64,
         pub rate_max_change: Decimal256,
+        /// Maximum growth of the rate per year, i.e. 0.2 for 20% APY.
+        pub rate_max_annual_growth: Option<Decimal256>,
+        /// Whether the rate can decrease, defaults to false.
+        pub allow_rate_decrease: Option<bool>,
     }
 
     #[cw_serde]
@@ -63,6 +79,14 @@ pub mod msg {
         pub underlying_key: Option<String>,
         pub rate_timeout: Option<u64>,
         pub rate_max_change: Option<Decimal256>,
+        /// Set to null to remove the growth limit, leave out to keep it unchanged.
+        #[serde(
+            default,
+            skip_serializing_if = "Option::is_none",
+            deserialize_with = "deserialize_some"
+        )]
+        pub rate_max_annual_growth: Option<Option<Decimal256>>,
+        pub allow_rate_decrease: Option<bool>,
     }
 
     #[cw_serde]
@@ -78,6 +102,18 @@ pub mod msg {
         pub timeout: u64,
         pub last_updated: u64,
         pub max_change: Decimal256,
+        /// Maximum growth of the rate per year, prorated over the time since the last update.
+        #[serde(default)]
+        pub max_annual_growth: Option<Decimal256>,
+        /// Redemption rates only grow, so a decrease is rejected unless an admin allows it.
+        #[serde(default)]
+        pub allow_decrease: bool,
+    }
+
+    #[cw_serde]
+    pub struct HistoricalRate {
+        pub value: Decimal256,
+        pub timestamp: u64,
     }
 
     #[cw_serde]
@@ -90,11 +126,15 @@ pub mod msg {
             underlying_key: Option<String>,
             rate_timeout: Option<u64>,
             rate_max_change: Option<Decimal256>,
+            rate_max_annual_growth: Option<Option<Decimal256>>,
+            allow_rate_decrease: Option<bool>,
         ) -> Self {
             Self {
                 underlying_key,
                 rate_max_change,
                 rate_timeout,
+                rate_max_annual_growth,
+                allow_rate_decrease,
             }
         }
     }
@@ -131,6 +171,8 @@ mod state {
                 timeout,
                 last_updated,
                 max_change,
+                max_annual_growth: None,
+                allow_decrease: false,
             };
             rate.set_value(value)?;
             Ok(rate)
@@ -152,14 +194,33 @@ mod state {
                 Ok(())
             }
         }
-        pub fn require_valid_change(
-            &self,
-            new_rate: Decimal256,
-        ) -> StdResult<()> {
+        pub fn require_valid_change(&self, new_rate: Decimal256, now: u64) -> StdResult<()> {
             if self.value.eq(&new_rate) {
                 return Ok(());
             }
 
+            if new_rate < self.value && !self.allow_decrease {
+                return Err(StdError::generic_err(format!(
+                    "Derivative rate cannot decrease from {} to {new_rate}.",
+                    self.value
+                )));
+            }
+
+            if let Some(max_annual_growth) = self.max_annual_growth {
+                if new_rate > self.value {
+                    let elapsed = now.saturating_sub(self.last_updated);
+                    let max_growth = self.value
+                        * max_annual_growth
+                        * Decimal256::from_ratio(elapsed, StrideStakingDerivativesOracle::YEAR);
+                    let growth = new_rate - self.value;
+                    if growth > max_growth {
+                        return Err(StdError::generic_err(format!(
+                            "Derivative rate is growing too fast. Maximum growth since the last update is {max_growth}. Attempted growth is {growth}."
+                        )));
+                    }
+                }
+            }
+
             let change = if self.value > new_rate {
                 self.value - new_rate
             } else {
@@ -211,11 +272,17 @@ mod state {
     impl<'a> StrideStakingDerivativesOracle {
         // Keyed by its symbol.
         pub const DERIVATIVES: Map<'static, &'a str, DerivativeData> = Map::new("derivatives");
+        // Keyed by its symbol, ordered from oldest to latest.
+        pub const RATE_HISTORY: Map<'static, &'a str, Vec<HistoricalRate>> =
+            Map::new("derivative_rate_history");
     }
 
     impl StrideStakingDerivativesOracle {
         /// To be appended to key to signal that consumer wants the rate.
         pub const RATE_STRING: &'static str = " Rate";
+        /// Max amount of past rates kept per derivative.
+        pub const MAX_RATE_HISTORY: usize = 30;
+        pub const YEAR: u64 = 365 * 24 * 60 * 60;
 
         pub fn create_rate_key(key: &str) -> String {
             format!("{}{}", key, Self::RATE_STRING)
@@ -237,16 +304,26 @@ mod state {
             now: u64,
             derivatives: Vec<RawDerivativeData>,
         ) -> StdResult<()> {
-            for data in derivatives {
-                let data = DerivativeData::new(
-                    data.key,
-                    data.underlying_key,
-                    data.initial_rate,
-                    data.rate_max_change,
-                    data.rate_timeout,
+            for raw in derivatives {
+                let mut data = DerivativeData::new(
+                    raw.key,
+                    raw.underlying_key,
+                    raw.initial_rate,
+                    raw.rate_max_change,
+                    raw.rate_timeout,
                     now,
                 )?;
+                data.rate.max_annual_growth = raw.rate_max_annual_growth;
+                data.rate.allow_decrease = raw.allow_rate_decrease.unwrap_or_default();
                 Self::DERIVATIVES.save(storage, &data.key, &data)?;
+                Self::RATE_HISTORY.save(
+                    storage,
+                    &data.key,
+                    &vec![HistoricalRate {
+                        value: data.rate.value,
+                        timestamp: now,
+                    }],
+                )?;
                 CommonConfig::add_supported_key(storage, &data.key)?;
             }
             Ok(())
@@ -257,6 +334,7 @@ mod state {
             for key in keys {
                 if supported_keys.remove(&key) {
                     Self::DERIVATIVES.remove(storage, &key);
+                    Self::RATE_HISTORY.remove(storage, &key);
                 }
             }
             CommonConfig::SUPPORTED_KEYS.save(storage, &supported_keys)?;
@@ -285,6 +363,15 @@ mod state {
                 if let Some(rate_timeout) = update.rate_timeout {
                     data.rate.timeout = rate_timeout;
                 }
+                if let Some(rate_max_change) = update.rate_max_change {
+                    data.rate.max_change = rate_max_change;
+                }
+                if let Some(rate_max_annual_growth) = update.rate_max_annual_growth {
+                    data.rate.max_annual_growth = rate_max_annual_growth;
+                }
+                if let Some(allow_rate_decrease) = update.allow_rate_decrease {
+                    data.rate.allow_decrease = allow_rate_decrease;
+                }
                 Self::DERIVATIVES.save(storage, &key, &data)?;
             }
             Ok(())
@@ -297,14 +384,44 @@ mod state {
         ) -> StdResult<()> {
             for (key, rate) in rates {
                 let mut data = Self::DERIVATIVES.load(storage, &key)?;
-                data.rate.require_valid_change(rate)?;
+                data.rate.require_valid_change(rate, now)?;
                 data.rate.last_updated = now;
                 data.rate.value = rate;
                 Self::DERIVATIVES.save(storage, &key, &data)?;
+                Self::record_rate(storage, &key, rate, now)?;
             }
             Ok(())
         }
 
+        fn record_rate(
+            storage: &mut dyn Storage,
+            key: &str,
+            value: Decimal256,
+            now: u64,
+        ) -> StdResult<()> {
+            let mut history = Self::RATE_HISTORY
+                .may_load(storage, key)?
+                .unwrap_or_default();
+            history.push(HistoricalRate {
+                value,
+                timestamp: now,
+            });
+            if history.len() > Self::MAX_RATE_HISTORY {
+                history.drain(..history.len() - Self::MAX_RATE_HISTORY);
+            }
+            Self::RATE_HISTORY.save(storage, key, &history)
+        }
+
+        pub fn get_rate_history(
+            storage: &dyn Storage,
+            key: &String,
+        ) -> StdResult<RateHistoryResponse> {
+            CommonConfig::require_supported_key(storage, key)?;
+            Ok(Self::RATE_HISTORY
+                .may_load(storage, key)?
+                .unwrap_or_default())
+        }
+
         pub fn get_supported_derivatives(storage: &dyn Storage) -> StdResult<DerivativesResponse> {
             let keys = CommonConfig::SUPPORTED_KEYS.load(storage)?;
             let mut supported_pairs = vec![];
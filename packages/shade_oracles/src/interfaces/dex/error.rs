@@ -0,0 +1,10 @@
+use cosmwasm_std::{Decimal256, Uint256};
+
+use crate::build_oracle_error;
+
+build_oracle_error! {LiquidityPairOracle {
+    #[error("Pair of {key} has {liquidity} of liquidity which is below the minimum of {min_liquidity}.")]
+    InsufficientLiquidity { key: String, liquidity: Uint256, min_liquidity: Uint256 },
+    #[error("Simulated trade on the pair of {key} has a price impact of {price_impact} which exceeds the max of {max_price_impact}.")]
+    ExcessivePriceImpact { key: String, price_impact: Decimal256, max_price_impact: Decimal256 },
+}}
@@ -1,9 +1,9 @@
 //! Pair oracles use a liquidity pair to determine the price of a token in that pair.
 //!
 use crate::asset::{Asset, RawAsset};
-use crate::interfaces::common::{PriceResponse, PricesResponse};
+use crate::interfaces::common::{DependenciesResponse, PriceResponse, PricesResponse};
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::Addr;
+use cosmwasm_std::{Addr, Decimal256, Uint128, Uint256};
 use secret_storage_plus::{Item, ItemStorage, Map};
 use shade_protocol::{utils::asset::RawContract, Contract};
 
@@ -39,6 +39,48 @@ pub mod msg {
         pub base_token: RawAsset,
         pub target_token: RawAsset,
         pub pair: RawContract,
+        /// How spot oracles price the LP token, defaults to spot pricing.
+        pub pricing_mode: Option<LpPricingMode>,
+        /// Liquidity, price impact and reference price thresholds enforced by market oracles.
+        pub market_guard: Option<MarketGuard>,
+    }
+
+    /// How spot oracles price the LP token of a pair.
+    #[cw_serde]
+    #[derive(Copy, Default)]
+    pub enum LpPricingMode {
+        /// Value of the reserves divided by the LP token supply.
+        #[default]
+        Spot,
+        /// Priced based on https://blog.alphafinance.io/fair-lp-token-pricing/,
+        /// so imbalancing the reserves can't inflate the price.
+        Fair,
+    }
+
+    /// Keeps market oracles from trusting the simulated trade of a thin pool.
+    #[cw_serde]
+    #[derive(Default)]
+    pub struct MarketGuard {
+        /// Minimum value of the reserves on each side of the pool, in the router's quote (i.e. USD * 10^18).
+        pub min_liquidity: Option<Uint256>,
+        /// Maximum price impact of the simulated trade relative to the spot rate of the pool.
+        pub max_price_impact: Option<Decimal256>,
+        /// Amount of target tokens the price impact check simulates selling, defaults to 1 token.
+        /// Prices are always derived from selling 1 target token.
+        pub trade_size: Option<Uint128>,
+        /// Router price the derived price is compared against.
+        pub reference: Option<ReferenceCheck>,
+    }
+
+    /// Compares the price derived from the pool with a reference router price (i.e. the Band feed of the target token).
+    #[cw_serde]
+    pub struct ReferenceCheck {
+        /// Router key of the reference price.
+        pub key: String,
+        /// Maximum deviation of the derived price from the reference price.
+        pub max_deviation: Decimal256,
+        /// Whether prices deviating beyond the max deviation are rejected or only flagged by GetReferenceCheck.
+        pub reject: bool,
     }
 
     #[cw_serde]
@@ -52,16 +94,39 @@ pub mod msg {
         GetConfig {},
         #[returns(PairsResponse)]
         GetPairs {},
+        /// Compares the price of a market oracle key with its reference price.
+        #[returns(ReferenceCheckResponse)]
+        GetReferenceCheck { key: String },
+        /// Market oracle keys are priced with their base token and any reference key,
+        /// spot oracle keys with both of their tokens.
+        #[returns(DependenciesResponse)]
+        GetDependencies { keys: Vec<String> },
+        #[returns(PricesResponse)]
+        GetPricesAtDepth { keys: Vec<String>, depth: u8 },
     }
 
     pub type PairsResponse = Vec<PairData>;
 
+    #[cw_serde]
+    pub struct ReferenceCheckResponse {
+        pub key: String,
+        pub price: Uint256,
+        pub reference_key: String,
+        pub reference_price: Uint256,
+        pub deviation: Decimal256,
+        pub max_deviation: Decimal256,
+        /// Whether the price deviates from the reference price by more than the max deviation.
+        pub flagged: bool,
+    }
+
     #[cw_serde]
     pub struct PairData {
         pub key: String,
         pub base_token: Asset,
         pub target_token: Asset,
         pub pair: Contract,
+        pub pricing_mode: LpPricingMode,
+        pub market_guard: Option<MarketGuard>,
     }
 }
 
@@ -78,12 +143,17 @@ mod state {
         interfaces::{
             common::{config::CommonConfig, OraclePrice},
             providers::ReferenceData,
+            router::registry::ProtectedKeyInfo,
         },
-        math::{FairLpPriceInfo, LiquidityPoolMath},
+        math::{FairLpPriceInfo, LiquidityPoolMath, TokenMath},
+        querier::query_price_at_depth,
     };
 
     use super::*;
-    use cosmwasm_std::{Api, QuerierWrapper, StdResult, Storage, Uint128, Uint256};
+    use crate::interfaces::dex::error::{LiquidityPairOracleError, LiquidityPairOracleResult};
+    use cosmwasm_std::{
+        Api, Decimal256, QuerierWrapper, StdError, StdResult, Storage, Uint128, Uint256,
+    };
     use shade_protocol::snip20::helpers::TokenInfo;
 
     #[cw_serde]
@@ -93,6 +163,10 @@ mod state {
         pub base_token: Addr,
         pub target_token: Addr,
         pub pair: Contract,
+        #[serde(default)]
+        pub pricing_mode: LpPricingMode,
+        #[serde(default)]
+        pub market_guard: Option<MarketGuard>,
     }
 
     #[cw_serde]
@@ -110,6 +184,204 @@ mod state {
         pub const PAIRS: Map<'static, &'a str, StoredPairData> = Map::new("pairs");
     }
 
+    impl MarketGuard {
+        pub fn validate(&self) -> StdResult<()> {
+            if self.trade_size == Some(Uint128::zero()) {
+                return Err(StdError::generic_err(
+                    "Market guard trade size must be greater than 0.",
+                ));
+            }
+            if self.max_price_impact > Some(Decimal256::one()) {
+                return Err(StdError::generic_err(
+                    "Market guard max price impact cannot be greater than 100%.",
+                ));
+            }
+            if let Some(reference) = &self.reference {
+                if reference.key.is_empty() {
+                    return Err(StdError::generic_err(
+                        "Market guard reference key cannot be empty.",
+                    ));
+                }
+            }
+            Ok(())
+        }
+
+        /// Checks the pool is deep enough and the simulated trade didn't move its price too much.
+        ///
+        /// Both rates are the amount of base tokens per target token normalized to 18 decimals.
+        pub fn check(
+            &self,
+            key: &str,
+            liquidity: Uint256,
+            spot_rate: Uint256,
+            rate: Uint256,
+        ) -> LiquidityPairOracleResult<()> {
+            if let Some(min_liquidity) = self.min_liquidity {
+                if liquidity < min_liquidity {
+                    return Err(LiquidityPairOracleError::InsufficientLiquidity {
+                        key: key.to_string(),
+                        liquidity,
+                        min_liquidity,
+                    });
+                }
+            }
+            if let Some(max_price_impact) = self.max_price_impact {
+                let price_impact = if rate >= spot_rate {
+                    Decimal256::zero()
+                } else {
+                    Decimal256::from_ratio(spot_rate - rate, spot_rate)
+                };
+                if price_impact > max_price_impact {
+                    return Err(LiquidityPairOracleError::ExcessivePriceImpact {
+                        key: key.to_string(),
+                        price_impact,
+                        max_price_impact,
+                    });
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl ReferenceCheck {
+        fn query_reference(
+            &self,
+            querier: &QuerierWrapper,
+            router: &Contract,
+            depth: u8,
+        ) -> StdResult<ProtectedKeyInfo> {
+            let reference = query_price_at_depth(router, querier, self.key.as_str(), depth)?;
+            Ok(ProtectedKeyInfo::new(
+                self.key.clone(),
+                self.max_deviation,
+                reference.data.rate,
+            ))
+        }
+
+        /// Compares the price with the router price of the reference key.
+        pub fn check(
+            &self,
+            querier: &QuerierWrapper,
+            router: &Contract,
+            price: &OraclePrice,
+        ) -> StdResult<ReferenceCheckResponse> {
+            let info = self.query_reference(querier, router, 0)?;
+            let deviation = info.deviation_of(price.data.rate);
+            Ok(ReferenceCheckResponse {
+                key: price.key.clone(),
+                price: price.data.rate,
+                reference_key: info.key,
+                reference_price: info.price,
+                deviation,
+                max_deviation: self.max_deviation,
+                flagged: deviation > self.max_deviation,
+            })
+        }
+
+        /// Errors if the check rejects prices and the price deviates too far from the reference price.
+        pub fn enforce(
+            &self,
+            querier: &QuerierWrapper,
+            router: &Contract,
+            price: &OraclePrice,
+            depth: u8,
+        ) -> StdResult<()> {
+            if !self.reject {
+                return Ok(());
+            }
+            self.query_reference(querier, router, depth)?
+                .check_deviation(price.data.rate)
+        }
+    }
+
+    impl PairData {
+        pub fn reference_check(&self) -> Option<&ReferenceCheck> {
+            self.market_guard
+                .as_ref()
+                .and_then(|guard| guard.reference.as_ref())
+        }
+
+        /// Router keys queried to price the key.
+        pub fn dependencies(&self, is_market: bool) -> Vec<String> {
+            let mut dependencies = vec![self.base_token.quote_symbol.clone()];
+            if is_market {
+                if let Some(reference) = self.reference_check() {
+                    dependencies.push(reference.key.clone());
+                }
+            } else {
+                dependencies.push(self.target_token.quote_symbol.clone());
+            }
+            dependencies
+        }
+
+        /// Amount of target tokens simulated to be sold by market oracles to price the target token.
+        pub fn one_target_token(&self) -> Uint128 {
+            Uint128::from(10u128.pow(self.target_token.decimals.into()))
+        }
+
+        /// Amount of target tokens simulated to be sold by the price impact check of the market guard.
+        pub fn trade_size(&self) -> Uint128 {
+            self.market_guard
+                .as_ref()
+                .and_then(|guard| guard.trade_size)
+                .unwrap_or_else(|| self.one_target_token())
+        }
+
+        /// Amount of base tokens received per target token sold normalized to 18 decimals.
+        pub fn exchange_rate(
+            &self,
+            base_amount: Uint128,
+            target_amount: Uint128,
+        ) -> StdResult<Uint256> {
+            let base_amount: Uint256 =
+                TokenMath::normalize_value(base_amount, self.base_token.decimals)?.into();
+            let target_amount: Uint256 =
+                TokenMath::normalize_value(target_amount, self.target_token.decimals)?.into();
+            if target_amount.is_zero() {
+                return Err(StdError::generic_err(format!(
+                    "Pair of {} has no {} reserves.",
+                    self.key, self.target_token.quote_symbol
+                )));
+            }
+            Ok(base_amount.multiply_ratio(10u128.pow(18), target_amount))
+        }
+
+        /// Enforces the market guard of the pair, if any, on the rate of its simulated trade.
+        ///
+        /// The reserves are the base and target token reserves of the pool. The spot rate defaults to their ratio.
+        /// The rate is the one the target token is priced at, which values the target reserves, while the trade rate
+        /// is the rate of the trade simulated by the price impact check.
+        pub fn check_market_guard(
+            &self,
+            base_price: &OraclePrice,
+            reserves: (Uint128, Uint128),
+            spot_rate: Option<Uint256>,
+            rate: Uint256,
+            trade_rate: Uint256,
+        ) -> StdResult<()> {
+            let market_guard = match &self.market_guard {
+                Some(market_guard) => market_guard,
+                None => return Ok(()),
+            };
+            let (base_reserves, target_reserves) = reserves;
+            let base_value: Uint256 =
+                TokenMath::normalize_value(base_reserves, self.base_token.decimals)?.into();
+            let base_value = base_value.multiply_ratio(base_price.data().rate, 10u128.pow(18));
+            let target_price = base_price.data().rate.multiply_ratio(rate, 10u128.pow(18));
+            let target_value: Uint256 =
+                TokenMath::normalize_value(target_reserves, self.target_token.decimals)?.into();
+            let target_value = target_value.multiply_ratio(target_price, 10u128.pow(18));
+            let spot_rate = match spot_rate {
+                Some(spot_rate) => spot_rate,
+                None => self.exchange_rate(base_reserves, target_reserves)?,
+            };
+            let liquidity = min(base_value, target_value);
+            market_guard
+                .check(&self.key, liquidity, spot_rate, trade_rate)
+                .map_err(|e| e.into())
+        }
+    }
+
     impl GenericLiquidityPairOracle {
         pub fn remove_keys(storage: &mut dyn Storage, keys: Vec<String>) -> StdResult<()> {
             let mut supported_keys = CommonConfig::SUPPORTED_KEYS.load(storage)?;
@@ -128,6 +400,8 @@ mod state {
             base_token: Asset,
             target_token: Asset,
             pair: Contract,
+            pricing_mode: LpPricingMode,
+            market_guard: Option<MarketGuard>,
         ) -> StdResult<StoredPairData> {
             Self::ASSETS.may_set(storage, &base_token)?;
             Self::ASSETS.may_set(storage, &target_token)?;
@@ -135,6 +409,8 @@ mod state {
                 base_token: base_token.contract.address,
                 target_token: target_token.contract.address,
                 pair,
+                pricing_mode,
+                market_guard,
             };
             Self::PAIRS.save(storage, &key, &data)?;
             CommonConfig::add_supported_key(storage, &key)?;
@@ -167,6 +443,20 @@ mod state {
             data: RawPairData,
             is_market: bool,
         ) -> StdResult<StoredPairData> {
+            let pricing_mode = data.pricing_mode.unwrap_or_default();
+            if is_market && pricing_mode != LpPricingMode::Spot {
+                return Err(StdError::generic_err(
+                    "LP pricing modes are only supported by spot oracles.",
+                ));
+            }
+            if let Some(market_guard) = &data.market_guard {
+                if !is_market {
+                    return Err(StdError::generic_err(
+                        "Market guards are only supported by market oracles.",
+                    ));
+                }
+                market_guard.validate()?;
+            }
             let pair = data.pair.into_valid(api)?;
             let base_token = data
                 .base_token
@@ -178,7 +468,15 @@ mod state {
                 data.target_token
                     .into_asset(&self.config.router, querier, api)
             }?;
-            Self::set_pair_data(storage, data.key, base_token, target_token, pair)
+            Self::set_pair_data(
+                storage,
+                data.key,
+                base_token,
+                target_token,
+                pair,
+                pricing_mode,
+                data.market_guard,
+            )
         }
 
         pub fn get_pair_data_resp(key: &String, storage: &dyn Storage) -> StdResult<PairData> {
@@ -190,9 +488,24 @@ mod state {
                 base_token,
                 target_token,
                 pair: data.pair,
+                pricing_mode: data.pricing_mode,
+                market_guard: data.market_guard,
             })
         }
 
+        pub fn get_dependencies(
+            storage: &dyn Storage,
+            keys: Vec<String>,
+            is_market: bool,
+        ) -> StdResult<DependenciesResponse> {
+            let mut dependencies = vec![];
+            for key in keys {
+                let data = Self::get_pair_data_resp(&key, storage)?;
+                dependencies.push((key, data.dependencies(is_market)));
+            }
+            Ok(dependencies)
+        }
+
         pub fn get_supported_pairs(storage: &dyn Storage) -> StdResult<Vec<PairData>> {
             let keys = CommonConfig::SUPPORTED_KEYS.load(storage)?;
             let mut supported_pairs = vec![];
@@ -215,6 +528,68 @@ mod state {
             let lp_token_decimals = lp_token_info.decimals;
             let price_0 = pair_prices[0].data();
             let price_1 = pair_prices[1].data();
+            let pricing_mode = data.pricing_mode;
+
+            let a = FairLpPriceInfo {
+                reserve: reserves_0.into(),
+                price: price_0.rate.into(),
+                decimals: data.base_token.decimals,
+            };
+
+            let b = FairLpPriceInfo {
+                reserve: reserves_1.into(),
+                price: price_1.rate.into(),
+                decimals: data.target_token.decimals,
+            };
+
+            let mut data = ReferenceData {
+                rate: Uint256::zero(),
+                last_updated_base: min(price_0.last_updated_base, price_1.last_updated_base),
+                last_updated_quote: min(price_0.last_updated_quote, price_1.last_updated_quote),
+            };
+
+            if a.reserve == 0 && b.reserve == 0 {
+                return Ok(data);
+            }
+
+            data.rate = match pricing_mode {
+                LpPricingMode::Spot => LiquidityPoolMath::get_lp_token_spot_price(
+                    a,
+                    b,
+                    total_supply.u128(),
+                    lp_token_decimals,
+                ),
+                LpPricingMode::Fair => LiquidityPoolMath::get_fair_lp_token_price(
+                    a,
+                    b,
+                    total_supply.u128(),
+                    lp_token_decimals,
+                ),
+            }?;
+
+            Ok(data)
+        }
+
+        /// Prices an LP token of a stableswap pair by valuing the pair's invariant,
+        /// so an imbalanced pool isn't priced as the plain sum of its reserves.
+        /// The pricing mode of the pair is ignored.
+        ///
+        /// ShadeSwap pairs scale the amplification with gamma1 or gamma2 depending on the side
+        /// of the imbalance, the larger of the two is expected as it gives the lower invariant.
+        pub fn calculate_lp_token_stableswap_rate(
+            data: msg::PairData,
+            lp_token_info: TokenInfo,
+            reserves_0: Uint128,
+            reserves_1: Uint128,
+            pair_prices: &[&OraclePrice; 2],
+            amp: Decimal256,
+            gamma: Uint256,
+        ) -> StdResult<ReferenceData> {
+            let gamma = u32::try_from(Uint128::try_from(gamma)?.u128())
+                .map_err(|_| StdError::generic_err("StableSwap gamma is too large."))?;
+            let total_supply = lp_token_info.total_supply.unwrap();
+            let price_0 = pair_prices[0].data();
+            let price_1 = pair_prices[1].data();
 
             let a = FairLpPriceInfo {
                 reserve: reserves_0.into(),
@@ -238,11 +613,13 @@ mod state {
                 return Ok(data);
             }
 
-            data.rate = LiquidityPoolMath::get_lp_token_spot_price(
+            data.rate = LiquidityPoolMath::get_stableswap_lp_token_price(
                 a,
                 b,
+                amp,
+                gamma,
                 total_supply.u128(),
-                lp_token_decimals,
+                lp_token_info.decimals,
             )?;
 
             Ok(data)
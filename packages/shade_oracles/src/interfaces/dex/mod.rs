@@ -1,2 +1,3 @@
 //! Oracle logic related to decentralized exchanges.
+pub mod error;
 pub mod generic;
@@ -19,6 +19,12 @@ build_oracle_error! {IndexOracle {
     RollbackNotFrozen,
     #[error("Prices are still stale so oracle cannot be rolled back. (Oldest price is {oldest_price}.)")]
     RollbackStale { oldest_price: u64 },
+    #[error("Auto unfreeze needs at least 1 computation.")]
+    InvalidAutoUnfreeze,
+    #[error("Ramp duration must be greater than 0, use UpdateTarget to change the target immediately.")]
+    InvalidRampDuration,
+    #[error("Max stale weight must be less than 100%. Currently {weight}.")]
+    InvalidMaxStaleWeight { weight: Decimal256 },
     #[error("Peg {peg} has deviated too far from the last_peg value {last_value}. {deviation} > {threshold}.")]
     PegDeviation { peg: Decimal256, last_value: Decimal256, deviation: Decimal256, threshold: Decimal256 },
 }}
@@ -15,6 +15,110 @@ pub struct IndexOracleConfig {
     /// The time difference between now and when the price feeds were last updated where we consider the price feeds to have gone stale.
     pub when_stale: u64,
     pub deviation_threshold: Decimal256,
+    /// Unfreezes the peg on `ComputeIndex` once the price feeds have recovered, if set.
+    pub auto_unfreeze: Option<AutoUnfreeze>,
+    /// Tolerates some stale assets in the basket instead of freezing the peg, if set.
+    pub stale_components: Option<StaleComponents>,
+}
+
+/// Policy for computing the peg while some of the basket assets have stale prices.
+#[cw_serde]
+pub struct StaleComponents {
+    pub mode: StaleMode,
+    /// Maximum share of the basket (by initial weight) that can be stale before the peg freezes.
+    pub max_stale_weight: Decimal256,
+}
+
+#[cw_serde]
+pub enum StaleMode {
+    /// Stale assets keep contributing at their last known price.
+    LastPrice,
+    /// Stale assets are left out and the rest of the basket is scaled up to make up for their weight.
+    Exclude,
+}
+
+/// Health of the price of a basket asset.
+#[cw_serde]
+pub enum ComponentHealth {
+    Fresh,
+    /// The price is older than `when_stale`.
+    Stale {
+        last_updated: u64,
+    },
+    /// The price couldn't be fetched from the router, so its last known price is used.
+    Unavailable,
+}
+
+/// Policy for unfreezing the peg without an admin.
+#[cw_serde]
+pub struct AutoUnfreeze {
+    /// Consecutive fresh computations needed to unfreeze the peg, each within the deviation threshold
+    /// of the first computation of the streak (`Recovery::first_value`).
+    pub computations: u32,
+    /// Minimum time the peg stays frozen (in seconds).
+    pub cooldown: u64,
+}
+
+/// Progress of a frozen peg towards being unfrozen automatically.
+#[cw_serde]
+#[derive(Default)]
+pub struct Recovery {
+    /// When the peg was frozen (in seconds).
+    pub frozen_at: u64,
+    /// Consecutive fresh computations within the deviation threshold of the first of them.
+    pub computations: u32,
+    /// Value of the first of those computations.
+    pub first_value: Uint256,
+    /// When the last of those computations was made (in seconds).
+    pub last_computed: u64,
+}
+
+/// Linear change of the target over time.
+#[cw_serde]
+pub struct Ramp {
+    /// Target when the ramp started.
+    pub from: Uint256,
+    /// Target when the ramp ends.
+    pub to: Uint256,
+    /// When the ramp started (in seconds).
+    pub start: u64,
+    /// How long the ramp lasts (in seconds).
+    pub duration: u64,
+}
+
+impl Ramp {
+    fn elapsed(&self, now: u64) -> u64 {
+        now.saturating_sub(self.start).min(self.duration)
+    }
+
+    /// Share of the ramp that has elapsed.
+    pub fn progress(&self, now: u64) -> Decimal256 {
+        Decimal256::from_ratio(self.elapsed(now), self.duration)
+    }
+
+    pub fn target_at(&self, now: u64) -> Uint256 {
+        let elapsed = self.elapsed(now);
+        if self.to >= self.from {
+            self.from + (self.to - self.from).multiply_ratio(elapsed, self.duration)
+        } else {
+            self.from - (self.from - self.to).multiply_ratio(elapsed, self.duration)
+        }
+    }
+
+    pub fn is_finished(&self, now: u64) -> bool {
+        self.elapsed(now) == self.duration
+    }
+
+    /// Restarts the ramp from the target it was held at while paused, with the time it had left when it was paused.
+    pub fn resume(&mut self, held: Uint256, paused_at: u64, now: u64) {
+        let remaining = self.duration - self.elapsed(paused_at);
+        if remaining == 0 {
+            return;
+        }
+        self.from = held;
+        self.start = now;
+        self.duration = remaining;
+    }
 }
 
 /// Symbol of an index asset
@@ -30,6 +134,18 @@ make_btr! {
     }
 }
 
+/// Why the peg was frozen.
+#[cw_serde]
+#[derive(Copy)]
+pub enum FreezeReason {
+    /// The price feeds were older than `when_stale`.
+    StaleFeeds,
+    /// The computed value deviated from the last value by more than the deviation threshold.
+    Deviation,
+    /// Frozen by an admin.
+    Manual,
+}
+
 make_btr! {
     /// The peg of the basket token
     Peg {
@@ -37,7 +153,9 @@ make_btr! {
         value: Uint256, U256, "Peg price of the index asset";
         last_value: Uint256, U256, "Last peg price of the index asset";
         frozen: bool, bool, "Whether or not this value is frozen";
-        last_updated: Uint64, u64, "When this value was last updated (in seconds)"
+        last_updated: Uint64, u64, "When this value was last updated (in seconds)";
+        feeds_updated: Uint64, u64, "Oldest update time of the price feeds this value was computed from (in seconds)";
+        frozen_reason: Option<FreezeReason>, Option<FreezeReason>, "Why this value is frozen, if it is"
     }
 }
 
@@ -69,6 +187,34 @@ mod state {
         const ITEM: Item<'static, Self> = Item::new("indexconfig");
     }
 
+    impl ItemStorage for Recovery {
+        const ITEM: Item<'static, Self> = Item::new("indexrecovery");
+    }
+
+    impl ItemStorage for Ramp {
+        const ITEM: Item<'static, Self> = Item::new("indexramp");
+    }
+
+    impl StaleComponents {
+        pub fn validate(&self) -> IndexOracleResult<()> {
+            if self.max_stale_weight >= Decimal256::percent(100) {
+                return Err(IndexOracleError::InvalidMaxStaleWeight {
+                    weight: self.max_stale_weight,
+                });
+            }
+            Ok(())
+        }
+    }
+
+    impl AutoUnfreeze {
+        pub fn validate(&self) -> IndexOracleResult<()> {
+            if self.computations == 0 {
+                return Err(IndexOracleError::InvalidAutoUnfreeze);
+            }
+            Ok(())
+        }
+    }
+
     impl ItemStorage<Bincode2> for BtrPeg {
         const ITEM: Item<'static, Self, Bincode2> = Item::new("indextarget");
     }
@@ -88,25 +234,42 @@ mod state {
         pub asset_symbols: Vec<String>,
         pub basket: BtrBasket,
         pub peg: BtrPeg,
+        pub recovery: Recovery,
+        pub ramp: Option<Ramp>,
+        /// Last price fetched for each asset, used in place of the prices the router can't return.
+        pub last_prices: HashMap<AssetSymbol, OraclePrice>,
     }
 
     impl_global_status!(IndexOracle, IndexOracleError);
 
+    impl<'a> IndexOracle {
+        pub const LAST_PRICES: Map<'static, &'a str, OraclePrice> = Map::new("indexlastprices");
+    }
+
     impl IndexOracle {
         pub fn load(storage: &dyn Storage) -> StdResult<Self> {
             let config = IndexOracleConfig::load(storage)?;
             let asset_symbols = AssetSymbols::load(storage)?;
             let mut basket = HashMap::new();
+            let mut last_prices = HashMap::new();
             for symbol in asset_symbols.as_slice() {
                 let item = BtrAssetWeights::load(storage, symbol.as_str())?;
                 basket.insert(symbol.to_string(), item);
+                if let Some(price) = Self::LAST_PRICES.may_load(storage, symbol.as_str())? {
+                    last_prices.insert(symbol.to_string(), price);
+                }
             }
             let peg = BtrPeg::load(storage)?;
+            let recovery = Recovery::ITEM.may_load(storage)?.unwrap_or_default();
+            let ramp = Ramp::ITEM.may_load(storage)?;
             Ok(Self {
                 config,
                 asset_symbols,
                 basket,
                 peg,
+                recovery,
+                ramp,
+                last_prices,
             })
         }
         pub fn init(
@@ -156,17 +319,31 @@ mod state {
                 return Err(IndexOracleError::InvalidBasketWeights { weight: weight_sum }.into());
             }
 
-            let peg = BtrPeg::new(target.into(), target.into(), target.into(), false, time.seconds());
+            let now = time.seconds();
+            let peg = BtrPeg::new(
+                target.into(),
+                target.into(),
+                target.into(),
+                false,
+                now,
+                now,
+                None,
+            );
             Ok(Self {
                 config: IndexOracleConfig {
                     symbol: index_symbol,
                     router,
                     when_stale: when_stale.into(),
                     deviation_threshold,
+                    auto_unfreeze: None,
+                    stale_components: None,
                 },
                 asset_symbols,
                 peg,
                 basket,
+                recovery: Recovery::default(),
+                ramp: None,
+                last_prices: HashMap::new(),
             })
         }
 
@@ -245,6 +422,7 @@ mod state {
         }
 
         pub fn compute_fixed_weights(&mut self, prices: &[OraclePrice]) -> StdResult<()> {
+            self.record_prices(prices);
             for price in prices {
                 let asset_symbol = price.key();
                 let weight = &self.basket[asset_symbol];
@@ -298,33 +476,190 @@ mod state {
             }
 
             self.compute_fixed_weights(prices)?;
+            // The ramp was held while the peg was frozen, so it carries on from where it stopped.
+            if let Some(ramp) = &mut self.ramp {
+                ramp.resume(self.peg.target.into(), self.recovery.frozen_at, now);
+            }
             self.peg.frozen = false;
+            self.peg.frozen_reason = None;
             self.peg.last_updated = now;
+            self.peg.feeds_updated = last_updated_feeds;
+            self.recovery = Recovery::default();
+            Ok(())
+        }
+
+        pub fn freeze(&mut self, reason: FreezeReason, now: u64) {
+            self.peg.frozen = true;
+            self.peg.frozen_reason = Some(reason);
+            self.recovery = Recovery {
+                frozen_at: now,
+                ..Default::default()
+            };
+        }
+
+        /// Counts the computation towards unfreezing the peg, rolling it back once the auto unfreeze policy is met.
+        /// Returns whether the peg was unfrozen.
+        ///
+        /// Missing or stale prices restart the count, as does a value deviating from the first one counted by more than the deviation threshold.
+        /// Computations made at the same time count once and manually frozen pegs are never unfrozen.
+        pub fn try_auto_unfreeze(
+            &mut self,
+            prices: Option<&Vec<OraclePrice>>,
+            time: &Timestamp,
+        ) -> IndexOracleResult<bool> {
+            let policy = match &self.config.auto_unfreeze {
+                Some(policy)
+                    if self.peg.frozen && self.peg.frozen_reason != Some(FreezeReason::Manual) =>
+                {
+                    policy.clone()
+                }
+                _ => return Ok(false),
+            };
+            let now = time.seconds();
+            let prices = match prices {
+                Some(prices) if self.has_all_prices(prices) => prices,
+                _ => {
+                    self.recovery.computations = 0;
+                    return Ok(false);
+                }
+            };
+            if self.recovery.computations > 0 && now <= self.recovery.last_computed {
+                return Ok(false);
+            }
+            let (value, last_updated_feeds) = self._compute_target(prices, now)?;
+            if now - last_updated_feeds > self.config.when_stale {
+                self.recovery.computations = 0;
+                return Ok(false);
+            }
+            if self.recovery.computations > 0
+                && Self::deviation(self.recovery.first_value.into(), value)
+                    > self.config.deviation_threshold
+            {
+                self.recovery.computations = 0;
+            }
+            if self.recovery.computations == 0 {
+                self.recovery.first_value = value.into();
+            }
+            self.recovery.computations += 1;
+            self.recovery.last_computed = now;
+
+            if self.recovery.computations < policy.computations
+                || now - self.recovery.frozen_at < policy.cooldown
+            {
+                return Ok(false);
+            }
+            self.rollback(prices, time)?;
+            Ok(true)
+        }
+
+        fn deviation(last_value: U256, value: U256) -> Decimal256 {
+            Decimal256::from_ratio(abs_diff(last_value, value), last_value)
+        }
+
+        /// Price of the index asset, timestamped with when the peg was last computed
+        /// and the oldest price feed it was computed from.
+        pub fn price(&self) -> OraclePrice {
+            OraclePrice::new(
+                self.config.symbol.clone(),
+                ReferenceData::new(
+                    self.peg.value.into(),
+                    self.peg.last_updated,
+                    self.peg.feeds_updated,
+                ),
+            )
+        }
+
+        /// Oldest update time of the prices, or now if there are none.
+        pub fn oldest_update<'a>(
+            prices: impl IntoIterator<Item = &'a OraclePrice>,
+            now: u64,
+        ) -> u64 {
+            prices.into_iter().fold(now, |oldest, price| {
+                min(
+                    oldest,
+                    min(price.data.last_updated_base, price.data.last_updated_quote),
+                )
+            })
+        }
+
+        fn is_stale(&self, price: &OraclePrice, now: u64) -> bool {
+            now.saturating_sub(Self::oldest_update([price], now)) > self.config.when_stale
+        }
+
+        fn has_all_prices(&self, prices: &[OraclePrice]) -> bool {
+            self.asset_symbols
+                .iter()
+                .all(|symbol| prices.iter().any(|price| price.key() == symbol))
+        }
+
+        /// Keeps the prices of the basket assets as their last known prices.
+        pub fn record_prices(&mut self, prices: &[OraclePrice]) {
+            for price in prices {
+                if self.basket.contains_key(price.key()) {
+                    self.last_prices.insert(price.key.clone(), price.clone());
+                }
+            }
+        }
+
+        /// Health of the price of the asset, given the prices fetched from the router.
+        /// Assets missing from the prices couldn't be fetched.
+        pub fn health(
+            &self,
+            symbol: &str,
+            prices: Option<&Vec<OraclePrice>>,
+            now: u64,
+        ) -> ComponentHealth {
+            match prices.and_then(|prices| prices.iter().find(|price| price.key() == symbol)) {
+                Some(price) if self.is_stale(price, now) => ComponentHealth::Stale {
+                    last_updated: Self::oldest_update([price], now),
+                },
+                Some(_) => ComponentHealth::Fresh,
+                None => ComponentHealth::Unavailable,
+            }
+        }
+
+        /// Moves the target along the ramp, scaling the fixed weights and last value with it.
+        /// A frozen peg holds its target until it is unfrozen, after which the ramp resumes from the held target.
+        pub fn apply_ramp(&mut self, now: u64) -> StdResult<()> {
+            let ramp = match &self.ramp {
+                Some(ramp) if !self.peg.frozen => ramp.clone(),
+                _ => return Ok(()),
+            };
+            let target: U256 = ramp.target_at(now).into();
+            let current = self.peg.target;
+            if target != current {
+                for weight in self.basket.values_mut() {
+                    weight.fixed = muldiv(weight.fixed, target, current)?;
+                }
+                self.peg.last_value = muldiv(self.peg.last_value, target, current)?;
+                self.peg.target = target;
+            }
+            if ramp.is_finished(now) {
+                self.ramp = None;
+            }
             Ok(())
         }
 
+        /// Computes the peg from the prices, freezing it if they are stale or deviate too much from the last value.
+        ///
+        /// The price returned keeps the timestamps of the last computed value, so a frozen peg goes stale.
         pub fn compute_peg(
             &mut self,
             prices: Option<&Vec<OraclePrice>>,
             time: &Timestamp,
         ) -> StdResult<OraclePrice> {
             let now = time.seconds();
-            let symbol = &self.config.symbol;
-            let mut resp = OraclePrice::new(
-                symbol.clone(),
-                ReferenceData {
-                    rate: self.peg.value.into(),
-                    last_updated_base: now,
-                    last_updated_quote: now,
-                },
-            );
+            self.apply_ramp(now)?;
+            if let Some(prices) = prices {
+                self.record_prices(prices);
+            }
 
             if self.peg.frozen || prices.is_none() {
                 // If peg is frozen or we aren't getting price feeds from provider, we use the last calculated value of the peg as the peg price.
                 if !self.peg.frozen && now - self.peg.last_updated > self.config.when_stale {
-                    self.peg.frozen = true;
+                    self.freeze(FreezeReason::StaleFeeds, now);
                 }
-                return Ok(resp);
+                return Ok(self.price());
             }
 
             // safe to unwrap cuz of above
@@ -332,22 +667,20 @@ mod state {
             let (new_target, last_updated_feeds) = self._compute_target(prices, now)?;
             // If the price feeds have gone stale, freeze the target peg and use its last calculated value.
             if now - last_updated_feeds > self.config.when_stale {
-                self.peg.frozen = true;
-                return Ok(resp);
+                self.freeze(FreezeReason::StaleFeeds, now);
+                return Ok(self.price());
             }
-            let diff = abs_diff(self.peg.last_value, new_target);
-            let expected: U256 = self.peg.last_value.into();
-            let deviation = Decimal256::from_ratio(diff, expected);
-            
+            let deviation = Self::deviation(self.peg.last_value, new_target);
+
             if deviation > self.config.deviation_threshold {
-                self.peg.frozen = true;
-                return Ok(resp);
+                self.freeze(FreezeReason::Deviation, now);
+                return Ok(self.price());
             }
-                self.peg.last_updated = now;
-                self.peg.last_value = new_target;
-                self.peg.value = new_target;
-                resp.data.rate = new_target.into();
-                Ok(resp)
+            self.peg.last_updated = now;
+            self.peg.feeds_updated = last_updated_feeds;
+            self.peg.last_value = new_target;
+            self.peg.value = new_target;
+            Ok(self.price())
         }
 
         pub fn save(&self, storage: &mut dyn Storage) -> IndexOracleResult<()> {
@@ -355,25 +688,72 @@ mod state {
             self.config.save(storage)?;
             AssetSymbols::save(storage, asset_symbols)?;
             self.peg.save(storage)?;
+            self.recovery.save(storage)?;
+            match &self.ramp {
+                Some(ramp) => ramp.save(storage)?,
+                None => Ramp::ITEM.remove(storage),
+            }
             for symbol in asset_symbols.as_slice() {
                 self.basket[symbol].save(storage, symbol)?;
+                if let Some(price) = self.last_prices.get(symbol) {
+                    Self::LAST_PRICES.save(storage, symbol, price)?;
+                }
             }
             Ok(())
         }
+        /// Computes the value of the basket and the oldest update time of the prices it was computed from.
+        ///
+        /// Assets missing from the prices are valued at their last known price and count as stale, as do stale prices.
+        /// Stale assets are handled by the stale components policy as long as their weight is within its max stale weight,
+        /// in which case only the fresh prices count towards the update time.
+        /// An asset without any known price can only be excluded, otherwise the value is as old as it can be.
         fn _compute_target(&self, prices: &[OraclePrice], now: u64) -> StdResult<(U256, u64)> {
             let mut new_target = U256::ZERO;
-            let mut last_updated_base = now;
-            let mut last_updated_quote = now;
-            for price in prices {
-                last_updated_base = min(last_updated_base, price.data.last_updated_base);
-                last_updated_quote = min(last_updated_quote, price.data.last_updated_quote);
-
-                let asset_symbol = price.key();
+            let mut fresh_target = U256::ZERO;
+            let mut stale_weight = U256::ZERO;
+            let mut fresh_prices = vec![];
+            let mut known_prices = vec![];
+            let mut unpriced = false;
+            for asset_symbol in &self.asset_symbols {
                 let weight = &self.basket[asset_symbol];
-                let price: U256 = price.data.rate.into();
-                new_target += muldiv18(weight.fixed, price)?;
+                let fetched = prices.iter().find(|price| price.key() == asset_symbol);
+                let price = match fetched.or_else(|| self.last_prices.get(asset_symbol)) {
+                    Some(price) => price,
+                    None => {
+                        stale_weight += weight.initial;
+                        unpriced = true;
+                        continue;
+                    }
+                };
+                let value = muldiv18(weight.fixed, price.data.rate.into())?;
+                new_target += value;
+                known_prices.push(price);
+                if fetched.is_some() && !self.is_stale(price, now) {
+                    fresh_target += value;
+                    fresh_prices.push(price);
+                } else {
+                    stale_weight += weight.initial;
+                }
             }
-            let last_updated_feeds = min(last_updated_base, last_updated_quote);
+            let policy = self.config.stale_components.as_ref().filter(|policy| {
+                let max_stale_weight: U256 = policy.max_stale_weight.into();
+                stale_weight > U256::ZERO
+                    && stale_weight <= max_stale_weight
+                    && (!unpriced || policy.mode == StaleMode::Exclude)
+            });
+            let (new_target, last_updated_feeds) = match policy {
+                Some(policy) => {
+                    let new_target = match policy.mode {
+                        StaleMode::LastPrice => new_target,
+                        StaleMode::Exclude => {
+                            muldiv(fresh_target, exp10(18), exp10(18) - stale_weight)?
+                        }
+                    };
+                    (new_target, Self::oldest_update(fresh_prices, now))
+                }
+                None if unpriced => (new_target, 0),
+                None => (new_target, Self::oldest_update(known_prices, now)),
+            };
             // Smooth out peg calculation to 10e-9 precision
             Ok((bankers_round(new_target, 9), last_updated_feeds))
         }
@@ -487,8 +867,311 @@ mod state {
                 .unwrap();
 
             assert!(index_oracle.peg.frozen);
+            assert_eq!(
+                index_oracle.peg.frozen_reason,
+                Some(FreezeReason::StaleFeeds)
+            );
             assert_eq!(index_oracle.peg.last_updated, 0u64);
             MathAsserter::within_deviation(index_oracle.peg.value, target, exp10(16));
+
+            let price = index_oracle.price();
+            assert_eq!(price.data.last_updated_base, 0u64);
+            assert_eq!(price.data.last_updated_quote, 0u64);
+        }
+
+        #[test]
+        fn freeze_deviation() {
+            let target = U256::new(105u128) * exp10(16);
+            let timestamp = Timestamp::from_seconds(0);
+            let mut index_oracle = basic_index_init(target);
+            index_oracle.compute_fixed_weights(&feed_0()).unwrap();
+
+            index_oracle
+                .compute_peg(Some(&feed_2()), &timestamp)
+                .unwrap();
+
+            assert!(index_oracle.peg.frozen);
+            assert_eq!(
+                index_oracle.peg.frozen_reason,
+                Some(FreezeReason::Deviation)
+            );
+            MathAsserter::within_deviation(index_oracle.peg.value, target, exp10(16));
+        }
+
+        #[test]
+        fn auto_unfreeze() {
+            let target = U256::new(105u128) * exp10(16);
+            let mut index_oracle = basic_index_init(target);
+            index_oracle.compute_fixed_weights(&feed_0()).unwrap();
+            index_oracle.config.auto_unfreeze = Some(AutoUnfreeze {
+                computations: 2,
+                cooldown: 100,
+            });
+            let time = Timestamp::from_seconds;
+
+            index_oracle.compute_peg(Some(&feed_2()), &time(0)).unwrap();
+            assert!(index_oracle.peg.frozen);
+
+            assert!(!index_oracle
+                .try_auto_unfreeze(Some(&feed_2()), &time(10))
+                .unwrap());
+            // Computations made at the same time only count once.
+            assert!(!index_oracle
+                .try_auto_unfreeze(Some(&feed_2()), &time(10))
+                .unwrap());
+            assert_eq!(index_oracle.recovery.computations, 1);
+            // Deviating value restarts the count.
+            assert!(!index_oracle
+                .try_auto_unfreeze(Some(&feed_0()), &time(20))
+                .unwrap());
+            assert_eq!(index_oracle.recovery.computations, 1);
+            // Still cooling down.
+            assert!(!index_oracle
+                .try_auto_unfreeze(Some(&feed_0()), &time(50))
+                .unwrap());
+            assert_eq!(index_oracle.recovery.computations, 2);
+
+            assert!(index_oracle
+                .try_auto_unfreeze(Some(&feed_0()), &time(100))
+                .unwrap());
+            assert!(!index_oracle.peg.frozen);
+            assert_eq!(index_oracle.peg.frozen_reason, None);
+            index_oracle
+                .compute_peg(Some(&feed_0()), &time(100))
+                .unwrap();
+            assert!(!index_oracle.peg.frozen);
+            MathAsserter::within_deviation(index_oracle.peg.value, target, exp10(16));
+
+            // Manually frozen pegs stay frozen.
+            index_oracle.freeze(FreezeReason::Manual, 100);
+            for t in [200, 300, 400] {
+                assert!(!index_oracle
+                    .try_auto_unfreeze(Some(&feed_0()), &time(t))
+                    .unwrap());
+            }
+            assert!(index_oracle.peg.frozen);
+        }
+
+        #[test]
+        fn auto_unfreeze_drift() {
+            let target = U256::new(105u128) * exp10(16);
+            let mut index_oracle = basic_index_init(target);
+            index_oracle.compute_fixed_weights(&feed_0()).unwrap();
+            index_oracle.config.auto_unfreeze = Some(AutoUnfreeze {
+                computations: 3,
+                cooldown: 100,
+            });
+            let time = Timestamp::from_seconds;
+            let usd_feed = |usd: &str| {
+                generate_price_feed(vec![
+                    ("USD", usd, 0),
+                    ("EURO", "1.0196", 0),
+                    ("GDP", "1.208", 0),
+                    ("JPY", "0.0074", 0),
+                ])
+            };
+
+            index_oracle.compute_peg(Some(&feed_2()), &time(0)).unwrap();
+            assert!(index_oracle.peg.frozen);
+
+            // Each step moves the value by ~7%, but the last one is ~14% away from the first.
+            assert!(!index_oracle
+                .try_auto_unfreeze(Some(&usd_feed("1.00")), &time(10))
+                .unwrap());
+            assert!(!index_oracle
+                .try_auto_unfreeze(Some(&usd_feed("1.28")), &time(20))
+                .unwrap());
+            assert_eq!(index_oracle.recovery.computations, 2);
+            assert!(!index_oracle
+                .try_auto_unfreeze(Some(&usd_feed("1.56")), &time(200))
+                .unwrap());
+            assert!(index_oracle.peg.frozen);
+            assert_eq!(index_oracle.recovery.computations, 1);
+        }
+
+        #[test]
+        fn ramp_target() {
+            let target = U256::new(105u128) * exp10(16);
+            let new_target = U256::new(110u128) * exp10(16);
+            let mut index_oracle = basic_index_init(target);
+            index_oracle.compute_fixed_weights(&feed_0()).unwrap();
+            index_oracle.ramp = Some(Ramp {
+                from: target.into(),
+                to: new_target.into(),
+                start: 0,
+                duration: 100,
+            });
+            let time = Timestamp::from_seconds;
+
+            index_oracle
+                .compute_peg(Some(&feed_0()), &time(50))
+                .unwrap();
+            let halfway = U256::new(1075u128) * exp10(15);
+            assert_eq!(index_oracle.peg.target, halfway);
+            MathAsserter::within_deviation(index_oracle.peg.value, halfway, exp10(16));
+
+            // Frozen peg holds its target.
+            index_oracle.freeze(FreezeReason::Manual, 50);
+            index_oracle
+                .compute_peg(Some(&feed_0()), &time(75))
+                .unwrap();
+            assert_eq!(index_oracle.peg.target, halfway);
+            index_oracle.rollback(&feed_0(), &time(75)).unwrap();
+
+            // The ramp resumes from where it was held instead of jumping ahead.
+            index_oracle
+                .compute_peg(Some(&feed_0()), &time(75))
+                .unwrap();
+            assert_eq!(index_oracle.peg.target, halfway);
+            index_oracle
+                .compute_peg(Some(&feed_0()), &time(100))
+                .unwrap();
+            let three_quarters = U256::new(10875u128) * exp10(14);
+            assert_eq!(index_oracle.peg.target, three_quarters);
+            MathAsserter::within_deviation(index_oracle.peg.value, three_quarters, exp10(16));
+            assert!(index_oracle.ramp.is_some());
+
+            index_oracle
+                .compute_peg(Some(&feed_0()), &time(125))
+                .unwrap();
+            assert!(!index_oracle.peg.frozen);
+            assert!(index_oracle.ramp.is_none());
+            assert_eq!(index_oracle.peg.target, new_target);
+            MathAsserter::within_deviation(index_oracle.peg.value, new_target, exp10(16));
+        }
+
+        #[test]
+        fn stale_components() {
+            let target = U256::new(105u128) * exp10(16);
+            let now = SIX_HOURS + 10u64;
+            let timestamp = Timestamp::from_seconds(now);
+            // JPY hasn't been updated since the weights were fixed.
+            let feed = |jpy: &str| {
+                generate_price_feed(vec![
+                    ("USD", "1.00", now),
+                    ("EURO", "1.0196", now),
+                    ("GDP", "1.208", now),
+                    ("JPY", jpy, 0),
+                ])
+            };
+            let init = |mode, max_stale_weight| {
+                let mut index_oracle = basic_index_init(target);
+                index_oracle.compute_fixed_weights(&feed_0()).unwrap();
+                index_oracle.config.stale_components = Some(StaleComponents {
+                    mode,
+                    max_stale_weight,
+                });
+                index_oracle
+            };
+
+            let mut index_oracle = basic_index_init(target);
+            index_oracle.compute_fixed_weights(&feed_0()).unwrap();
+            index_oracle
+                .compute_peg(Some(&feed("0.0074")), &timestamp)
+                .unwrap();
+            assert!(index_oracle.peg.frozen);
+
+            let mut index_oracle = init(StaleMode::LastPrice, Decimal256::percent(25));
+            index_oracle
+                .compute_peg(Some(&feed("0.0074")), &timestamp)
+                .unwrap();
+            assert!(!index_oracle.peg.frozen);
+            assert_eq!(index_oracle.peg.feeds_updated, now);
+            MathAsserter::within_deviation(index_oracle.peg.value, target, exp10(16));
+            assert_eq!(
+                index_oracle.health("JPY", Some(&feed("0.0074")), now),
+                ComponentHealth::Stale { last_updated: 0 }
+            );
+            assert_eq!(
+                index_oracle.health("USD", Some(&feed("0.0074")), now),
+                ComponentHealth::Fresh
+            );
+            assert_eq!(
+                index_oracle.health("USD", None, now),
+                ComponentHealth::Unavailable
+            );
+
+            // Stale JPY price is left out of the basket.
+            let mut index_oracle = init(StaleMode::Exclude, Decimal256::percent(25));
+            index_oracle
+                .compute_peg(Some(&feed("0.0148")), &timestamp)
+                .unwrap();
+            assert!(!index_oracle.peg.frozen);
+            MathAsserter::within_deviation(index_oracle.peg.value, target, exp10(16));
+
+            let mut index_oracle = init(StaleMode::Exclude, Decimal256::percent(20));
+            index_oracle
+                .compute_peg(Some(&feed("0.0074")), &timestamp)
+                .unwrap();
+            assert!(index_oracle.peg.frozen);
+            assert_eq!(
+                index_oracle.peg.frozen_reason,
+                Some(FreezeReason::StaleFeeds)
+            );
+        }
+
+        #[test]
+        fn last_prices() {
+            let target = U256::new(105u128) * exp10(16);
+            let now = SIX_HOURS + 10u64;
+            let feed = |updated: u64| {
+                generate_price_feed(vec![
+                    ("USD", "1.00", updated),
+                    ("EURO", "1.0196", updated),
+                    ("GDP", "1.208", updated),
+                    ("JPY", "0.0074", updated),
+                ])
+            };
+            // The router couldn't return the JPY price.
+            let without_jpy = |updated: u64| {
+                let mut prices = feed(updated);
+                prices.retain(|price| price.key() != "JPY");
+                prices
+            };
+
+            // JPY keeps its last known price while it is fresh.
+            let mut index_oracle = basic_index_init(target);
+            index_oracle.compute_fixed_weights(&feed(now)).unwrap();
+            assert_eq!(index_oracle.last_prices.len(), 4);
+            index_oracle
+                .compute_peg(
+                    Some(&without_jpy(now + 10)),
+                    &Timestamp::from_seconds(now + 10),
+                )
+                .unwrap();
+            assert!(!index_oracle.peg.frozen);
+            assert_eq!(index_oracle.peg.feeds_updated, now);
+            MathAsserter::within_deviation(index_oracle.peg.value, target, exp10(16));
+            assert_eq!(
+                index_oracle.health("JPY", Some(&without_jpy(now + 10)), now + 10),
+                ComponentHealth::Unavailable
+            );
+            assert_eq!(
+                index_oracle.last_prices["USD"].data.last_updated_base,
+                now + 10
+            );
+
+            // Once its last known price is stale, JPY is handled by the stale components policy.
+            let mut index_oracle = basic_index_init(target);
+            index_oracle.compute_fixed_weights(&feed_0()).unwrap();
+            let timestamp = Timestamp::from_seconds(now);
+            index_oracle
+                .compute_peg(Some(&without_jpy(now)), &timestamp)
+                .unwrap();
+            assert!(index_oracle.peg.frozen);
+
+            let mut index_oracle = basic_index_init(target);
+            index_oracle.compute_fixed_weights(&feed_0()).unwrap();
+            index_oracle.config.stale_components = Some(StaleComponents {
+                mode: StaleMode::LastPrice,
+                max_stale_weight: Decimal256::percent(25),
+            });
+            index_oracle
+                .compute_peg(Some(&without_jpy(now)), &timestamp)
+                .unwrap();
+            assert!(!index_oracle.peg.frozen);
+            assert_eq!(index_oracle.peg.feeds_updated, now);
+            MathAsserter::within_deviation(index_oracle.peg.value, target, exp10(16));
         }
 
         #[test]
@@ -521,6 +1204,7 @@ mod state {
                 .unwrap();
 
             assert!(!index_oracle.peg.frozen);
+            assert_eq!(index_oracle.peg.frozen_reason, None);
             assert_eq!(index_oracle.peg.last_updated, 0u64);
             MathAsserter::within_deviation(index_oracle.peg.value, target, exp10(16));
         }
@@ -1,13 +1,15 @@
 use crate::{
     common::status::ContractStatus,
     impl_msg_callbacks,
-    interfaces::common::{PriceResponse, PricesResponse},
+    interfaces::common::{DependenciesResponse, PriceResponse, PricesResponse},
 };
 use cosmwasm_schema::{cw_serde, QueryResponses};
 use cosmwasm_std::{Decimal256, Uint256, Uint64};
 use shade_protocol::{utils::asset::RawContract, Contract};
 
-use super::{AssetSymbol, AssetWeights, Peg};
+use super::{
+    AssetSymbol, AssetWeights, AutoUnfreeze, ComponentHealth, Peg, Ramp, Recovery, StaleComponents,
+};
 
 impl_msg_callbacks!();
 
@@ -44,8 +46,24 @@ pub enum AdminMsg {
         when_stale: Option<Uint64>,
         deviation_threshold: Option<Decimal256>,
     },
+    /// Changes the target immediately, cancelling any ramp in progress.
     UpdateTarget(Uint256),
+    /// Moves the target linearly from its current value to the new target over the duration (in seconds).
+    /// Replaces any ramp in progress.
+    RampTarget {
+        new_target: Uint256,
+        duration: Uint64,
+    },
+    /// Stops the ramp in progress, keeping the target it has reached.
+    CancelRamp {},
     UnfreezePeg {},
+    /// Freezes the peg at its last computed value until it is unfrozen.
+    /// Manually frozen pegs are never unfrozen automatically.
+    FreezePeg {},
+    /// Sets or removes the policy for unfreezing the peg on `ComputeIndex`.
+    SetAutoUnfreeze(Option<AutoUnfreeze>),
+    /// Sets or removes the policy for stale assets in the basket.
+    SetStaleComponents(Option<StaleComponents>),
 }
 
 #[cw_serde]
@@ -59,6 +77,17 @@ pub enum QueryMsg {
     GetIndexData {},
     #[returns(BasketResponse)]
     GetBasket {},
+    /// Applies the basket changes in memory, the same way `AdminMsg::ModBasket` would.
+    #[returns(SimulationResponse)]
+    SimulateModBasket { basket: Vec<InitialBasketItem> },
+    /// Sets the target in memory, the same way `AdminMsg::UpdateTarget` would.
+    #[returns(SimulationResponse)]
+    SimulateUpdateTarget { target: Uint256 },
+    /// The index is priced with every asset of its basket.
+    #[returns(DependenciesResponse)]
+    GetDependencies { keys: Vec<String> },
+    #[returns(PricesResponse)]
+    GetPricesAtDepth { keys: Vec<String>, depth: u8 },
 }
 
 #[cw_serde]
@@ -68,15 +97,37 @@ pub struct IndexDataResponse {
     pub when_stale: Uint64,
     pub peg: Peg,
     pub basket: Vec<IndexAsset>,
+    pub auto_unfreeze: Option<AutoUnfreeze>,
+    pub recovery: Recovery,
+    pub stale_components: Option<StaleComponents>,
+    pub ramp: Option<RampProgress>,
+}
+
+#[cw_serde]
+pub struct RampProgress {
+    pub ramp: Ramp,
+    /// Share of the ramp that has elapsed.
+    pub progress: Decimal256,
 }
 
 #[cw_serde]
 pub struct IndexAsset {
     pub symbol: AssetSymbol,
     pub weight: AssetWeights,
+    pub health: ComponentHealth,
 }
 
 #[cw_serde]
 pub struct BasketResponse {
     pub basket: Vec<(String, Decimal256, Decimal256)>,
 }
+
+#[cw_serde]
+pub struct SimulationResponse {
+    /// Basket after the change, as (Symbol, Initial Weight, Fixed Weight).
+    pub basket: Vec<(String, Decimal256, Decimal256)>,
+    /// Peg value after the change.
+    pub peg: Option<Uint256>,
+    /// Why the change would fail, if it would.
+    pub error: Option<String>,
+}
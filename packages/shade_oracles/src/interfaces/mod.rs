@@ -4,5 +4,6 @@ pub mod dex;
 pub mod index;
 pub mod providers;
 pub mod router;
+pub mod twap;
 
 use super::*;
@@ -210,6 +210,120 @@ impl Provider {
             }
         }
     }
+    /// Queries the median reference data if median is set, otherwise the latest reference data.
+    pub fn query_reference_data(
+        &self,
+        querier: &QuerierWrapper,
+        symbol_pair: (&str, &str),
+        median: bool,
+    ) -> StdResult<ReferenceData> {
+        if median {
+            self.median_reference_data(querier, symbol_pair)
+        } else {
+            self.reference_data(querier, symbol_pair)
+        }
+    }
+    pub fn query_reference_data_bulk<I>(
+        &self,
+        querier: &QuerierWrapper,
+        symbols: I,
+        median: bool,
+    ) -> StdResult<Vec<ReferenceData>>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        if median {
+            self.median_reference_data_bulk(querier, symbols)
+        } else {
+            self.reference_data_bulk(querier, symbols)
+        }
+    }
+    /// Queries each provider in order until one of them answers with healthy data,
+    /// returning that provider alongside its data or the reason each provider failed.
+    pub fn reference_data_with_failover(
+        providers: &[Provider],
+        querier: &QuerierWrapper,
+        symbol_pair: (&str, &str),
+        median: bool,
+        is_healthy: impl Fn(&ReferenceData) -> bool,
+    ) -> Result<(Provider, ReferenceData), Vec<String>> {
+        let mut errors = vec![];
+        for provider in providers {
+            match provider.query_reference_data(querier, symbol_pair, median) {
+                Ok(data) if is_healthy(&data) => return Ok((provider.clone(), data)),
+                Ok(_) => errors.push(provider.error(STALE_DATA)),
+                Err(err) => errors.push(provider.error(err)),
+            }
+        }
+        Err(errors)
+    }
+    /// Bulk version of `reference_data_with_failover` where each symbol pair fails over independently.
+    ///
+    /// If a provider fails the bulk query, the pending symbols are queried one by one so a single
+    /// missing symbol doesn't force every other symbol onto the next provider.
+    pub fn reference_data_bulk_with_failover(
+        providers: &[Provider],
+        querier: &QuerierWrapper,
+        symbols: &[(String, String)],
+        median: bool,
+        is_healthy: impl Fn(usize, &ReferenceData) -> bool,
+    ) -> Vec<Result<(Provider, ReferenceData), Vec<String>>> {
+        let mut results: Vec<Result<(Provider, ReferenceData), Vec<String>>> =
+            vec![Err(vec![]); symbols.len()];
+        for provider in providers {
+            let pending: Vec<usize> = (0..symbols.len())
+                .filter(|index| results[*index].is_err())
+                .collect();
+            if pending.is_empty() {
+                break;
+            }
+            let pairs: Vec<(String, String)> = pending
+                .iter()
+                .map(|index| symbols[*index].clone())
+                .collect();
+            let data: Vec<StdResult<ReferenceData>> =
+                match provider.query_reference_data_bulk(querier, pairs.clone(), median) {
+                    Ok(data) if data.len() == pending.len() => data.into_iter().map(Ok).collect(),
+                    _ if pending.len() > 1 => pairs
+                        .iter()
+                        .map(|(base, quote)| {
+                            provider.query_reference_data(
+                                querier,
+                                (base.as_str(), quote.as_str()),
+                                median,
+                            )
+                        })
+                        .collect(),
+                    Ok(_) => vec![Err(StdError::generic_err(
+                        "Bulk query returned the wrong amount of data.",
+                    ))],
+                    Err(err) => vec![Err(err)],
+                };
+            for (index, data) in pending.into_iter().zip(data) {
+                let error = match data {
+                    Ok(data) if is_healthy(index, &data) => {
+                        results[index] = Ok((provider.clone(), data));
+                        continue;
+                    }
+                    Ok(_) => provider.error(STALE_DATA),
+                    Err(err) => provider.error(err),
+                };
+                if let Err(errors) = &mut results[index] {
+                    errors.push(error);
+                }
+            }
+        }
+        results
+    }
+    pub fn contract(&self) -> &Contract {
+        match self {
+            Provider::Band(c) | Provider::Ojo(c) => c,
+        }
+    }
+    /// Describes why the provider couldn't be used.
+    pub fn error(&self, reason: impl std::fmt::Display) -> String {
+        format!("{}: {reason}", self.contract().address)
+    }
     pub fn median_reference_data(
         &self,
         querier: &QuerierWrapper,
@@ -247,6 +361,9 @@ impl Provider {
     }
 }
 
+/// Reason given for a source whose data is older than the max age.
+pub const STALE_DATA: &str = "Reported data is stale.";
+
 #[cw_serde]
 pub enum ProviderError {
     Unimplemented,
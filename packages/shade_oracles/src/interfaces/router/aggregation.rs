@@ -0,0 +1,237 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Decimal256, StdError, StdResult, Uint256};
+
+use super::{error::OracleRouterError, registry::deviation};
+use crate::interfaces::{
+    common::{OraclePrice, PriceSource, SourcedPrice},
+    providers::ReferenceData,
+};
+
+/// How the router combines the data reported by the sources of a key.
+#[cw_serde]
+pub enum AggregationMethod {
+    /// Median of the rates reported by the healthy sources.
+    Median,
+    /// Mean of the rates within max deviation of the median, rejecting any outliers.
+    Mean { max_deviation: Decimal256 },
+    /// Data of the first healthy source, in the order the sources are configured.
+    FirstHealthy,
+}
+
+#[cw_serde]
+pub struct Aggregation {
+    pub method: AggregationMethod,
+    /// Minimum amount of sources that need to be used to aggregate a price.
+    pub min_sources: u32,
+}
+
+impl Default for Aggregation {
+    fn default() -> Self {
+        Aggregation {
+            method: AggregationMethod::FirstHealthy,
+            min_sources: 1,
+        }
+    }
+}
+
+impl Aggregation {
+    pub fn new(method: AggregationMethod, min_sources: u32) -> Self {
+        Aggregation {
+            method,
+            min_sources,
+        }
+    }
+
+    pub fn validate(&self) -> StdResult<()> {
+        if self.min_sources == 0 {
+            return Err(StdError::generic_err(
+                "Aggregation needs a minimum of at least 1 source.",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether only the first healthy source is needed, so the remaining sources don't have to be queried.
+    pub fn is_failover(&self) -> bool {
+        self.method == AggregationMethod::FirstHealthy && self.min_sources == 1
+    }
+
+    /// Aggregates the data reported by the healthy sources of the key (ordered by source priority).
+    ///
+    /// The timestamps of the result are the oldest of the data used to compute it.
+    /// The errors of the sources that couldn't be used are reported if there aren't enough sources left.
+    pub fn aggregate(
+        &self,
+        key: String,
+        mut data: Vec<(PriceSource, ReferenceData)>,
+        errors: Vec<String>,
+    ) -> StdResult<SourcedPrice> {
+        let used = match &self.method {
+            AggregationMethod::FirstHealthy => {
+                let available = data.len();
+                data.truncate(1);
+                self.require_quorum(&key, available, &errors)?;
+                data
+            }
+            AggregationMethod::Median => {
+                self.require_quorum(&key, data.len(), &errors)?;
+                data.sort_by_key(|(_, d)| d.rate);
+                Self::middle(data)
+            }
+            AggregationMethod::Mean { max_deviation } => {
+                let mut sorted = data.clone();
+                sorted.sort_by_key(|(_, d)| d.rate);
+                let median = Self::combine(key.clone(), Self::middle(sorted))
+                    .map(|price| price.price.data.rate)
+                    .unwrap_or_default();
+                data.retain(|(_, d)| deviation(d.rate, median) <= *max_deviation);
+                self.require_quorum(&key, data.len(), &errors)?;
+                data
+            }
+        };
+        Self::combine(key.clone(), used).ok_or_else(|| {
+            OracleRouterError::InsufficientSources {
+                key,
+                available: 0,
+                required: self.min_sources,
+                errors,
+            }
+            .into()
+        })
+    }
+
+    fn require_quorum(&self, key: &str, available: usize, errors: &[String]) -> StdResult<()> {
+        if (available as u32) < self.min_sources {
+            return Err(OracleRouterError::InsufficientSources {
+                key: key.to_string(),
+                available: available as u32,
+                required: self.min_sources,
+                errors: errors.to_vec(),
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    /// Middle element(s) of a sorted list.
+    fn middle<T>(mut sorted: Vec<T>) -> Vec<T> {
+        let len = sorted.len();
+        if len <= 2 {
+            return sorted;
+        }
+        let start = if len % 2 == 0 { len / 2 - 1 } else { len / 2 };
+        sorted.drain(start..len / 2 + 1).collect()
+    }
+
+    /// Averages the rates, keeping the oldest timestamps and reporting every source used.
+    fn combine(key: String, data: Vec<(PriceSource, ReferenceData)>) -> Option<SourcedPrice> {
+        let (_, first) = data.first()?;
+        let mut combined = first.clone();
+        let mut total = Uint256::zero();
+        for (_, d) in &data {
+            total += d.rate;
+            combined.last_updated_base = combined.last_updated_base.min(d.last_updated_base);
+            combined.last_updated_quote = combined.last_updated_quote.min(d.last_updated_quote);
+        }
+        combined.rate = total / Uint256::from(data.len() as u128);
+        let mut sources: Vec<PriceSource> = data.into_iter().map(|(source, _)| source).collect();
+        let source = if sources.len() == 1 {
+            sources.remove(0)
+        } else {
+            PriceSource::Aggregate(sources)
+        };
+        Some(SourcedPrice::new(OraclePrice::new(key, combined), source))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::Addr;
+    use shade_protocol::Contract;
+
+    fn source(i: usize) -> PriceSource {
+        PriceSource::Oracle(Contract::new(
+            &Addr::unchecked(format!("oracle{i}")),
+            &"hash".to_string(),
+        ))
+    }
+
+    fn data(rates: &[u128]) -> Vec<(PriceSource, ReferenceData)> {
+        rates
+            .iter()
+            .enumerate()
+            .map(|(i, rate)| {
+                let data = ReferenceData::new(Uint256::from(*rate), 100 + i as u64, 200);
+                (source(i), data)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn aggregation_methods() {
+        let key = "SCRT".to_string();
+        let first = Aggregation::new(AggregationMethod::FirstHealthy, 1);
+        let resp = first
+            .aggregate(key.clone(), data(&[120, 100, 90]), vec![])
+            .unwrap();
+        assert_eq!(
+            resp.price.data,
+            ReferenceData::new(Uint256::from(120u128), 100, 200)
+        );
+        assert_eq!(resp.source, Some(source(0)));
+        let err = first
+            .aggregate(
+                key.clone(),
+                vec![],
+                vec!["oracle0: Unsupported key.".into()],
+            )
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Source errors: [\"oracle0: Unsupported key.\"]"));
+
+        let median = Aggregation::new(AggregationMethod::Median, 3);
+        let resp = median
+            .aggregate(key.clone(), data(&[120, 100, 90]), vec![])
+            .unwrap();
+        assert_eq!(
+            resp.price.data,
+            ReferenceData::new(Uint256::from(100u128), 101, 200)
+        );
+        assert_eq!(resp.source, Some(source(1)));
+        let resp = median
+            .aggregate(key.clone(), data(&[120, 100, 90, 80]), vec![])
+            .unwrap();
+        assert_eq!(
+            resp.price.data,
+            ReferenceData::new(Uint256::from(95u128), 101, 200)
+        );
+        assert_eq!(
+            resp.source,
+            Some(PriceSource::Aggregate(vec![source(2), source(1)]))
+        );
+        assert!(median
+            .aggregate(key.clone(), data(&[120, 100]), vec![])
+            .is_err());
+
+        let mean = Aggregation::new(
+            AggregationMethod::Mean {
+                max_deviation: Decimal256::percent(10),
+            },
+            2,
+        );
+        let resp = mean
+            .aggregate(key.clone(), data(&[150, 100, 96]), vec![])
+            .unwrap();
+        assert_eq!(
+            resp.price.data,
+            ReferenceData::new(Uint256::from(98u128), 101, 200)
+        );
+        assert_eq!(
+            resp.source,
+            Some(PriceSource::Aggregate(vec![source(1), source(2)]))
+        );
+        assert!(mean.aggregate(key, data(&[150, 100, 50]), vec![]).is_err());
+    }
+}
@@ -9,4 +9,32 @@ build_oracle_error! {OracleRouter {
     ExistingOracle { key: String },
     #[error("Reported price {actual} is not within expected deviation {deviation} of the expected price {expected}. Actual deviation {actual_deviation}.")]
     ProtectedPriceDeviation { actual: Uint256, expected: Uint256, deviation: Decimal256, actual_deviation: Decimal256 },
+    #[error("Price for {key} was last updated {age} seconds ago which exceeds the max age of {max_age} seconds.")]
+    StalePrice { key: String, age: u64, max_age: u64 },
+    #[error("Only {available} sources could be used to price {key} but at least {required} are required. Source errors: {errors:?}.")]
+    InsufficientSources { key: String, available: u32, required: u32, errors: Vec<String> },
+    #[error("Oracle {oracle} can't price {keys:?}.")]
+    UnsupportedKeys { oracle: String, keys: Vec<String> },
+    #[error("Price {price} of {key} from the new oracle deviates by {deviation} from its current price {current}, more than the max deviation of {max_deviation}.")]
+    NewOracleDeviation { key: String, price: Uint256, current: Uint256, deviation: Decimal256, max_deviation: Decimal256 },
+    #[error("Changes have to be proposed and wait {timelock} seconds before being executed.")]
+    TimelockRequired { timelock: u64 },
+    #[error("Proposal {id} does not exist.")]
+    ProposalNotFound { id: u64 },
+    #[error("Proposal {id} can't be executed before {executable_at}.")]
+    ProposalNotReady { id: u64, executable_at: u64 },
+    #[error("Key {key} is frozen.")]
+    FrozenKey { key: String },
+    #[error("Routing {route:?} loops back on itself.")]
+    RoutingCycle { route: Vec<String> },
+    #[error("Routing {route:?} goes deeper than the max routing depth of {max}.")]
+    RoutingTooDeep { route: Vec<String>, max: u8 },
+    #[error("Query at routing depth {depth} exceeds the max routing depth of {max}.")]
+    MaxRoutingDepth { depth: u8, max: u8 },
+    #[error("Override of {key} expires at {expires_at}, which has already passed.")]
+    ExpiredOverride { key: String, expires_at: u64 },
+    #[error("Override of unprotected key {key} has to be forced.")]
+    UnprotectedOverride { key: String },
+    #[error("Override of {key} expires at {expires_at}, after the latest expiry of {max_expires_at} for overrides that aren't forced.")]
+    OverrideTooLong { key: String, expires_at: u64, max_expires_at: u64 },
 }}
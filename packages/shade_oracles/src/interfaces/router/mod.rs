@@ -1,4 +1,5 @@
-//! Router will use Band for the quote if an oracle has not been registered for that symbol.
+//! Router will aggregate the quotes of its providers (Band, Ojo) if an oracle has not been registered for that symbol.
+pub mod aggregation;
 pub mod error;
 pub mod msg;
 pub mod registry;
@@ -2,50 +2,86 @@ use crate::{
     common::status::ContractStatus,
     impl_msg_callbacks,
     interfaces::{
-        common::{PriceResponse, PricesResponse},
+        common::{deserialize_some, PriceResponse, PriceSource, PricesResponse},
         providers::{Provider, RawProvider},
     },
 };
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::Uint256;
+use cosmwasm_std::{Addr, Decimal256, Uint256};
+use serde::{Deserialize, Deserializer};
 use shade_protocol::{utils::asset::RawContract, Contract};
 
-use super::registry::ProtectedKeyInfo;
+use super::{
+    aggregation::Aggregation,
+    registry::{LastGoodPrice, PriceOverride, ProtectedKeyInfo},
+};
 
 impl_msg_callbacks!();
 
 #[cw_serde]
 pub struct InstantiateMsg {
     pub admin_auth: RawContract,
-    pub provider: RawProvider,
+    /// Also accepts a single `provider`.
+    #[serde(alias = "provider", deserialize_with = "one_or_many")]
+    pub providers: Vec<RawProvider>,
     pub quote_symbol: String,
+    /// Default max age (in seconds) of any price returned by the router.
+    pub max_age: Option<u64>,
+    pub aggregation: Option<Aggregation>,
 }
 
 #[cw_serde]
 pub enum ExecuteMsg {
     SetStatus(ContractStatus),
     UpdateProtectedKeys(Vec<(String, Uint256)>),
+    /// Records the current prices of the protected keys as their last good prices if they pass the deviation test.
+    /// Callable by anyone.
+    RecordLastGoodPrices(Vec<String>),
+    /// Requires the timelock to be disabled.
     UpdateConfig(UpdateConfig),
+    /// Only emergency operations are allowed while the timelock is enabled.
     UpdateRegistry(RegistryOperation),
     BatchUpdateRegistry(Vec<RegistryOperation>),
+    /// Proposes a change that can be executed once the timelock has passed.
+    Propose(ProposalAction),
+    ExecuteProposal(u64),
+    CancelProposal(u64),
 }
 
 #[cw_serde]
 pub struct Config {
     pub this: Contract,
     pub admin_auth: Contract,
-    pub provider: Provider,
+    /// Providers used for any key that hasn't been registered to an oracle, in order of priority.
+    /// Configs stored with a single `provider` are read as a list of one.
+    #[serde(alias = "provider", deserialize_with = "one_or_many")]
+    pub providers: Vec<Provider>,
     pub quote_symbol: String,
+    /// Default max age (in seconds) of any price returned by the router.
+    /// Can be overridden per key in the registry.
+    pub max_age: Option<u64>,
+    /// How the data of the providers (and any extra sources) is aggregated.
+    #[serde(default)]
+    pub aggregation: Aggregation,
+    /// Delay (in seconds) before a proposed change can be executed.
+    /// Changes can be made directly when it is 0.
+    #[serde(default)]
+    pub timelock: u64,
 }
 
 #[cw_serde]
 pub enum RegistryOperation {
+    /// Keys without an oracle are served by the providers.
+    /// Also removes the fallbacks of the keys. Can be done without waiting for the timelock.
     RemoveKeys {
         keys: Vec<String>,
     },
+    /// Registers the keys to the oracle, which must be able to price all of them.
     SetKeys {
         oracle: RawContract,
         keys: Vec<String>,
+        /// Max deviation of the prices of the oracle from those of the sources currently used for the keys.
+        max_deviation: Option<Decimal256>,
     },
     SetProtection {
         infos: Vec<ProtectedKeyInfo>,
@@ -53,13 +89,150 @@ pub enum RegistryOperation {
     RemoveProtection {
         keys: Vec<String>,
     },
+    /// Sets the max age (in seconds) allowed for the price at each key.
+    SetMaxAges {
+        max_ages: Vec<(String, u64)>,
+    },
+    /// Removes the key specific max age so the key falls back to the default max age.
+    RemoveMaxAges {
+        keys: Vec<String>,
+    },
+    /// Sets oracles that are aggregated alongside the providers for the key.
+    /// Their routes are checked for cycles and depth like the routes of registered keys.
+    SetSources {
+        key: String,
+        oracles: Vec<RawContract>,
+    },
+    RemoveSources {
+        keys: Vec<String>,
+    },
+    /// Sets the oracles the key falls back to, in order, when the price of its oracle errors,
+    /// is stale or fails its deviation test. Each of them must be able to price the key.
+    SetFallbacks {
+        key: String,
+        oracles: Vec<RawContract>,
+    },
+    RemoveFallbacks {
+        keys: Vec<String>,
+    },
+    /// Serves the keys from the median reference data of the providers instead of the latest.
+    SetMedianKeys {
+        keys: Vec<String>,
+    },
+    RemoveMedianKeys {
+        keys: Vec<String>,
+    },
+    /// Sets the expected price of a tripped protected key and clears its last good price.
+    /// Can also be done by the price bot while the timelock is disabled.
+    ResetCircuitBreaker {
+        key: String,
+        price: Uint256,
+    },
+    /// Makes price queries of the keys fail. Can be done without waiting for the timelock.
+    FreezeKeys {
+        keys: Vec<String>,
+    },
+    UnfreezeKeys {
+        keys: Vec<String>,
+    },
+    /// Serves the price for the key in place of its queried price until the override expires.
+    /// Must be within the deviation of the protected key unless it is forced, which only an admin can do.
+    /// Overrides that aren't forced can also be set by the price bot without waiting for the timelock,
+    /// can't last longer than the max override duration and stop being served once they leave the deviation of the key.
+    SetOverride {
+        key: String,
+        price: Uint256,
+        expires_at: u64,
+        reason: String,
+        #[serde(default)]
+        force: bool,
+    },
+    /// Can also be done by the price bot without waiting for the timelock.
+    RemoveOverrides {
+        keys: Vec<String>,
+    },
+}
+
+impl RegistryOperation {
+    /// Whether the operation can only make the registry more restrictive or is bounded by the protected keys,
+    /// so it doesn't need to wait for the timelock.
+    pub fn is_emergency(&self) -> bool {
+        matches!(
+            self,
+            RegistryOperation::RemoveKeys { .. }
+                | RegistryOperation::FreezeKeys { .. }
+                | RegistryOperation::SetOverride { force: false, .. }
+                | RegistryOperation::RemoveOverrides { .. }
+        )
+    }
+}
+
+#[cw_serde]
+pub enum ProposalAction {
+    Registry(Vec<RegistryOperation>),
+    Config(UpdateConfig),
+}
+
+#[cw_serde]
+pub struct Proposal {
+    pub id: u64,
+    pub action: ProposalAction,
+    pub proposer: Addr,
+    /// Time after which the proposal can be executed.
+    pub executable_at: u64,
 }
 
 #[cw_serde]
 pub struct UpdateConfig {
     pub admin_auth: Option<RawContract>,
-    pub provider: Option<RawProvider>,
+    /// Also accepts a single `provider`.
+    #[serde(default, alias = "provider", deserialize_with = "maybe_one_or_many")]
+    pub providers: Option<Vec<RawProvider>>,
     pub quote_symbol: Option<String>,
+    /// Set to null to remove the default max age, leave out to keep it unchanged.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_some"
+    )]
+    pub max_age: Option<Option<u64>>,
+    pub aggregation: Option<Aggregation>,
+    pub timelock: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T> From<OneOrMany<T>> for Vec<T> {
+    fn from(value: OneOrMany<T>) -> Self {
+        match value {
+            OneOrMany::One(item) => vec![item],
+            OneOrMany::Many(items) => items,
+        }
+    }
+}
+
+/// Deserializes either a single item or a list of them, so fields that used to hold a single
+/// item keep accepting it.
+fn one_or_many<'de, T, D>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    OneOrMany::deserialize(deserializer).map(Vec::from)
+}
+
+/// Optional version of `one_or_many`.
+fn maybe_one_or_many<'de, T, D>(deserializer: D) -> Result<Option<Vec<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<OneOrMany<T>>::deserialize(deserializer).map(|value| value.map(Vec::from))
 }
 
 #[cw_serde]
@@ -77,15 +250,81 @@ pub enum QueryMsg {
     GetOracles { keys: Vec<String> },
     #[returns(PricesResponse)]
     GetPrices { keys: Vec<String> },
+    /// Get price of the key quoted in another key, triangulated through the quote symbol of the router.
+    #[returns(PriceResponse)]
+    GetPriceIn { key: String, quote: String },
+    #[returns(PricesResponse)]
+    GetPricesIn { keys: Vec<String>, quote: String },
+    /// Used by routed oracles to query the prices they depend on, erroring once the depth exceeds the max routing depth.
+    #[returns(PricesResponse)]
+    GetPricesAtDepth { keys: Vec<String>, depth: u8 },
+    /// Get the price of the key along with where it came from and how it is protected.
+    #[returns(PriceDetailedResponse)]
+    GetPriceDetailed { key: String },
+    /// Get the price of each key, or why it can't be priced, without failing the whole query.
+    /// Routed oracles pass on the routing depth of the query they are answering.
+    #[returns(LenientPricesResponse)]
+    GetPricesLenient {
+        keys: Vec<String>,
+        #[serde(default)]
+        depth: u8,
+    },
+    /// Get the keys registered to an oracle, paginated in the order they were registered.
+    /// Removing a key moves the last registered key into its place.
+    #[returns(KeysResponse)]
+    GetKeys {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Get the keys registered to the oracle at that address.
     #[returns(KeysResponse)]
-    GetKeys {},
+    GetKeysByOracle {
+        oracle: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
     #[returns(ProtectedKeysResponse)]
-    GetProtectedKeys {},
+    GetProtectedKeys {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Get the max age enforced on the price of each key, if any.
+    #[returns(MaxAgesResponse)]
+    GetMaxAges { keys: Vec<String> },
+    /// Get the providers and extra oracles aggregated for the key.
+    #[returns(SourcesResponse)]
+    GetSources { key: String },
+    /// Get the changes the operations would make to the registry, without making them.
+    #[returns(DryRunRegistryResponse)]
+    DryRunRegistry { operations: Vec<RegistryOperation> },
+    /// Get the proposals waiting to be executed, ordered by id.
+    #[returns(ProposalsResponse)]
+    GetProposals {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    #[returns(Proposal)]
+    GetProposal { id: u64 },
+    /// Get the circuit breaker state of each key, reporting errors per key.
+    #[returns(CircuitBreakersResponse)]
+    GetCircuitBreakers { keys: Vec<String> },
+    /// Get the price overrides, including expired ones until they are removed or replaced.
+    #[returns(OverridesResponse)]
+    GetOverrides {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
 }
 
 pub type OraclesResponse = Vec<OracleResponse>;
 pub type KeysResponse = Vec<String>;
 pub type ProtectedKeysResponse = Vec<ProtectedKeyInfo>;
+pub type MaxAgesResponse = Vec<(String, Option<u64>)>;
+pub type CircuitBreakersResponse = Vec<CircuitBreakerResponse>;
+pub type DryRunRegistryResponse = Vec<RegistryChange>;
+pub type LenientPricesResponse = Vec<LenientPriceResponse>;
+pub type ProposalsResponse = Vec<Proposal>;
+pub type OverridesResponse = Vec<PriceOverride>;
 
 #[cw_serde]
 pub struct ConfigResponse {
@@ -93,8 +332,174 @@ pub struct ConfigResponse {
     pub status: ContractStatus,
 }
 
+#[cw_serde]
+pub struct SourcesResponse {
+    pub key: String,
+    pub providers: Vec<Provider>,
+    pub oracles: Vec<Contract>,
+    /// Whether the providers are queried for their median reference data.
+    pub median: bool,
+}
+
 #[cw_serde]
 pub struct OracleResponse {
     pub key: String,
     pub oracle: Contract,
+    /// The oracle followed by its fallbacks.
+    pub chain: Vec<Contract>,
+}
+
+#[cw_serde]
+pub struct PriceDetailedResponse {
+    pub price: PriceResponse,
+    /// Where the price came from.
+    pub source: Option<PriceSource>,
+    /// Oracle the price was queried from, the router itself when it was served by its providers or overridden.
+    pub oracle: Contract,
+    /// Index of the oracle in the oracle chain of the key, unset if the price is overridden.
+    pub active: Option<u32>,
+    /// Providers the price was taken from, if any.
+    pub providers: Vec<Provider>,
+    pub protection: Option<ProtectedKeyInfo>,
+    /// Deviation of the queried price from the expected price of the protected key,
+    /// which can differ from the price served while its circuit breaker is tripped.
+    pub deviation: Option<Decimal256>,
+    /// Seconds since the price was last updated, as checked against the max age of the key.
+    pub age: u64,
+}
+
+/// Step of the price query that failed for a key.
+#[cw_serde]
+pub enum PriceErrorKind {
+    Frozen,
+    /// The oracle (or every provider) errored or didn't return the key.
+    Unavailable,
+    Stale,
+    /// The price deviates too much from the protected price of the key.
+    Deviation,
+}
+
+#[cw_serde]
+pub struct PriceError {
+    pub kind: PriceErrorKind,
+    pub reason: String,
+}
+
+impl PriceError {
+    pub fn new(kind: PriceErrorKind, err: impl ToString) -> Self {
+        PriceError {
+            kind,
+            reason: err.to_string(),
+        }
+    }
+}
+
+/// Either the price or the error is set.
+#[cw_serde]
+pub struct LenientPriceResponse {
+    pub key: String,
+    pub price: Option<PriceResponse>,
+    pub error: Option<PriceError>,
+}
+
+#[cw_serde]
+pub struct CircuitBreakerResponse {
+    pub key: String,
+    pub grace_period: Option<u64>,
+    pub last_good_price: Option<LastGoodPrice>,
+    /// Whether the current price deviates too much from the expected price.
+    /// False if the current price couldn't be queried.
+    pub tripped: bool,
+    /// Whether the last good price is being served in place of the current price.
+    pub serving: bool,
+    /// Time until which the last good price is served, if tripped.
+    pub expires_at: Option<u64>,
+    /// Why the key couldn't be checked, if it isn't protected or its price couldn't be queried.
+    pub error: Option<String>,
+}
+
+/// Change made to the registry by a registry operation.
+#[cw_serde]
+pub enum RegistryChange {
+    /// Keys without an oracle are served by the router itself.
+    Oracle {
+        key: String,
+        before: Option<Contract>,
+        after: Option<Contract>,
+    },
+    Protection {
+        key: String,
+        before: Option<ProtectedKeyInfo>,
+        after: Option<ProtectedKeyInfo>,
+    },
+    ClearLastGoodPrice {
+        key: String,
+        before: LastGoodPrice,
+    },
+    MaxAge {
+        key: String,
+        before: Option<u64>,
+        after: Option<u64>,
+    },
+    Sources {
+        key: String,
+        before: Vec<Contract>,
+        after: Vec<Contract>,
+    },
+    Fallbacks {
+        key: String,
+        before: Vec<Contract>,
+        after: Vec<Contract>,
+    },
+    Median {
+        key: String,
+        before: bool,
+        after: bool,
+    },
+    Frozen {
+        key: String,
+        before: bool,
+        after: bool,
+    },
+    /// Whether the oracle reports the keys it routes through, so it is queried with the routing depth.
+    Routed {
+        oracle: Contract,
+        before: bool,
+        after: bool,
+    },
+    Override {
+        key: String,
+        before: Option<PriceOverride>,
+        after: Option<PriceOverride>,
+    },
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use cosmwasm_std::from_slice;
+
+    #[test]
+    fn single_provider_config() {
+        let config: Config = from_slice(
+            br#"{
+                "this": {"address": "router", "code_hash": "hash"},
+                "admin_auth": {"address": "admin", "code_hash": "hash"},
+                "provider": {"band": {"address": "band", "code_hash": "hash"}},
+                "quote_symbol": "USD"
+            }"#,
+        )
+        .unwrap();
+        let band = Contract::new(&Addr::unchecked("band"), &"hash".to_string());
+        assert_eq!(config.providers, vec![Provider::Band(band)]);
+        assert_eq!(config.aggregation, Aggregation::default());
+        assert_eq!(config.max_age, None);
+
+        let update: UpdateConfig =
+            from_slice(br#"{"provider": {"ojo": {"address": "ojo", "code_hash": "hash"}}}"#)
+                .unwrap();
+        assert_eq!(update.providers.map(|providers| providers.len()), Some(1));
+        let update: UpdateConfig = from_slice(br#"{}"#).unwrap();
+        assert_eq!(update.providers, None);
+    }
 }
@@ -1,17 +1,43 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Decimal256, Uint256};
+use cosmwasm_std::{Decimal256, StdError, StdResult, Uint256};
 use shade_protocol::Contract;
 
+use super::error::OracleRouterError;
+use crate::interfaces::{
+    common::{OraclePrice, PriceSource, SourcedPrice},
+    providers::ReferenceData,
+};
+
 #[cw_serde]
 pub struct OracleRouter {
     pub config: Config,
 }
 
+/// Relative deviation of the price from the expected price.
+pub fn deviation(price: Uint256, expected: Uint256) -> Decimal256 {
+    let abs_diff = if expected > price {
+        expected - price
+    } else {
+        price - expected
+    };
+    if abs_diff.is_zero() {
+        Decimal256::zero()
+    } else if expected.is_zero() {
+        Decimal256::MAX
+    } else {
+        Decimal256::from_ratio(abs_diff, expected)
+    }
+}
+
 #[cw_serde]
 pub struct ProtectedKeyInfo {
     pub key: String,
     pub deviation: Decimal256,
     pub price: Uint256,
+    /// Seconds after the circuit breaker tripped during which the last good price is served instead of a deviating price.
+    /// Deviating prices fail right away if not set.
+    #[serde(default)]
+    pub grace_period: Option<u64>,
 }
 
 impl ProtectedKeyInfo {
@@ -20,10 +46,103 @@ impl ProtectedKeyInfo {
             key,
             deviation,
             price,
+            grace_period: None,
+        }
+    }
+
+    pub fn with_grace_period(mut self, grace_period: u64) -> Self {
+        self.grace_period = Some(grace_period);
+        self
+    }
+
+    /// Relative deviation of the price from the expected price.
+    pub fn deviation_of(&self, price: Uint256) -> Decimal256 {
+        deviation(price, self.price)
+    }
+
+    /// Errors if the price deviates from the expected price by more than the allowed deviation.
+    pub fn check_deviation(&self, price: Uint256) -> StdResult<()> {
+        let actual_deviation = self.deviation_of(price);
+        if actual_deviation > self.deviation {
+            Err(StdError::generic_err(
+                OracleRouterError::ProtectedPriceDeviation {
+                    actual: price,
+                    expected: self.price,
+                    deviation: self.deviation,
+                    actual_deviation,
+                }
+                .to_string(),
+            ))
+        } else {
+            Ok(())
         }
     }
 }
 
+/// Last price of a protected key that was within its allowed deviation.
+#[cw_serde]
+pub struct LastGoodPrice {
+    pub price: OraclePrice,
+    /// Where the price came from.
+    #[serde(default)]
+    pub source: Option<PriceSource>,
+    pub recorded_at: u64,
+    /// Time a deviating price was first recorded after this price.
+    #[serde(default)]
+    pub tripped_at: Option<u64>,
+}
+
+impl LastGoodPrice {
+    /// Time the grace period started, falling back to when the price was recorded
+    /// if the trip hasn't been recorded yet.
+    pub fn grace_start(&self) -> u64 {
+        self.tripped_at.unwrap_or(self.recorded_at)
+    }
+
+    /// Time until which the price can be served by the circuit breaker.
+    pub fn expires_at(&self, grace_period: u64) -> u64 {
+        self.grace_start().saturating_add(grace_period)
+    }
+
+    /// The recorded price, marked as being served by the circuit breaker.
+    pub fn serve(self, key: String) -> SourcedPrice {
+        let source = PriceSource::CircuitBreaker {
+            recorded_at: self.recorded_at,
+            source: self.source.map(Box::new),
+        };
+        SourcedPrice::new(OraclePrice::new(key, self.price.data), source)
+    }
+}
+
+/// Price served for a key in place of its queried price until it expires.
+#[cw_serde]
+pub struct PriceOverride {
+    pub key: String,
+    pub price: Uint256,
+    pub expires_at: u64,
+    pub reason: String,
+    /// Whether the price skipped the deviation test of the protected key.
+    pub forced: bool,
+    pub set_at: u64,
+}
+
+impl PriceOverride {
+    pub fn is_expired(&self, now: u64) -> bool {
+        now >= self.expires_at
+    }
+
+    /// The override price, marked as an override and timestamped with the time it was set.
+    pub fn serve(self) -> SourcedPrice {
+        let data = ReferenceData::new(self.price, self.set_at, self.set_at);
+        let source = PriceSource::Override {
+            reason: self.reason,
+            expires_at: self.expires_at,
+            forced: self.forced,
+        };
+        SourcedPrice::new(OraclePrice::new(self.key, data), source)
+    }
+}
+
 pub struct Oracle;
 
 #[cfg(feature = "router")]
@@ -32,27 +151,157 @@ pub use state::*;
 use super::msg::Config;
 #[cfg(feature = "router")]
 mod state {
-    use std::{
-        collections::{HashMap, HashSet},
-        vec,
-    };
+    use std::{collections::HashMap, vec};
 
     use crate::{
         impl_global_status,
-        interfaces::{common::OraclePrice, router::msg::RegistryOperation},
         interfaces::{
-            common::{PriceResponse, PricesResponse},
-            router::{error::OracleRouterError, msg::UpdateConfig},
+            common::{BtrOraclePrice, OraclePrice, PriceSource, SourcedPrice},
+            router::msg::{
+                CircuitBreakerResponse, Proposal, ProposalAction, RegistryChange, RegistryOperation,
+            },
+        },
+        interfaces::{
+            providers::{Provider, ReferenceData, STALE_DATA},
+            router::{
+                error::OracleRouterError,
+                msg::{SourcesResponse, UpdateConfig},
+            },
         },
+        querier::{query_dependencies, query_price, query_prices, query_routed_prices},
     };
 
     use super::*;
     use cosmwasm_std::{
-        to_binary, Api, Binary, Deps, QuerierWrapper, StdError, StdResult, Storage,
+        to_binary, Addr, Api, Binary, Deps, DepsMut, QuerierWrapper, StdError, StdResult, Storage,
+        Timestamp,
     };
-    use secret_borsh_storage::BorshItem;
     use secret_storage_plus::{GenericMapStorage, Item, ItemStorage, Map};
 
+    pub const DEFAULT_PAGE_LIMIT: u32 = 30;
+    pub const MAX_PAGE_LIMIT: u32 = 100;
+    /// Max amount of routed oracles a price query can go through.
+    pub const MAX_ROUTING_DEPTH: u8 = 4;
+    /// Max amount of seconds an override that isn't forced can be served for.
+    pub const MAX_OVERRIDE_DURATION: u64 = 24 * 60 * 60;
+
+    /// Sets of keys stored as indexed maps so they can be paginated without loading every key.
+    ///
+    /// Keys can be split into multiple sets (i.e. one per oracle), an empty set name is used when only one is needed.
+    /// Keys are listed in the order they were added to their set, except that removing a key moves the last key
+    /// of the set into its slot so the slots stay contiguous and a page never scans more slots than its limit.
+    pub struct IndexedKeys<'a> {
+        lens: &'a str,
+        keys: &'a str,
+        indexes: &'a str,
+    }
+
+    impl<'a> IndexedKeys<'a> {
+        pub const fn new(lens: &'a str, keys: &'a str, indexes: &'a str) -> Self {
+            IndexedKeys {
+                lens,
+                keys,
+                indexes,
+            }
+        }
+
+        fn lens<'k>(&self) -> Map<'a, &'k str, u32> {
+            Map::new(self.lens)
+        }
+
+        fn keys<'k>(&self) -> Map<'a, (&'k str, u32), String> {
+            Map::new(self.keys)
+        }
+
+        /// Slot of every key that was ever added to the set. Removed keys keep the slot they were removed from,
+        /// which is where paginating after them resumes.
+        fn indexes<'k>(&self) -> Map<'a, (&'k str, &'k str), u32> {
+            Map::new(self.indexes)
+        }
+
+        /// Number of keys in the set.
+        fn len(&self, storage: &dyn Storage, set: &str) -> StdResult<u32> {
+            Ok(self.lens().may_load(storage, set)?.unwrap_or_default())
+        }
+
+        /// Slot of the key if it is in the set.
+        fn index_of(&self, storage: &dyn Storage, set: &str, key: &str) -> StdResult<Option<u32>> {
+            let index = match self.indexes().may_load(storage, (set, key))? {
+                Some(index) => index,
+                None => return Ok(None),
+            };
+            match self.keys().may_load(storage, (set, index))? {
+                Some(listed) if listed == key => Ok(Some(index)),
+                _ => Ok(None),
+            }
+        }
+
+        pub fn contains(&self, storage: &dyn Storage, set: &str, key: &str) -> StdResult<bool> {
+            Ok(self.index_of(storage, set, key)?.is_some())
+        }
+
+        /// Returns whether the key was added to the set.
+        pub fn insert(&self, storage: &mut dyn Storage, set: &str, key: &str) -> StdResult<bool> {
+            if self.contains(storage, set, key)? {
+                return Ok(false);
+            }
+            let index = self.len(storage, set)?;
+            self.indexes().save(storage, (set, key), &index)?;
+            self.keys().save(storage, (set, index), &key.to_string())?;
+            self.lens().save(storage, set, &(index + 1))?;
+            Ok(true)
+        }
+
+        /// Returns whether the key was in the set.
+        pub fn remove(&self, storage: &mut dyn Storage, set: &str, key: &str) -> StdResult<bool> {
+            let index = match self.index_of(storage, set, key)? {
+                Some(index) => index,
+                None => return Ok(false),
+            };
+            let last = self.len(storage, set)? - 1;
+            if index != last {
+                let moved = self.keys().load(storage, (set, last))?;
+                self.keys().save(storage, (set, index), &moved)?;
+                self.indexes().save(storage, (set, &moved), &index)?;
+            }
+            self.keys().remove(storage, (set, last));
+            self.lens().save(storage, set, &last)?;
+            Ok(true)
+        }
+
+        /// Keys of the set that come after the given key, up to the limit.
+        ///
+        /// The given key can have been removed since, in which case the page starts at the slot it was removed from
+        /// so the key moved into it is listed. Keys moved in front of the given key while paginating are skipped.
+        pub fn page(
+            &self,
+            storage: &dyn Storage,
+            set: &str,
+            start_after: Option<&str>,
+            limit: Option<u32>,
+        ) -> StdResult<Vec<String>> {
+            let start = match start_after {
+                None => 0,
+                Some(key) => match self.index_of(storage, set, key)? {
+                    Some(index) => index + 1,
+                    None => match self.indexes().may_load(storage, (set, key))? {
+                        Some(index) => index,
+                        None => {
+                            return Err(StdError::generic_err(format!(
+                                "Can't paginate after {key} because it was never listed."
+                            )))
+                        }
+                    },
+                },
+            };
+            let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT).min(MAX_PAGE_LIMIT);
+            let end = self.len(storage, set)?.min(start.saturating_add(limit));
+            (start..end)
+                .map(|index| self.keys().load(storage, (set, index)))
+                .collect()
+        }
+    }
+
     impl_global_status!(OracleRouter, OracleRouterError);
 
     impl ItemStorage for Config {
@@ -65,20 +314,56 @@ mod state {
 
     impl<'a> OracleRouter {
         /// List of explicity supported keys (keys registered to an oracle).
-        pub const KEYS: BorshItem<'static, HashSet<String>> = BorshItem::new("oraclerouterkeys");
-        pub const PROTECTED_KEYS_LIST: BorshItem<'static, HashSet<String>> =
-            BorshItem::new("oraclerouterprotectedkeyslist");
+        pub const KEYS: IndexedKeys<'static> = IndexedKeys::new(
+            "oraclerouterkeyslen",
+            "oraclerouterkeyslist",
+            "oraclerouterkeysindex",
+        );
+        pub const PROTECTED_KEYS_LIST: IndexedKeys<'static> = IndexedKeys::new(
+            "oraclerouterprotectedkeyslen",
+            "oraclerouterprotectedkeyslist",
+            "oraclerouterprotectedkeysindex",
+        );
+        pub const OVERRIDE_KEYS_LIST: IndexedKeys<'static> = IndexedKeys::new(
+            "oraclerouteroverridekeyslen",
+            "oraclerouteroverridekeyslist",
+            "oraclerouteroverridekeysindex",
+        );
+        /// Keys registered to each oracle, where the set is the address of the oracle.
+        pub const ORACLE_KEYS: IndexedKeys<'static> = IndexedKeys::new(
+            "oraclerouteroraclekeyslen",
+            "oraclerouteroraclekeyslist",
+            "oraclerouteroraclekeysindex",
+        );
         pub const PROTECTED_KEYS: Map<'static, &'a str, ProtectedKeyInfo> =
             Map::new("oraclerouterprotectedkeys");
+        /// Key specific max ages which take precedence over the default max age.
+        pub const MAX_AGES: Map<'static, &'a str, u64> = Map::new("oraclerouterkeymaxages");
+        /// Extra oracles aggregated alongside the providers for a key.
+        pub const SOURCES: Map<'static, &'a str, Vec<Contract>> =
+            Map::new("oraclerouterkeysources");
+        /// Oracles each key falls back to, in order, when the price of its oracle can't be used.
+        pub const FALLBACKS: Map<'static, &'a str, Vec<Contract>> =
+            Map::new("oraclerouterkeyfallbacks");
+        /// Keys served from the median reference data of the providers.
+        pub const MEDIAN_KEYS: Map<'static, &'a str, bool> = Map::new("oraclerouterkeymedians");
+        /// Last price of each protected key that passed its deviation test.
+        pub const LAST_GOOD_PRICES: Map<'static, &'a str, LastGoodPrice> =
+            Map::new("oraclerouterlastgoodprices");
+        /// Keys that can't be priced until they are unfrozen.
+        pub const FROZEN_KEYS: Map<'static, &'a str, bool> = Map::new("oraclerouterfrozenkeys");
+        /// Prices served in place of the queried prices of the keys until they expire.
+        pub const OVERRIDES: Map<'static, &'a str, PriceOverride> =
+            Map::new("oraclerouterpriceoverrides");
+        /// Oracles that report the keys they route through, keyed by address.
+        pub const ROUTED_ORACLES: Map<'static, &'a str, bool> =
+            Map::new("oraclerouterroutedoracles");
+        /// Proposals that haven't been executed or cancelled.
+        pub const PROPOSALS: Map<'static, u64, Proposal> = Map::new("oraclerouterproposals");
+        pub const PROPOSAL_COUNT: Item<'static, u64> = Item::new("oraclerouterproposalcount");
     }
 
     impl OracleRouter {
-        pub fn init_storage(storage: &mut dyn Storage) -> StdResult<()> {
-            Self::PROTECTED_KEYS_LIST.save(storage, &HashSet::new())?;
-            Self::KEYS.save(storage, &HashSet::new())?;
-            Ok(())
-        }
-
         pub fn update_protected_key(
             storage: &mut dyn Storage,
             key: &str,
@@ -99,47 +384,234 @@ mod state {
             let info = Self::PROTECTED_KEYS.may_load(storage, &price.key)?;
             match info {
                 None => Ok(()),
-                Some(info) => {
-                    let price = price.data.rate;
-                    let abs_diff = if info.price > price {
-                        info.price - price
-                    } else {
-                        price - info.price
-                    };
-                    if abs_diff.is_zero() {
-                        Ok(())
-                    } else {
-                        let deviation = Decimal256::from_ratio(abs_diff, info.price);
-                        if deviation > info.deviation {
-                            Err(StdError::generic_err(
-                                OracleRouterError::ProtectedPriceDeviation {
-                                    actual: price,
-                                    expected: info.price,
-                                    deviation: info.deviation,
-                                    actual_deviation: deviation,
-                                }
-                                .to_string(),
-                            ))
-                        } else {
-                            Ok(())
+                Some(info) => info.check_deviation(price.data.rate),
+            }
+        }
+
+        /// Runs the deviation test of protected keys, serving the last good price instead of a deviating one
+        /// while the grace period of the key hasn't passed.
+        ///
+        /// Forced overrides are let through, overrides that aren't forced have to stay within the deviation
+        /// of the current protection of the key.
+        pub fn try_circuit_breaker(
+            storage: &dyn Storage,
+            price: SourcedPrice,
+            now: &Timestamp,
+        ) -> StdResult<SourcedPrice> {
+            if let Some(PriceSource::Override { forced, .. }) = &price.source {
+                if !*forced {
+                    match Self::PROTECTED_KEYS.may_load(storage, &price.price.key)? {
+                        Some(info) => info.check_deviation(price.price.data.rate)?,
+                        None => {
+                            return Err(OracleRouterError::UnprotectedOverride {
+                                key: price.price.key,
+                            }
+                            .into())
                         }
                     }
                 }
+                return Ok(price);
+            }
+            let info = match Self::PROTECTED_KEYS.may_load(storage, &price.price.key)? {
+                None => return Ok(price),
+                Some(info) => info,
+            };
+            let err = match info.check_deviation(price.price.data.rate) {
+                Ok(_) => return Ok(price),
+                Err(err) => err,
+            };
+            let last_good = Self::LAST_GOOD_PRICES.may_load(storage, &price.price.key)?;
+            match (info.grace_period, last_good) {
+                (Some(grace_period), Some(last_good))
+                    if now.seconds() <= last_good.expires_at(grace_period) =>
+                {
+                    Ok(last_good.serve(price.price.key))
+                }
+                _ => Err(err),
             }
         }
 
+        /// Records the price as the last good price of its protected key if it passes the deviation test,
+        /// otherwise records when the circuit breaker tripped if it wasn't already.
+        /// Overrides are never recorded.
+        ///
+        /// Returns whether the price was recorded.
+        pub fn record_last_good_price(
+            storage: &mut dyn Storage,
+            price: SourcedPrice,
+            now: &Timestamp,
+        ) -> StdResult<bool> {
+            let info = match Self::PROTECTED_KEYS.may_load(storage, &price.price.key)? {
+                None => {
+                    return Err(StdError::generic_err(format!(
+                        "Can't record last good price for unprotected key {}.",
+                        price.price.key
+                    )))
+                }
+                Some(info) => info,
+            };
+            if price.is_override() {
+                return Ok(false);
+            }
+            if info.check_deviation(price.price.data.rate).is_err() {
+                if let Some(mut last_good) =
+                    Self::LAST_GOOD_PRICES.may_load(storage, &price.price.key)?
+                {
+                    if last_good.tripped_at.is_none() {
+                        last_good.tripped_at = Some(now.seconds());
+                        Self::LAST_GOOD_PRICES.save(storage, &price.price.key, &last_good)?;
+                    }
+                }
+                return Ok(false);
+            }
+            let last_good = LastGoodPrice {
+                price: price.price,
+                source: price.source,
+                recorded_at: now.seconds(),
+                tripped_at: None,
+            };
+            Self::LAST_GOOD_PRICES.save(storage, &last_good.price.key, &last_good)?;
+            Ok(true)
+        }
+
+        /// State of the circuit breaker of a key, where price is its latest unprotected price.
+        ///
+        /// Unprotected keys and prices that couldn't be queried are reported in the error of the response.
+        pub fn get_circuit_breaker(
+            storage: &dyn Storage,
+            key: String,
+            price: StdResult<OraclePrice>,
+            now: &Timestamp,
+        ) -> StdResult<CircuitBreakerResponse> {
+            let mut resp = CircuitBreakerResponse {
+                key,
+                grace_period: None,
+                last_good_price: None,
+                tripped: false,
+                serving: false,
+                expires_at: None,
+                error: None,
+            };
+            let info = match Self::PROTECTED_KEYS.may_load(storage, &resp.key)? {
+                None => {
+                    resp.error = Some(format!("Key {} is not protected.", resp.key));
+                    return Ok(resp);
+                }
+                Some(info) => info,
+            };
+            resp.grace_period = info.grace_period;
+            resp.last_good_price = Self::LAST_GOOD_PRICES.may_load(storage, &resp.key)?;
+            let price = match price {
+                Ok(price) => price,
+                Err(err) => {
+                    resp.error = Some(err.to_string());
+                    return Ok(resp);
+                }
+            };
+            resp.tripped = info.check_deviation(price.data.rate).is_err();
+            resp.expires_at = match (&resp.last_good_price, info.grace_period) {
+                (Some(last_good), Some(grace_period)) if resp.tripped => {
+                    Some(last_good.expires_at(grace_period))
+                }
+                _ => None,
+            };
+            resp.serving = resp
+                .expires_at
+                .map_or(false, |expires_at| now.seconds() <= expires_at);
+            Ok(resp)
+        }
+
+        /// Gets the max age of the price at the key, falling back to the default max age.
+        pub fn get_max_age(&self, storage: &dyn Storage, key: &str) -> StdResult<Option<u64>> {
+            Ok(Self::MAX_AGES
+                .may_load(storage, key)?
+                .or(self.config.max_age))
+        }
+
+        /// Whether the data is older than the max age, if there is one.
+        fn exceeds_max_age(
+            max_age: Option<u64>,
+            data: &ReferenceData,
+            now: &Timestamp,
+        ) -> StdResult<bool> {
+            match max_age {
+                None => Ok(false),
+                Some(max_age) => {
+                    BtrOraclePrice::from(OraclePrice::new(String::new(), data.clone()))
+                        .is_stale_price(max_age, now)
+                }
+            }
+        }
+
+        pub fn is_stale(
+            &self,
+            storage: &dyn Storage,
+            price: &OraclePrice,
+            now: &Timestamp,
+        ) -> StdResult<bool> {
+            let max_age = self.get_max_age(storage, &price.key)?;
+            Self::exceeds_max_age(max_age, &price.data, now)
+        }
+
+        /// Overrides don't go stale, they expire instead.
+        pub fn try_staleness_test(
+            &self,
+            storage: &dyn Storage,
+            price: &SourcedPrice,
+            now: &Timestamp,
+        ) -> StdResult<()> {
+            if price.is_override() || !self.is_stale(storage, &price.price, now)? {
+                return Ok(());
+            }
+            let max_age = self
+                .get_max_age(storage, &price.price.key)?
+                .unwrap_or_default();
+            let price = BtrOraclePrice::from(price.price.clone());
+            Err(OracleRouterError::StalePrice {
+                age: price.time_since_updated(now)?,
+                key: price.key,
+                max_age,
+            }
+            .into())
+        }
+
         pub fn load(storage: &dyn Storage) -> StdResult<Self> {
             let config = Config::load(storage)?;
             Ok(OracleRouter { config })
         }
 
-        pub fn get_keys(deps: Deps) -> StdResult<Binary> {
-            let keys = Self::KEYS.load(deps.storage)?;
-            to_binary(&keys.into_iter().collect::<Vec<String>>())
+        pub fn get_keys(
+            deps: Deps,
+            start_after: Option<String>,
+            limit: Option<u32>,
+        ) -> StdResult<Binary> {
+            let keys = Self::KEYS.page(deps.storage, "", start_after.as_deref(), limit)?;
+            to_binary(&keys)
         }
 
-        pub fn get_protected_keys(deps: Deps) -> StdResult<Binary> {
-            let keys = Self::PROTECTED_KEYS_LIST.load(deps.storage)?;
+        pub fn get_keys_by_oracle(
+            deps: Deps,
+            oracle: String,
+            start_after: Option<String>,
+            limit: Option<u32>,
+        ) -> StdResult<Binary> {
+            let oracle = deps.api.addr_validate(&oracle)?;
+            let keys = Self::ORACLE_KEYS.page(
+                deps.storage,
+                oracle.as_str(),
+                start_after.as_deref(),
+                limit,
+            )?;
+            to_binary(&keys)
+        }
+
+        pub fn get_protected_keys(
+            deps: Deps,
+            start_after: Option<String>,
+            limit: Option<u32>,
+        ) -> StdResult<Binary> {
+            let keys =
+                Self::PROTECTED_KEYS_LIST.page(deps.storage, "", start_after.as_deref(), limit)?;
             let mut protected_keys = vec![];
             for key in keys {
                 if let Some(info) = Self::PROTECTED_KEYS.may_load(deps.storage, &key)? {
@@ -149,46 +621,863 @@ mod state {
             to_binary(&protected_keys)
         }
 
-        pub fn resolve_registry_operation(
-            api: &dyn Api,
+        /// Price of the override of the key, if it has one that hasn't expired.
+        pub fn get_override(
+            storage: &dyn Storage,
+            key: &str,
+            now: &Timestamp,
+        ) -> StdResult<Option<SourcedPrice>> {
+            Ok(Self::OVERRIDES
+                .may_load(storage, key)?
+                .filter(|price_override| !price_override.is_expired(now.seconds()))
+                .map(PriceOverride::serve))
+        }
+
+        pub fn get_overrides(
+            deps: Deps,
+            start_after: Option<String>,
+            limit: Option<u32>,
+        ) -> StdResult<Binary> {
+            let keys =
+                Self::OVERRIDE_KEYS_LIST.page(deps.storage, "", start_after.as_deref(), limit)?;
+            let mut overrides = vec![];
+            for key in keys {
+                if let Some(price_override) = Self::OVERRIDES.may_load(deps.storage, &key)? {
+                    overrides.push(price_override);
+                }
+            }
+            to_binary(&overrides)
+        }
+
+        /// Removes the key from the registry and the keys of the oracle it was registered to.
+        fn unregister_key(storage: &mut dyn Storage, key: &str) -> StdResult<()> {
+            if let Some(oracle) = Oracle::may_load(storage, key)? {
+                Self::ORACLE_KEYS.remove(storage, oracle.address.as_str(), key)?;
+                Oracle::MAP.remove(storage, key);
+            }
+            Self::KEYS.remove(storage, "", key)?;
+            Ok(())
+        }
+
+        pub fn get_max_ages(&self, deps: Deps, keys: Vec<String>) -> StdResult<Binary> {
+            let mut max_ages = vec![];
+            for key in keys {
+                let max_age = self.get_max_age(deps.storage, &key)?;
+                max_ages.push((key, max_age));
+            }
+            to_binary(&max_ages)
+        }
+
+        pub fn require_no_timelock(&self) -> StdResult<()> {
+            if self.config.timelock > 0 {
+                return Err(OracleRouterError::TimelockRequired {
+                    timelock: self.config.timelock,
+                }
+                .into());
+            }
+            Ok(())
+        }
+
+        pub fn require_routing_depth(depth: u8) -> StdResult<()> {
+            if depth > MAX_ROUTING_DEPTH {
+                return Err(OracleRouterError::MaxRoutingDepth {
+                    depth,
+                    max: MAX_ROUTING_DEPTH,
+                }
+                .into());
+            }
+            Ok(())
+        }
+
+        pub fn is_routed(storage: &dyn Storage, oracle: &Contract) -> StdResult<bool> {
+            Ok(Self::ROUTED_ORACLES
+                .may_load(storage, oracle.address.as_str())?
+                .unwrap_or_default())
+        }
+
+        pub fn require_not_frozen(storage: &dyn Storage, key: &str) -> StdResult<()> {
+            if Self::FROZEN_KEYS
+                .may_load(storage, key)?
+                .unwrap_or_default()
+            {
+                return Err(OracleRouterError::FrozenKey {
+                    key: key.to_string(),
+                }
+                .into());
+            }
+            Ok(())
+        }
+
+        pub fn propose(
+            &self,
             storage: &mut dyn Storage,
+            action: ProposalAction,
+            proposer: Addr,
+            now: &Timestamp,
+        ) -> StdResult<Proposal> {
+            let id = Self::PROPOSAL_COUNT.may_load(storage)?.unwrap_or_default();
+            let proposal = Proposal {
+                id,
+                action,
+                proposer,
+                executable_at: now.seconds().saturating_add(self.config.timelock),
+            };
+            Self::PROPOSALS.save(storage, id, &proposal)?;
+            Self::PROPOSAL_COUNT.save(storage, &(id + 1))?;
+            Ok(proposal)
+        }
+
+        /// Removes the proposal so it can't be executed or cancelled again.
+        pub fn remove_proposal(storage: &mut dyn Storage, id: u64) -> StdResult<Proposal> {
+            let proposal = Self::load_proposal(storage, id)?;
+            Self::PROPOSALS.remove(storage, id);
+            Ok(proposal)
+        }
+
+        /// Removes the proposal if the timelock has passed.
+        pub fn take_ready_proposal(
+            storage: &mut dyn Storage,
+            id: u64,
+            now: &Timestamp,
+        ) -> StdResult<Proposal> {
+            let proposal = Self::load_proposal(storage, id)?;
+            if now.seconds() < proposal.executable_at {
+                return Err(OracleRouterError::ProposalNotReady {
+                    id,
+                    executable_at: proposal.executable_at,
+                }
+                .into());
+            }
+            Self::remove_proposal(storage, id)
+        }
+
+        fn load_proposal(storage: &dyn Storage, id: u64) -> StdResult<Proposal> {
+            Self::PROPOSALS
+                .may_load(storage, id)?
+                .ok_or_else(|| OracleRouterError::ProposalNotFound { id }.into())
+        }
+
+        pub fn get_proposal(deps: Deps, id: u64) -> StdResult<Binary> {
+            to_binary(&Self::load_proposal(deps.storage, id)?)
+        }
+
+        /// Ids are given in increasing order, so pending proposals are found by skipping the removed ones.
+        pub fn get_proposals(
+            deps: Deps,
+            start_after: Option<u64>,
+            limit: Option<u32>,
+        ) -> StdResult<Binary> {
+            let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT).min(MAX_PAGE_LIMIT) as usize;
+            let count = Self::PROPOSAL_COUNT
+                .may_load(deps.storage)?
+                .unwrap_or_default();
+            let start = start_after.map_or(0, |start_after| start_after + 1);
+            let mut proposals = vec![];
+            for id in start..count {
+                if proposals.len() == limit {
+                    break;
+                }
+                if let Some(proposal) = Self::PROPOSALS.may_load(deps.storage, id)? {
+                    proposals.push(proposal);
+                }
+            }
+            to_binary(&proposals)
+        }
+
+        /// Applies the changes of the operation to the registry.
+        pub fn resolve_registry_operation(
+            &self,
+            deps: &mut DepsMut,
+            now: &Timestamp,
             operation: RegistryOperation,
         ) -> StdResult<()> {
+            let changes = self.plan_registry_operation(deps.as_ref(), now, &[], operation)?;
+            for change in changes {
+                Self::apply_registry_change(deps.storage, change)?;
+            }
+            Ok(())
+        }
+
+        /// Changes the operations would make to the registry if they were applied in order, without applying them.
+        pub fn dry_run_registry(
+            &self,
+            deps: Deps,
+            now: &Timestamp,
+            operations: Vec<RegistryOperation>,
+        ) -> StdResult<Binary> {
+            let mut changes = vec![];
+            for operation in operations {
+                let planned = self.plan_registry_operation(deps, now, &changes, operation)?;
+                changes.extend(planned);
+            }
+            to_binary(&changes)
+        }
+
+        /// Latest value a change still pending in the batch sets, if any.
+        fn pending<T>(
+            pending: &[RegistryChange],
+            f: impl Fn(&RegistryChange) -> Option<T>,
+        ) -> Option<T> {
+            pending.iter().rev().find_map(f)
+        }
+
+        fn current_oracle(
+            storage: &dyn Storage,
+            pending: &[RegistryChange],
+            key: &str,
+        ) -> StdResult<Option<Contract>> {
+            match Self::pending(pending, |change| match change {
+                RegistryChange::Oracle { key: k, after, .. } if k == key => Some(after.clone()),
+                _ => None,
+            }) {
+                Some(oracle) => Ok(oracle),
+                None => Oracle::may_load(storage, key),
+            }
+        }
+
+        fn current_protection(
+            storage: &dyn Storage,
+            pending: &[RegistryChange],
+            key: &str,
+        ) -> StdResult<Option<ProtectedKeyInfo>> {
+            match Self::pending(pending, |change| match change {
+                RegistryChange::Protection { key: k, after, .. } if k == key => Some(after.clone()),
+                _ => None,
+            }) {
+                Some(info) => Ok(info),
+                None => Self::PROTECTED_KEYS.may_load(storage, key),
+            }
+        }
+
+        fn current_last_good_price(
+            storage: &dyn Storage,
+            pending: &[RegistryChange],
+            key: &str,
+        ) -> StdResult<Option<LastGoodPrice>> {
+            let cleared = pending.iter().any(|change| {
+                matches!(change, RegistryChange::ClearLastGoodPrice { key: k, .. } if k == key)
+            });
+            if cleared {
+                return Ok(None);
+            }
+            Self::LAST_GOOD_PRICES.may_load(storage, key)
+        }
+
+        fn current_max_age(
+            storage: &dyn Storage,
+            pending: &[RegistryChange],
+            key: &str,
+        ) -> StdResult<Option<u64>> {
+            match Self::pending(pending, |change| match change {
+                RegistryChange::MaxAge { key: k, after, .. } if k == key => Some(*after),
+                _ => None,
+            }) {
+                Some(max_age) => Ok(max_age),
+                None => Self::MAX_AGES.may_load(storage, key),
+            }
+        }
+
+        fn current_sources(
+            storage: &dyn Storage,
+            pending: &[RegistryChange],
+            key: &str,
+        ) -> StdResult<Vec<Contract>> {
+            match Self::pending(pending, |change| match change {
+                RegistryChange::Sources { key: k, after, .. } if k == key => Some(after.clone()),
+                _ => None,
+            }) {
+                Some(sources) => Ok(sources),
+                None => Ok(Self::SOURCES.may_load(storage, key)?.unwrap_or_default()),
+            }
+        }
+
+        fn current_fallbacks(
+            storage: &dyn Storage,
+            pending: &[RegistryChange],
+            key: &str,
+        ) -> StdResult<Vec<Contract>> {
+            match Self::pending(pending, |change| match change {
+                RegistryChange::Fallbacks { key: k, after, .. } if k == key => Some(after.clone()),
+                _ => None,
+            }) {
+                Some(fallbacks) => Ok(fallbacks),
+                None => Ok(Self::FALLBACKS.may_load(storage, key)?.unwrap_or_default()),
+            }
+        }
+
+        fn current_median(
+            storage: &dyn Storage,
+            pending: &[RegistryChange],
+            key: &str,
+        ) -> StdResult<bool> {
+            match Self::pending(pending, |change| match change {
+                RegistryChange::Median { key: k, after, .. } if k == key => Some(*after),
+                _ => None,
+            }) {
+                Some(median) => Ok(median),
+                None => Self::is_median_key(storage, key),
+            }
+        }
+
+        fn current_frozen(
+            storage: &dyn Storage,
+            pending: &[RegistryChange],
+            key: &str,
+        ) -> StdResult<bool> {
+            match Self::pending(pending, |change| match change {
+                RegistryChange::Frozen { key: k, after, .. } if k == key => Some(*after),
+                _ => None,
+            }) {
+                Some(frozen) => Ok(frozen),
+                None => Ok(Self::FROZEN_KEYS
+                    .may_load(storage, key)?
+                    .unwrap_or_default()),
+            }
+        }
+
+        fn current_override(
+            storage: &dyn Storage,
+            pending: &[RegistryChange],
+            key: &str,
+        ) -> StdResult<Option<PriceOverride>> {
+            match Self::pending(pending, |change| match change {
+                RegistryChange::Override { key: k, after, .. } if k == key => Some(after.clone()),
+                _ => None,
+            }) {
+                Some(price_override) => Ok(price_override),
+                None => Self::OVERRIDES.may_load(storage, key),
+            }
+        }
+
+        fn current_routed(
+            storage: &dyn Storage,
+            pending: &[RegistryChange],
+            oracle: &Contract,
+        ) -> StdResult<bool> {
+            match Self::pending(pending, |change| match change {
+                RegistryChange::Routed {
+                    oracle: o, after, ..
+                } if o == oracle => Some(*after),
+                _ => None,
+            }) {
+                Some(routed) => Ok(routed),
+                None => Self::is_routed(storage, oracle),
+            }
+        }
+
+        /// Follows the keys the oracle reports routing through, erroring if any route loops back to a key already on it
+        /// or goes deeper than the max routing depth. Returns whether the oracle reports them at all.
+        ///
+        /// Routes end at keys served by the providers or by oracles that don't report the keys they route through.
+        fn check_routing(
+            deps: Deps,
+            pending: &[RegistryChange],
+            oracle: &Contract,
+            keys: &[String],
+        ) -> StdResult<bool> {
+            let dependencies = match query_dependencies(oracle, &deps.querier, keys) {
+                Ok(dependencies) => dependencies,
+                Err(_) => return Ok(false),
+            };
+            for (key, key_dependencies) in dependencies {
+                let mut route = vec![key];
+                Self::follow_route(deps, pending, oracle, keys, &mut route, key_dependencies)?;
+            }
+            Ok(true)
+        }
+
+        fn follow_route(
+            deps: Deps,
+            pending: &[RegistryChange],
+            oracle: &Contract,
+            keys: &[String],
+            route: &mut Vec<String>,
+            dependencies: Vec<String>,
+        ) -> StdResult<()> {
+            for dependency in dependencies {
+                let is_cycle = route.contains(&dependency);
+                route.push(dependency.clone());
+                if is_cycle {
+                    return Err(OracleRouterError::RoutingCycle {
+                        route: route.clone(),
+                    }
+                    .into());
+                }
+                if route.len() - 1 > MAX_ROUTING_DEPTH as usize {
+                    return Err(OracleRouterError::RoutingTooDeep {
+                        route: route.clone(),
+                        max: MAX_ROUTING_DEPTH,
+                    }
+                    .into());
+                }
+                // Keys being registered are routed through the oracle being registered.
+                let next = if keys.contains(&dependency) {
+                    Some(oracle.clone())
+                } else {
+                    Self::current_oracle(deps.storage, pending, &dependency)?
+                };
+                if let Some(next) = next {
+                    if next == *oracle || Self::current_routed(deps.storage, pending, &next)? {
+                        let next_dependencies =
+                            query_dependencies(&next, &deps.querier, &[dependency])
+                                .unwrap_or_default()
+                                .into_iter()
+                                .flat_map(|(_, dependencies)| dependencies)
+                                .collect();
+                        Self::follow_route(deps, pending, oracle, keys, route, next_dependencies)?;
+                    }
+                }
+                route.pop();
+            }
+            Ok(())
+        }
+
+        /// Records whether the oracle is routed if it changed.
+        fn plan_routed(
+            storage: &dyn Storage,
+            pending: &[RegistryChange],
+            oracle: &Contract,
+            routed: bool,
+            changes: &mut Vec<RegistryChange>,
+        ) -> StdResult<()> {
+            let before = Self::current_routed(storage, pending, oracle)?;
+            let planned = changes.iter().any(
+                |change| matches!(change, RegistryChange::Routed { oracle: o, .. } if o == oracle),
+            );
+            if before != routed && !planned {
+                changes.push(RegistryChange::Routed {
+                    oracle: oracle.clone(),
+                    before,
+                    after: routed,
+                });
+            }
+            Ok(())
+        }
+
+        /// Queries the new oracle of the keys, erroring if it can't price any of them or if max deviation is given and
+        /// its price deviates by more than that from the price of the currently registered source.
+        ///
+        /// Keys that can't be priced by their current source aren't compared.
+        fn validate_oracle(
+            &self,
+            deps: Deps,
+            now: &Timestamp,
+            pending: &[RegistryChange],
+            oracle: &Contract,
+            keys: &[String],
+            max_deviation: Option<Decimal256>,
+        ) -> StdResult<()> {
+            let Deps {
+                storage, querier, ..
+            } = deps;
+            let prices = Self::query_oracle_prices(&querier, oracle, keys);
+            let unsupported: Vec<String> = keys
+                .iter()
+                .zip(&prices)
+                .filter(|(_, price)| price.is_err())
+                .map(|(key, _)| key.clone())
+                .collect();
+            if !unsupported.is_empty() {
+                return Err(OracleRouterError::UnsupportedKeys {
+                    oracle: oracle.address.to_string(),
+                    keys: unsupported,
+                }
+                .into());
+            }
+            let max_deviation = match max_deviation {
+                Some(max_deviation) => max_deviation,
+                None => return Ok(()),
+            };
+            for (key, price) in keys.iter().zip(prices) {
+                let price = price.unwrap_or_default().rate;
+                let current = match Self::current_oracle(storage, pending, key)? {
+                    Some(current) => query_price(&current, &querier, key),
+                    None => self
+                        .query_provider_price(storage, &querier, key.clone(), now, 0)
+                        .map(|current| current.price),
+                };
+                let current = match current {
+                    Ok(current) => current.data.rate,
+                    Err(_) => continue,
+                };
+                let deviation = deviation(price, current);
+                if deviation > max_deviation {
+                    return Err(OracleRouterError::NewOracleDeviation {
+                        key: key.clone(),
+                        price,
+                        current,
+                        deviation,
+                        max_deviation,
+                    }
+                    .into());
+                }
+            }
+            Ok(())
+        }
+
+        /// Changes the operation would make to the registry, given the changes still pending in the batch.
+        pub fn plan_registry_operation(
+            &self,
+            deps: Deps,
+            now: &Timestamp,
+            pending: &[RegistryChange],
+            operation: RegistryOperation,
+        ) -> StdResult<Vec<RegistryChange>> {
+            let Deps { storage, api, .. } = deps;
+            let mut changes = vec![];
             match operation {
                 RegistryOperation::RemoveKeys { keys } => {
-                    let mut current_keys = Self::KEYS.load(storage)?;
-                    for key in &keys {
-                        Oracle::MAP.remove(storage, key);
+                    for key in keys {
+                        let before = Self::current_oracle(storage, pending, &key)?;
+                        if before.is_some() {
+                            changes.push(RegistryChange::Oracle {
+                                key: key.clone(),
+                                before,
+                                after: None,
+                            });
+                        }
+                        let before = Self::current_fallbacks(storage, pending, &key)?;
+                        if !before.is_empty() {
+                            changes.push(RegistryChange::Fallbacks {
+                                key,
+                                before,
+                                after: vec![],
+                            });
+                        }
                     }
-                    current_keys.retain(|k| !keys.contains(k));
-                    Self::KEYS.save(storage, &current_keys)?;
                 }
-                RegistryOperation::SetKeys { oracle, keys } => {
+                RegistryOperation::SetKeys {
+                    oracle,
+                    keys,
+                    max_deviation,
+                } => {
                     let oracle = oracle.into_valid(api)?;
-                    let mut current_keys = Self::KEYS.load(storage)?;
+                    self.validate_oracle(deps, now, pending, &oracle, &keys, max_deviation)?;
+                    let routed = Self::check_routing(deps, pending, &oracle, &keys)?;
+                    Self::plan_routed(storage, pending, &oracle, routed, &mut changes)?;
                     for key in keys {
-                        Oracle::MAP.save(storage, &key, &oracle)?;
-                        current_keys.insert(key);
+                        let before = Self::current_oracle(storage, pending, &key)?;
+                        if before.as_ref() != Some(&oracle) {
+                            changes.push(RegistryChange::Oracle {
+                                key,
+                                before,
+                                after: Some(oracle.clone()),
+                            });
+                        }
                     }
-                    Self::KEYS.save(storage, &current_keys)?;
                 }
                 RegistryOperation::SetProtection { infos } => {
-                    let mut protected_keys = Self::PROTECTED_KEYS_LIST.load(storage)?;
                     for info in infos {
-                        Self::PROTECTED_KEYS.save(storage, &info.key, &info)?;
-                        protected_keys.insert(info.key);
+                        let before = Self::current_protection(storage, pending, &info.key)?;
+                        if before.as_ref() != Some(&info) {
+                            changes.push(RegistryChange::Protection {
+                                key: info.key.clone(),
+                                before,
+                                after: Some(info),
+                            });
+                        }
                     }
-                    Self::PROTECTED_KEYS_LIST.save(storage, &protected_keys)?;
                 }
                 RegistryOperation::RemoveProtection { keys } => {
-                    let mut protected_keys = Self::PROTECTED_KEYS_LIST.load(storage)?;
-                    let mut keys_to_remove = vec![];
                     for key in keys {
+                        let before = Self::current_protection(storage, pending, &key)?;
+                        if before.is_some() {
+                            changes.push(RegistryChange::Protection {
+                                key: key.clone(),
+                                before,
+                                after: None,
+                            });
+                        }
+                        if let Some(before) = Self::current_last_good_price(storage, pending, &key)?
+                        {
+                            changes.push(RegistryChange::ClearLastGoodPrice { key, before });
+                        }
+                    }
+                }
+                RegistryOperation::SetMaxAges { max_ages } => {
+                    for (key, max_age) in max_ages {
+                        let before = Self::current_max_age(storage, pending, &key)?;
+                        if before != Some(max_age) {
+                            changes.push(RegistryChange::MaxAge {
+                                key,
+                                before,
+                                after: Some(max_age),
+                            });
+                        }
+                    }
+                }
+                RegistryOperation::RemoveMaxAges { keys } => {
+                    for key in keys {
+                        let before = Self::current_max_age(storage, pending, &key)?;
+                        if before.is_some() {
+                            changes.push(RegistryChange::MaxAge {
+                                key,
+                                before,
+                                after: None,
+                            });
+                        }
+                    }
+                }
+                RegistryOperation::SetSources { key, oracles } => {
+                    let after = oracles
+                        .into_iter()
+                        .map(|oracle| oracle.into_valid(api))
+                        .collect::<StdResult<Vec<Contract>>>()?;
+                    for oracle in &after {
+                        let routed =
+                            Self::check_routing(deps, pending, oracle, std::slice::from_ref(&key))?;
+                        Self::plan_routed(storage, pending, oracle, routed, &mut changes)?;
+                    }
+                    let before = Self::current_sources(storage, pending, &key)?;
+                    if before != after {
+                        changes.push(RegistryChange::Sources { key, before, after });
+                    }
+                }
+                RegistryOperation::RemoveSources { keys } => {
+                    for key in keys {
+                        let before = Self::current_sources(storage, pending, &key)?;
+                        if !before.is_empty() {
+                            changes.push(RegistryChange::Sources {
+                                key,
+                                before,
+                                after: vec![],
+                            });
+                        }
+                    }
+                }
+                RegistryOperation::SetFallbacks { key, oracles } => {
+                    let after = oracles
+                        .into_iter()
+                        .map(|oracle| oracle.into_valid(api))
+                        .collect::<StdResult<Vec<Contract>>>()?;
+                    for oracle in &after {
+                        self.validate_oracle(
+                            deps,
+                            now,
+                            pending,
+                            oracle,
+                            std::slice::from_ref(&key),
+                            None,
+                        )?;
+                        let routed =
+                            Self::check_routing(deps, pending, oracle, std::slice::from_ref(&key))?;
+                        Self::plan_routed(storage, pending, oracle, routed, &mut changes)?;
+                    }
+                    let before = Self::current_fallbacks(storage, pending, &key)?;
+                    if before != after {
+                        changes.push(RegistryChange::Fallbacks { key, before, after });
+                    }
+                }
+                RegistryOperation::RemoveFallbacks { keys } => {
+                    for key in keys {
+                        let before = Self::current_fallbacks(storage, pending, &key)?;
+                        if !before.is_empty() {
+                            changes.push(RegistryChange::Fallbacks {
+                                key,
+                                before,
+                                after: vec![],
+                            });
+                        }
+                    }
+                }
+                RegistryOperation::SetMedianKeys { keys } => {
+                    for key in keys {
+                        if !Self::current_median(storage, pending, &key)? {
+                            changes.push(RegistryChange::Median {
+                                key,
+                                before: false,
+                                after: true,
+                            });
+                        }
+                    }
+                }
+                RegistryOperation::RemoveMedianKeys { keys } => {
+                    for key in keys {
+                        if Self::current_median(storage, pending, &key)? {
+                            changes.push(RegistryChange::Median {
+                                key,
+                                before: true,
+                                after: false,
+                            });
+                        }
+                    }
+                }
+                RegistryOperation::ResetCircuitBreaker { key, price } => {
+                    let before = match Self::current_protection(storage, pending, &key)? {
+                        Some(before) => before,
+                        None => {
+                            return Err(StdError::generic_err(format!(
+                                "Can't update price for unprotected key {key}.",
+                            )))
+                        }
+                    };
+                    if before.price != price {
+                        let mut after = before.clone();
+                        after.price = price;
+                        changes.push(RegistryChange::Protection {
+                            key: key.clone(),
+                            before: Some(before),
+                            after: Some(after),
+                        });
+                    }
+                    if let Some(before) = Self::current_last_good_price(storage, pending, &key)? {
+                        changes.push(RegistryChange::ClearLastGoodPrice { key, before });
+                    }
+                }
+                RegistryOperation::FreezeKeys { keys } => {
+                    for key in keys {
+                        if !Self::current_frozen(storage, pending, &key)? {
+                            changes.push(RegistryChange::Frozen {
+                                key,
+                                before: false,
+                                after: true,
+                            });
+                        }
+                    }
+                }
+                RegistryOperation::UnfreezeKeys { keys } => {
+                    for key in keys {
+                        if Self::current_frozen(storage, pending, &key)? {
+                            changes.push(RegistryChange::Frozen {
+                                key,
+                                before: true,
+                                after: false,
+                            });
+                        }
+                    }
+                }
+                RegistryOperation::SetOverride {
+                    key,
+                    price,
+                    expires_at,
+                    reason,
+                    force,
+                } => {
+                    if expires_at <= now.seconds() {
+                        return Err(OracleRouterError::ExpiredOverride { key, expires_at }.into());
+                    }
+                    if !force {
+                        let max_expires_at = now.seconds().saturating_add(MAX_OVERRIDE_DURATION);
+                        if expires_at > max_expires_at {
+                            return Err(OracleRouterError::OverrideTooLong {
+                                key,
+                                expires_at,
+                                max_expires_at,
+                            }
+                            .into());
+                        }
+                        match Self::current_protection(storage, pending, &key)? {
+                            Some(info) => info.check_deviation(price)?,
+                            None => {
+                                return Err(OracleRouterError::UnprotectedOverride { key }.into())
+                            }
+                        }
+                    }
+                    let before = Self::current_override(storage, pending, &key)?;
+                    let after = PriceOverride {
+                        key: key.clone(),
+                        price,
+                        expires_at,
+                        reason,
+                        forced: force,
+                        set_at: now.seconds(),
+                    };
+                    changes.push(RegistryChange::Override {
+                        key,
+                        before,
+                        after: Some(after),
+                    });
+                }
+                RegistryOperation::RemoveOverrides { keys } => {
+                    for key in keys {
+                        let before = Self::current_override(storage, pending, &key)?;
+                        if before.is_some() {
+                            changes.push(RegistryChange::Override {
+                                key,
+                                before,
+                                after: None,
+                            });
+                        }
+                    }
+                }
+            }
+            Ok(changes)
+        }
+
+        pub fn apply_registry_change(
+            storage: &mut dyn Storage,
+            change: RegistryChange,
+        ) -> StdResult<()> {
+            match change {
+                RegistryChange::Oracle { key, after, .. } => {
+                    Self::unregister_key(storage, &key)?;
+                    if let Some(oracle) = after {
+                        Oracle::MAP.save(storage, &key, &oracle)?;
+                        Self::ORACLE_KEYS.insert(storage, oracle.address.as_str(), &key)?;
+                        Self::KEYS.insert(storage, "", &key)?;
+                    }
+                }
+                RegistryChange::Protection { key, after, .. } => match after {
+                    Some(info) => {
+                        Self::PROTECTED_KEYS.save(storage, &key, &info)?;
+                        Self::PROTECTED_KEYS_LIST.insert(storage, "", &key)?;
+                    }
+                    None => {
                         Self::PROTECTED_KEYS.remove(storage, &key);
-                        keys_to_remove.push(key);
+                        Self::PROTECTED_KEYS_LIST.remove(storage, "", &key)?;
+                    }
+                },
+                RegistryChange::ClearLastGoodPrice { key, .. } => {
+                    Self::LAST_GOOD_PRICES.remove(storage, &key);
+                }
+                RegistryChange::MaxAge { key, after, .. } => match after {
+                    Some(max_age) => Self::MAX_AGES.save(storage, &key, &max_age)?,
+                    None => Self::MAX_AGES.remove(storage, &key),
+                },
+                RegistryChange::Sources { key, after, .. } => {
+                    if after.is_empty() {
+                        Self::SOURCES.remove(storage, &key);
+                    } else {
+                        Self::SOURCES.save(storage, &key, &after)?;
+                    }
+                }
+                RegistryChange::Fallbacks { key, after, .. } => {
+                    if after.is_empty() {
+                        Self::FALLBACKS.remove(storage, &key);
+                    } else {
+                        Self::FALLBACKS.save(storage, &key, &after)?;
+                    }
+                }
+                RegistryChange::Median { key, after, .. } => {
+                    if after {
+                        Self::MEDIAN_KEYS.save(storage, &key, &true)?;
+                    } else {
+                        Self::MEDIAN_KEYS.remove(storage, &key);
+                    }
+                }
+                RegistryChange::Frozen { key, after, .. } => {
+                    if after {
+                        Self::FROZEN_KEYS.save(storage, &key, &true)?;
+                    } else {
+                        Self::FROZEN_KEYS.remove(storage, &key);
+                    }
+                }
+                RegistryChange::Override { key, after, .. } => match after {
+                    Some(price_override) => {
+                        Self::OVERRIDES.save(storage, &key, &price_override)?;
+                        Self::OVERRIDE_KEYS_LIST.insert(storage, "", &key)?;
+                    }
+                    None => {
+                        Self::OVERRIDES.remove(storage, &key);
+                        Self::OVERRIDE_KEYS_LIST.remove(storage, "", &key)?;
+                    }
+                },
+                RegistryChange::Routed { oracle, after, .. } => {
+                    if after {
+                        Self::ROUTED_ORACLES.save(storage, oracle.address.as_str(), &true)?;
+                    } else {
+                        Self::ROUTED_ORACLES.remove(storage, oracle.address.as_str());
                     }
-                    protected_keys.retain(|k| !keys_to_remove.contains(k));
-                    Self::PROTECTED_KEYS_LIST.save(storage, &protected_keys)?;
                 }
             }
             Ok(())
@@ -201,6 +1490,21 @@ mod state {
             }
         }
 
+        /// The oracle of the key followed by the oracles it falls back to.
+        pub fn get_oracle_chain(
+            &self,
+            storage: &dyn Storage,
+            key: &str,
+        ) -> StdResult<Vec<Contract>> {
+            let mut chain = vec![self.get_oracle(storage, key)?];
+            chain.extend(Self::FALLBACKS.may_load(storage, key)?.unwrap_or_default());
+            Ok(chain)
+        }
+
+        pub fn has_fallbacks(storage: &dyn Storage, key: &str) -> StdResult<bool> {
+            Ok(Self::FALLBACKS.may_load(storage, key)?.is_some())
+        }
+
         pub fn group_keys_by_oracle(
             &self,
             storage: &dyn Storage,
@@ -226,51 +1530,257 @@ mod state {
             if let Some(admin_auth) = config.admin_auth {
                 new_config.admin_auth = admin_auth.into_valid(api)?;
             }
-            if let Some(provider) = config.provider {
-                new_config.provider = provider.into_valid(api)?;
+            if let Some(providers) = config.providers {
+                new_config.providers = providers
+                    .into_iter()
+                    .map(|provider| provider.into_valid(api))
+                    .collect::<StdResult<Vec<Provider>>>()?;
+            }
+            if let Some(aggregation) = config.aggregation {
+                aggregation.validate()?;
+                new_config.aggregation = aggregation;
             }
             new_config.quote_symbol = config.quote_symbol.unwrap_or(new_config.quote_symbol);
+            if let Some(max_age) = config.max_age {
+                new_config.max_age = max_age;
+            }
+            new_config.timelock = config.timelock.unwrap_or(new_config.timelock);
             self.config = new_config;
             Ok(self)
         }
 
+        pub fn is_median_key(storage: &dyn Storage, key: &str) -> StdResult<bool> {
+            Ok(Self::MEDIAN_KEYS
+                .may_load(storage, key)?
+                .unwrap_or_default())
+        }
+
+        pub fn get_sources(&self, deps: Deps, key: String) -> StdResult<Binary> {
+            let oracles = Self::SOURCES
+                .may_load(deps.storage, &key)?
+                .unwrap_or_default();
+            let median = Self::is_median_key(deps.storage, &key)?;
+            to_binary(&SourcesResponse {
+                key,
+                providers: self.config.providers.clone(),
+                oracles,
+                median,
+            })
+        }
+
+        /// Queries the price of the key from the oracle, one level deeper than the routing depth if the oracle is routed.
+        fn query_oracle_price(
+            storage: &dyn Storage,
+            querier: &QuerierWrapper,
+            oracle: &Contract,
+            key: &str,
+            depth: u8,
+        ) -> StdResult<ReferenceData> {
+            if Self::is_routed(storage, oracle)? {
+                query_routed_prices(oracle, querier, &[key.to_string()], depth + 1)?
+                    .pop()
+                    .map(|price| price.data)
+                    .ok_or_else(|| {
+                        StdError::generic_err(format!("Oracle didn't return a price for {key}."))
+                    })
+            } else {
+                query_price(oracle, querier, key).map(|price| price.data)
+            }
+        }
+
+        /// Queries the prices of the keys from the oracle, querying them one by one if the bulk query fails.
+        fn query_oracle_prices(
+            storage: &dyn Storage,
+            querier: &QuerierWrapper,
+            oracle: &Contract,
+            keys: &[String],
+            depth: u8,
+        ) -> Vec<StdResult<ReferenceData>> {
+            let prices = match Self::is_routed(storage, oracle) {
+                Ok(true) => query_routed_prices(oracle, querier, keys, depth + 1),
+                Ok(false) => query_prices(oracle, querier, keys),
+                Err(err) => Err(err),
+            };
+            match prices {
+                Ok(prices) if prices.len() == keys.len() => {
+                    prices.into_iter().map(|price| Ok(price.data)).collect()
+                }
+                _ if keys.len() > 1 => keys
+                    .iter()
+                    .map(|key| Self::query_oracle_price(storage, querier, oracle, key, depth))
+                    .collect(),
+                Ok(_) => vec![Err(StdError::generic_err(
+                    "Bulk query returned the wrong amount of prices.",
+                ))],
+                Err(err) => vec![Err(err)],
+            }
+        }
+
+        /// Describes why the oracle couldn't be used.
+        fn oracle_error(oracle: &Contract, reason: impl std::fmt::Display) -> String {
+            format!("{}: {reason}", oracle.address)
+        }
+
+        /// Queries the providers and extra sources of the key, aggregating the healthy data.
+        ///
+        /// When failing over, sources are queried in order until one of them answers with healthy data.
+        /// Routed sources are queried one level deeper than the depth the router was queried at.
         pub fn query_provider_price(
             &self,
+            storage: &dyn Storage,
             querier: &QuerierWrapper,
             key: String,
-        ) -> StdResult<PriceResponse> {
+            now: &Timestamp,
+            depth: u8,
+        ) -> StdResult<SourcedPrice> {
             let Config {
                 quote_symbol,
-                provider,
+                providers,
+                aggregation,
                 ..
             } = &self.config;
+            let max_age = self.get_max_age(storage, &key)?;
+            let is_healthy =
+                |data: &ReferenceData| !Self::exceeds_max_age(max_age, data, now).unwrap_or(true);
             let symbol_pair = (key.as_str(), quote_symbol.as_str());
-            let resp = provider.reference_data(querier, symbol_pair)?;
-            Ok(OraclePrice::new(key, resp))
+            let median = Self::is_median_key(storage, &key)?;
+            let oracles = Self::SOURCES.may_load(storage, &key)?.unwrap_or_default();
+
+            let mut data = vec![];
+            let mut errors = vec![];
+            let chains: Vec<&[Provider]> = if aggregation.is_failover() {
+                vec![providers.as_slice()]
+            } else {
+                providers.iter().map(std::slice::from_ref).collect()
+            };
+            for chain in chains {
+                match Provider::reference_data_with_failover(
+                    chain,
+                    querier,
+                    symbol_pair,
+                    median,
+                    &is_healthy,
+                ) {
+                    Ok((provider, resp)) => data.push((PriceSource::Provider(provider), resp)),
+                    Err(provider_errors) => errors.extend(provider_errors),
+                }
+            }
+            for oracle in oracles {
+                if aggregation.is_failover() && !data.is_empty() {
+                    break;
+                }
+                match Self::query_oracle_price(storage, querier, &oracle, &key, depth) {
+                    Ok(price) if is_healthy(&price) => {
+                        data.push((PriceSource::Oracle(oracle), price))
+                    }
+                    Ok(_) => errors.push(Self::oracle_error(&oracle, STALE_DATA)),
+                    Err(err) => errors.push(Self::oracle_error(&oracle, err)),
+                }
+            }
+            aggregation.aggregate(key, data, errors)
         }
 
+        /// Bulk version of `query_provider_price`, where each key fails over independently.
         pub fn query_provider_prices(
             &self,
+            storage: &dyn Storage,
             querier: &QuerierWrapper,
             keys: Vec<String>,
-        ) -> StdResult<PricesResponse> {
+            now: &Timestamp,
+            depth: u8,
+        ) -> StdResult<Vec<SourcedPrice>> {
             let Config {
                 quote_symbol,
-                provider,
+                providers,
+                aggregation,
                 ..
             } = &self.config;
-            let mut prices: Vec<OraclePrice> = vec![];
-            let symbol_pairs: Vec<(String, String)> = keys
-                .iter()
-                .map(|key| (key.to_string(), quote_symbol.to_string()))
-                .collect();
+            let mut max_ages = vec![];
+            let mut medians = vec![];
+            let mut oracles = vec![];
+            for key in &keys {
+                max_ages.push(self.get_max_age(storage, key)?);
+                medians.push(Self::is_median_key(storage, key)?);
+                oracles.push(Self::SOURCES.may_load(storage, key)?.unwrap_or_default());
+            }
+            let is_healthy = |index: usize, data: &ReferenceData| {
+                !Self::exceeds_max_age(max_ages[index], data, now).unwrap_or(true)
+            };
 
-            let data = provider.reference_data_bulk(querier, symbol_pairs)?;
+            // Healthy data reported for each key, in order of source priority.
+            let mut data: Vec<Vec<(PriceSource, ReferenceData)>> = vec![vec![]; keys.len()];
+            // Reasons the other sources of each key couldn't be used.
+            let mut errors: Vec<Vec<String>> = vec![vec![]; keys.len()];
+            let chains: Vec<&[Provider]> = if aggregation.is_failover() {
+                vec![providers.as_slice()]
+            } else {
+                providers.iter().map(std::slice::from_ref).collect()
+            };
+            // Latest and median reference data are queried separately.
+            for median in [false, true] {
+                let indexes: Vec<usize> = (0..keys.len())
+                    .filter(|index| medians[*index] == median)
+                    .collect();
+                if indexes.is_empty() {
+                    continue;
+                }
+                let symbol_pairs: Vec<(String, String)> = indexes
+                    .iter()
+                    .map(|index| (keys[*index].to_string(), quote_symbol.to_string()))
+                    .collect();
+                for chain in &chains {
+                    let resps = Provider::reference_data_bulk_with_failover(
+                        chain,
+                        querier,
+                        &symbol_pairs,
+                        median,
+                        |i, resp| is_healthy(indexes[i], resp),
+                    );
+                    for (i, resp) in resps.into_iter().enumerate() {
+                        match resp {
+                            Ok((provider, resp)) => {
+                                data[indexes[i]].push((PriceSource::Provider(provider), resp))
+                            }
+                            Err(provider_errors) => errors[indexes[i]].extend(provider_errors),
+                        }
+                    }
+                }
+            }
 
-            for (index, key) in keys.iter().enumerate() {
-                prices.push(OraclePrice::new(key.to_string(), data[index].clone()));
+            // Extra sources are queried by priority, grouping the keys by oracle at each step.
+            let steps = oracles.iter().map(|o| o.len()).max().unwrap_or_default();
+            for step in 0..steps {
+                let mut grouped: HashMap<Contract, Vec<usize>> = HashMap::new();
+                for (index, key_oracles) in oracles.iter().enumerate() {
+                    if aggregation.is_failover() && !data[index].is_empty() {
+                        continue;
+                    }
+                    if let Some(oracle) = key_oracles.get(step) {
+                        grouped.entry(oracle.clone()).or_insert(vec![]).push(index);
+                    }
+                }
+                for (oracle, indexes) in grouped {
+                    let oracle_keys: Vec<String> =
+                        indexes.iter().map(|index| keys[*index].clone()).collect();
+                    let resps =
+                        Self::query_oracle_prices(storage, querier, &oracle, &oracle_keys, depth);
+                    for (index, resp) in indexes.into_iter().zip(resps) {
+                        match resp {
+                            Ok(resp) if is_healthy(index, &resp) => {
+                                data[index].push((PriceSource::Oracle(oracle.clone()), resp))
+                            }
+                            Ok(_) => errors[index].push(Self::oracle_error(&oracle, STALE_DATA)),
+                            Err(err) => errors[index].push(Self::oracle_error(&oracle, err)),
+                        }
+                    }
+                }
             }
-            Ok(prices)
+
+            keys.into_iter()
+                .zip(data)
+                .zip(errors)
+                .map(|((key, data), errors)| aggregation.aggregate(key, data, errors))
+                .collect()
         }
     }
 }
@@ -0,0 +1,306 @@
+//! TWAP oracles serve the time-weighted average of the prices observed for a key
+//! over a configurable window, so a single block can't move the price.
+//!
+//! Observations are stored in a ring buffer per key and are either pushed by a keeper
+//! or pulled from the router.
+
+use super::*;
+
+pub mod msg {
+    use crate::interfaces::common::{config::CommonConfigResponse, PriceResponse, PricesResponse};
+
+    use super::*;
+
+    impl_msg_callbacks!();
+
+    #[cw_serde]
+    pub struct InstantiateMsg {
+        pub router: RawContract,
+    }
+
+    #[cw_serde]
+    pub enum ExecuteMsg {
+        /// For setting and updating TWAP configs.
+        /// Observations are cleared if the source key or max observations of a TWAP changes.
+        SetTwaps(Vec<TwapConfig>),
+        RemoveTwaps(Vec<String>),
+        /// Records the prices as the latest observations of the TWAPs.
+        PushObservations(Vec<(String, Uint256)>),
+        /// Records the router prices of the source keys as the latest observations of the TWAPs.
+        /// Callable by anyone, but only once the min spacing of each TWAP has passed since its latest observation.
+        PullObservations(Vec<String>),
+        UpdateConfig(RawContract),
+        SetStatus(bool),
+    }
+
+    #[cw_serde]
+    #[derive(QueryResponses)]
+    pub enum QueryMsg {
+        #[returns(PriceResponse)]
+        GetPrice { key: String },
+        #[returns(PricesResponse)]
+        GetPrices { keys: Vec<String> },
+        #[returns(CommonConfigResponse)]
+        GetConfig {},
+        #[returns(TwapsResponse)]
+        GetTwaps {},
+        #[returns(ObservationsResponse)]
+        GetObservations { key: String },
+    }
+
+    pub type TwapsResponse = Vec<TwapData>;
+    /// Ordered from oldest to latest.
+    pub type ObservationsResponse = Vec<Observation>;
+
+    #[cw_serde]
+    pub struct TwapConfig {
+        /// The key the TWAP is served under, i.e. `SHD (TWAP 1h)`.
+        pub key: String,
+        /// The router key observations are pulled from.
+        pub source_key: String,
+        /// Length of the averaging window in seconds.
+        pub window: u64,
+        /// Minimum amount of observations inside the window needed to serve a price.
+        pub min_observations: u32,
+        /// Size of the ring buffer, the oldest observation is overwritten once it is full.
+        pub max_observations: u32,
+    }
+
+    #[cw_serde]
+    pub struct TwapData {
+        pub config: TwapConfig,
+        /// Index of the ring buffer the next observation is written to.
+        pub next_index: u32,
+        /// Amount of observations stored.
+        pub len: u32,
+    }
+
+    #[cw_serde]
+    pub struct Observation {
+        pub rate: Uint256,
+        pub timestamp: u64,
+    }
+
+    #[cw_serde]
+    pub enum BotPermission {
+        PushObservations,
+    }
+
+    impl ToString for BotPermission {
+        fn to_string(&self) -> String {
+            match self {
+                BotPermission::PushObservations => "SHADE_ORACLES_TWAP_BOT".to_string(),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "twap")]
+pub use msg::*;
+#[cfg(feature = "twap")]
+pub use state::*;
+#[cfg(feature = "twap")]
+mod state {
+    use super::*;
+    use crate::interfaces::{common::config::CommonConfig, providers::ReferenceData};
+    use ssp::{Item, ItemStorage, Map};
+
+    impl TwapConfig {
+        pub fn validate(&self) -> StdResult<()> {
+            if self.window == 0 {
+                return Err(StdError::generic_err(format!(
+                    "TWAP window of {} must be greater than 0.",
+                    self.key
+                )));
+            }
+            if self.min_observations == 0 || self.min_observations > self.max_observations {
+                return Err(StdError::generic_err(format!(
+                    "TWAP of {} needs between 1 and {} min observations.",
+                    self.key, self.max_observations
+                )));
+            }
+            Ok(())
+        }
+
+        /// Seconds pulled observations have to be apart, so the ring buffer always spans the window.
+        pub fn min_spacing(&self) -> u64 {
+            (self.window / self.max_observations as u64).max(1)
+        }
+    }
+
+    impl TwapData {
+        /// Averages the observations over the window, each rate being weighted by how long it was the latest one.
+        ///
+        /// The timestamps of the result are those of the latest observation.
+        pub fn twap(&self, observations: &[Observation], now: u64) -> StdResult<ReferenceData> {
+            let TwapConfig {
+                key,
+                window,
+                min_observations,
+                ..
+            } = &self.config;
+            let start = now.saturating_sub(*window);
+            let available = observations.iter().filter(|o| o.timestamp >= start).count();
+            let latest = match observations.last() {
+                Some(latest) if available >= *min_observations as usize => latest,
+                _ => {
+                    return Err(StdError::generic_err(format!(
+                        "Only {available} observations of {key} in the last {window} seconds but at least {min_observations} are required."
+                    )))
+                }
+            };
+
+            let mut weighted_total = Uint256::zero();
+            let mut total_time = 0u64;
+            for (i, observation) in observations.iter().enumerate() {
+                let from = observation.timestamp.max(start);
+                let to = observations.get(i + 1).map_or(now, |next| next.timestamp);
+                if to <= from {
+                    continue;
+                }
+                weighted_total += observation.rate * Uint256::from(to - from);
+                total_time += to - from;
+            }
+            let rate = if total_time == 0 {
+                latest.rate
+            } else {
+                weighted_total / Uint256::from(total_time)
+            };
+            Ok(ReferenceData::new(rate, latest.timestamp, latest.timestamp))
+        }
+    }
+
+    #[cw_serde]
+    pub struct TwapOracle {
+        pub config: CommonConfig,
+    }
+
+    impl ItemStorage for TwapOracle {
+        const ITEM: Item<'static, Self> = Item::new("twap_oracle");
+    }
+
+    impl<'a> TwapOracle {
+        // Keyed by the TWAP key.
+        pub const TWAPS: Map<'static, &'a str, TwapData> = Map::new("twaps");
+        // Keyed by the TWAP key and index in its ring buffer.
+        pub const OBSERVATIONS: Map<'static, (&'a str, u32), Observation> =
+            Map::new("twap_observations");
+    }
+
+    impl TwapOracle {
+        pub fn set_twaps(storage: &mut dyn Storage, twaps: Vec<TwapConfig>) -> StdResult<()> {
+            for config in twaps {
+                config.validate()?;
+                let data = match Self::TWAPS.may_load(storage, &config.key)? {
+                    Some(mut data)
+                        if data.config.source_key == config.source_key
+                            && data.config.max_observations == config.max_observations =>
+                    {
+                        data.config = config;
+                        data
+                    }
+                    Some(data) => {
+                        Self::clear_observations(storage, &data);
+                        TwapData {
+                            config,
+                            next_index: 0,
+                            len: 0,
+                        }
+                    }
+                    None => {
+                        CommonConfig::add_supported_key(storage, &config.key)?;
+                        TwapData {
+                            config,
+                            next_index: 0,
+                            len: 0,
+                        }
+                    }
+                };
+                Self::TWAPS.save(storage, &data.config.key, &data)?;
+            }
+            Ok(())
+        }
+
+        pub fn remove_keys(storage: &mut dyn Storage, keys: Vec<String>) -> StdResult<()> {
+            let mut supported_keys = CommonConfig::SUPPORTED_KEYS.load(storage)?;
+            for key in keys {
+                if supported_keys.remove(&key) {
+                    let data = Self::TWAPS.load(storage, &key)?;
+                    Self::clear_observations(storage, &data);
+                    Self::TWAPS.remove(storage, &key);
+                }
+            }
+            CommonConfig::SUPPORTED_KEYS.save(storage, &supported_keys)?;
+            Ok(())
+        }
+
+        fn clear_observations(storage: &mut dyn Storage, data: &TwapData) {
+            for index in 0..data.config.max_observations {
+                Self::OBSERVATIONS.remove(storage, (data.config.key.as_str(), index));
+            }
+        }
+
+        pub fn load_twap(storage: &dyn Storage, key: &String) -> StdResult<TwapData> {
+            CommonConfig::require_supported_key(storage, key)?;
+            Self::TWAPS.load(storage, key)
+        }
+
+        /// Records the rate as the latest observation of the TWAP.
+        ///
+        /// Errors unless at least `min_spacing` seconds (and never less than one) have passed since the latest observation,
+        /// so an observation can't be replaced within the same block.
+        pub fn record_observation(
+            storage: &mut dyn Storage,
+            key: &String,
+            rate: Uint256,
+            now: u64,
+            min_spacing: u64,
+        ) -> StdResult<()> {
+            let mut data = Self::load_twap(storage, key)?;
+            let capacity = data.config.max_observations;
+            let latest_index = (data.next_index + capacity - 1) % capacity;
+            if data.len > 0 {
+                let latest = Self::OBSERVATIONS.load(storage, (key.as_str(), latest_index))?;
+                let next = latest.timestamp.saturating_add(min_spacing.max(1));
+                if now < next {
+                    return Err(StdError::generic_err(format!(
+                        "Next observation of {key} can't be recorded before {next}."
+                    )));
+                }
+            }
+            let observation = Observation {
+                rate,
+                timestamp: now,
+            };
+            Self::OBSERVATIONS.save(storage, (key.as_str(), data.next_index), &observation)?;
+            data.next_index = (data.next_index + 1) % capacity;
+            data.len = (data.len + 1).min(capacity);
+            Self::TWAPS.save(storage, key, &data)?;
+            Ok(())
+        }
+
+        pub fn get_observations(
+            storage: &dyn Storage,
+            data: &TwapData,
+        ) -> StdResult<ObservationsResponse> {
+            let capacity = data.config.max_observations;
+            let oldest_index = (data.next_index + capacity - data.len) % capacity;
+            let mut observations = vec![];
+            for i in 0..data.len {
+                let index = (oldest_index + i) % capacity;
+                observations
+                    .push(Self::OBSERVATIONS.load(storage, (data.config.key.as_str(), index))?);
+            }
+            Ok(observations)
+        }
+
+        pub fn get_twaps(storage: &dyn Storage) -> StdResult<TwapsResponse> {
+            let keys = CommonConfig::SUPPORTED_KEYS.load(storage)?;
+            let mut twaps = vec![];
+            for key in keys {
+                twaps.push(Self::TWAPS.load(storage, &key)?);
+            }
+            Ok(twaps)
+        }
+    }
+}
@@ -2,8 +2,6 @@ use cosmwasm_std::Decimal256;
 use schemars::JsonSchema;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::asset::Asset;
-
 use super::*;
 pub struct ShadeSwapQuerier;
 
@@ -69,24 +67,56 @@ impl ShadeSwapQuerier {
             _ => Err(StdError::generic_err("Unexpected response")),
         }
     }
+    /// Simulates the swap on a stableswap pair, which requires the oracle key it registered for the offered token.
     pub fn query_stableswap_simulation(
         querier: &QuerierWrapper,
         pair: &Contract,
+        token: &Contract,
+        oracle_key: String,
         amount: Uint128,
-        token: &Asset,
     ) -> StdResult<SwapSimulationResponse> {
-        // Stableswap assets are registered with an oracle key in the ShadeSwap pair.
         let token = TokenType::CustomToken {
-            contract_addr: token.contract.address.clone(),
-            token_code_hash: token.contract.code_hash.clone(),
-            oracle_key: Some(token.quote_symbol.clone()),
+            contract_addr: token.address.clone(),
+            token_code_hash: token.code_hash.clone(),
+            oracle_key: Some(oracle_key),
         };
         let offer = TokenAmount { token, amount };
-        QueryMsg::SwapSimulation {
+        let resp: ShadeSwapQueryMsgResponse = QueryMsg::SwapSimulation {
             offer,
             exclude_fee: Some(true),
         }
-        .query(querier, pair)
+        .query(querier, pair)?;
+        match resp {
+            ShadeSwapQueryMsgResponse::SwapSimulation {
+                total_fee_amount,
+                lp_fee_amount,
+                shade_dao_fee_amount,
+                result,
+                price,
+            } => Ok(SwapSimulationResponse {
+                total_fee_amount,
+                lp_fee_amount,
+                shade_dao_fee_amount,
+                result,
+                price,
+            }),
+            _ => Err(StdError::generic_err("Unexpected response")),
+        }
+    }
+    /// Simulates the swap with the stableswap simulation if the pair is a stableswap pair.
+    pub fn simulate_swap(
+        querier: &QuerierWrapper,
+        pair: &Contract,
+        pair_info: &PairInfo,
+        token: &Contract,
+        amount: Uint128,
+    ) -> StdResult<SwapSimulationResponse> {
+        if pair_info.is_stableswap() {
+            let oracle_key = pair_info.stable_oracle_key(&token.address)?;
+            Self::query_stableswap_simulation(querier, pair, token, oracle_key, amount)
+        } else {
+            Self::query_swap_simulation(querier, pair, token, amount)
+        }
     }
 }
 
@@ -120,6 +150,23 @@ impl PairInfo {
     pub fn is_stableswap(&self) -> bool {
         self.pair.2
     }
+    /// Gets the oracle key the stableswap pair uses for the token.
+    pub fn stable_oracle_key(&self, token: &Addr) -> StdResult<String> {
+        let stable_info = self
+            .stable_info
+            .as_ref()
+            .ok_or_else(|| StdError::generic_err("Pair is not a stableswap pair."))?;
+        if self.pair.0.eq_address(token) {
+            Ok(stable_info.stable_token0_data.oracle_key.clone())
+        } else if self.pair.1.eq_address(token) {
+            Ok(stable_info.stable_token1_data.oracle_key.clone())
+        } else {
+            Err(StdError::generic_err(format!(
+                "Token {} is not in pair.",
+                token
+            )))
+        }
+    }
 }
 
 #[cw_serde]